@@ -24,15 +24,21 @@
 //!   [+10] suppression - Suppression (0.0-1.0)
 //!   [+11] is_alive    - 1.0=alive, 0.0=dead
 //!   [+12] is_routing  - 1.0=routing
-//!   [+13] order_type  - 0=Hold, 1=Move, 2=Attack, 3=Retreat
+//!   [+13] order_type  - 0=Hold, 1=Move, 2=Attack, 3=Retreat, 4=Suppress,
+//!                       5=Defend, 6=Patrol, 7=Garrison, 8=Ungarrison
 //! ```
 //!
 //! See `sim/src/godot_bridge.rs` for the authoritative format documentation.
 
 use godot::prelude::*;
-use godot::builtin::{PackedFloat32Array, Dictionary};
-use tbg_sim::SimWorld;
-use tbg_sim::godot_bridge::{snapshot_to_flatbuffer, BattleSummary, SQUAD_STRIDE, HEADER_SIZE};
+use godot::builtin::{PackedFloat32Array, PackedInt32Array, Dictionary};
+use tbg_sim::{OrderResult, SimError, SimWorld};
+use tbg_sim::components::Order;
+use tbg_sim::godot_bridge::{
+    command_level_from_id, fire_priority_from_id, fire_stance_from_id, formation_from_id, id_from_ffi, order_result_to_id,
+    order_trigger_from_id, snapshot_to_flatbuffer, unit_class_from_id, BattleSummary, FIRE_STANCE_AI, HEADER_SIZE, ORDER_RESULT_NO_WORLD,
+    SQUAD_STRIDE,
+};
 use tbg_sim::systems::{SimConfig, SimRate};
 
 /// Bridge class exposing the Rust simulation to Godot.
@@ -52,12 +58,14 @@ use tbg_sim::systems::{SimConfig, SimRate};
 pub struct SimWorldBridge {
     base: Base<RefCounted>,
     sim: Option<SimWorld>,
+    /// Why the last rejected order was rejected.
+    last_order_error: String,
 }
 
 #[godot_api]
 impl IRefCounted for SimWorldBridge {
     fn init(base: Base<RefCounted>) -> Self {
-        Self { base, sim: None }
+        Self { base, sim: None, last_order_error: String::new() }
     }
 }
 
@@ -112,38 +120,295 @@ impl SimWorldBridge {
         }
     }
 
-    /// Issue a move order to a squad.
+    /// Start recording attributed fire (tracers, muzzle flashes, stats).
     #[func]
-    fn order_move(&mut self, squad_id: i32, target_x: f32, target_y: f32) {
+    fn enable_combat_log(&mut self, capacity: i32) {
         if let Some(ref mut sim) = self.sim {
-            sim.order_move(squad_id as u32, target_x, target_y);
+            sim.enable_combat_log(capacity.max(0) as usize);
         }
     }
 
-    /// Issue an attack-move order to a squad.
+    /// Take the combat log entries recorded since the last call, as JSON.
     #[func]
-    fn order_attack_move(&mut self, squad_id: i32, target_x: f32, target_y: f32) {
-        if let Some(ref mut sim) = self.sim {
-            sim.order_attack_move(squad_id as u32, target_x, target_y);
+    fn drain_combat_log_json(&mut self) -> GString {
+        match &mut self.sim {
+            Some(sim) => GString::from(sim.drain_combat_log_json().as_str()),
+            None => GString::from("[]"),
         }
     }
 
+    /// Why the last order was rejected, for showing to the player. Empty if
+    /// it was accepted.
+    ///
+    /// Order methods return an `ORDER_RESULT_*` code (see godot_bridge);
+    /// `ORDER_RESULT_OK` (0) means the order was accepted.
+    #[func]
+    fn get_last_order_error(&self) -> GString {
+        GString::from(self.last_order_error.as_str())
+    }
+
+    /// Issue a move order to a squad.
+    #[func]
+    fn order_move(&mut self, squad_id: i32, target_x: f32, target_y: f32) -> i32 {
+        self.issue(|sim| sim.order_move(id_from_ffi(squad_id)?, target_x, target_y))
+    }
+
+    /// Issue an attack-move order to a squad.
+    #[func]
+    fn order_attack_move(&mut self, squad_id: i32, target_x: f32, target_y: f32) -> i32 {
+        self.issue(|sim| sim.order_attack_move(id_from_ffi(squad_id)?, target_x, target_y))
+    }
+
     /// Issue a hold order to a squad.
     #[func]
-    fn order_hold(&mut self, squad_id: i32) {
-        if let Some(ref mut sim) = self.sim {
-            sim.order_hold(squad_id as u32);
-        }
+    fn order_hold(&mut self, squad_id: i32) -> i32 {
+        self.issue(|sim| sim.order_hold(id_from_ffi(squad_id)?))
     }
 
     /// Issue a retreat order to a squad.
     #[func]
-    fn order_retreat(&mut self, squad_id: i32) {
-        if let Some(ref mut sim) = self.sim {
-            sim.order_retreat(squad_id as u32);
+    fn order_retreat(&mut self, squad_id: i32) -> i32 {
+        self.issue(|sim| sim.order_retreat(id_from_ffi(squad_id)?))
+    }
+
+    /// Order a squad to lay suppressive fire on an area.
+    #[func]
+    fn order_suppress_area(&mut self, squad_id: i32, x: f32, y: f32, radius: f32) -> i32 {
+        self.issue(|sim| sim.order_suppress_area(id_from_ffi(squad_id)?, x, y, radius))
+    }
+
+    /// Order a squad to defend a circular area, taking cover inside it.
+    #[func]
+    fn order_defend_area(&mut self, squad_id: i32, x: f32, y: f32, radius: f32) -> i32 {
+        self.issue(|sim| sim.order_defend_area(id_from_ffi(squad_id)?, x, y, radius))
+    }
+
+    /// Order a squad to patrol a loop of waypoints, given as x, y pairs.
+    #[func]
+    fn order_patrol(&mut self, squad_id: i32, waypoints: PackedFloat32Array) -> i32 {
+        let points: Vec<(f32, f32)> = waypoints.as_slice().chunks_exact(2).map(|p| (p[0], p[1])).collect();
+        self.issue(|sim| sim.order_patrol(id_from_ffi(squad_id)?, &points))
+    }
+
+    /// Set a squad's fire stance (see `FIRE_STANCE_*` in godot_bridge).
+    /// `FIRE_STANCE_AI` hands the stance back to the AI.
+    #[func]
+    fn set_fire_stance(&mut self, squad_id: i32, stance: i32) -> i32 {
+        self.issue(|sim| match stance {
+            FIRE_STANCE_AI => sim.clear_fire_stance(id_from_ffi(squad_id)?),
+            _ => sim.set_fire_stance(id_from_ffi(squad_id)?, fire_stance_from_id(stance)?),
+        })
+    }
+
+    /// Set which enemies a squad fires at first (see `FIRE_PRIORITY_*` in
+    /// godot_bridge).
+    #[func]
+    fn set_fire_priority(&mut self, squad_id: i32, priority: i32) -> i32 {
+        self.issue(|sim| sim.set_fire_priority(id_from_ffi(squad_id)?, fire_priority_from_id(priority)?))
+    }
+
+    /// Set a squad's unit class (see `UNIT_CLASS_*` in godot_bridge).
+    #[func]
+    fn set_unit_class(&mut self, squad_id: i32, class: i32) -> i32 {
+        self.issue(|sim| sim.set_unit_class(id_from_ffi(squad_id)?, unit_class_from_id(class)?))
+    }
+
+    /// Order a squad to attack a specific enemy squad, firing only at it.
+    #[func]
+    fn order_attack_target(&mut self, squad_id: i32, target_id: i32) -> i32 {
+        self.issue(|sim| sim.order_attack_target(id_from_ffi(squad_id)?, id_from_ffi(target_id)?))
+    }
+
+    /// Queue a move after the squad's current orders.
+    #[func]
+    fn queue_move(&mut self, squad_id: i32, x: f32, y: f32) -> i32 {
+        self.issue(|sim| sim.queue_order(id_from_ffi(squad_id)?, Order::MoveTo { x, y }, None))
+    }
+
+    /// Queue an attack-move after the squad's current orders.
+    #[func]
+    fn queue_attack_move(&mut self, squad_id: i32, x: f32, y: f32) -> i32 {
+        self.issue(|sim| sim.queue_order(id_from_ffi(squad_id)?, Order::AttackMove { x, y }, None))
+    }
+
+    /// Queue a hold facing `facing` (radians) after the squad's current orders.
+    #[func]
+    fn queue_hold(&mut self, squad_id: i32, facing: f32) -> i32 {
+        self.issue(|sim| sim.queue_order(id_from_ffi(squad_id)?, Order::Hold, Some(facing)))
+    }
+
+    /// Move squads as a group in formation (see `FORMATION_*` in godot_bridge).
+    #[func]
+    fn order_formation_move(&mut self, squad_ids: PackedInt32Array, x: f32, y: f32, formation: i32) -> i32 {
+        self.issue(|sim| sim.order_formation_move(&ids_from_ffi(&squad_ids)?, x, y, formation_from_id(formation)?))
+    }
+
+    /// March squads in column and deploy into line near the destination.
+    #[func]
+    fn order_march_and_deploy(&mut self, squad_ids: PackedInt32Array, x: f32, y: f32) -> i32 {
+        self.issue(|sim| sim.order_march_and_deploy(&ids_from_ffi(&squad_ids)?, x, y))
+    }
+
+    /// Remaining waypoints of a squad's route as `[x0, y0, x1, y1, ...]`,
+    /// ending at its goal, for drawing. Empty until the route is found, and
+    /// for invalid IDs.
+    #[func]
+    fn get_squad_path(&mut self, squad_id: i32) -> PackedFloat32Array {
+        match (&mut self.sim, id_from_ffi(squad_id)) {
+            (Some(sim), Ok(id)) => {
+                let points: Vec<f32> = sim.squad_path(id).into_iter().flat_map(|(x, y)| [x, y]).collect();
+                PackedFloat32Array::from(points.as_slice())
+            }
+            _ => PackedFloat32Array::new(),
+        }
+    }
+
+    /// Drop a squad's queued orders, keeping the current one.
+    #[func]
+    fn clear_order_queue(&mut self, squad_id: i32) -> i32 {
+        self.issue(|sim| sim.clear_order_queue(id_from_ffi(squad_id)?))
+    }
+
+    /// Move a squad once a trigger is met (see `TRIGGER_*` in godot_bridge
+    /// for trigger IDs and their arguments).
+    #[func]
+    fn schedule_move(&mut self, squad_id: i32, x: f32, y: f32, trigger: i32, trigger_args: PackedFloat32Array) -> i32 {
+        self.schedule_order(squad_id, Order::MoveTo { x, y }, trigger, &trigger_args)
+    }
+
+    /// Attack-move a squad once a trigger is met, e.g. when a barrage lifts.
+    #[func]
+    fn schedule_attack_move(&mut self, squad_id: i32, x: f32, y: f32, trigger: i32, trigger_args: PackedFloat32Array) -> i32 {
+        self.schedule_order(squad_id, Order::AttackMove { x, y }, trigger, &trigger_args)
+    }
+
+    /// Retreat a squad once a trigger is met, e.g. when its morale falls.
+    #[func]
+    fn schedule_retreat(&mut self, squad_id: i32, trigger: i32, trigger_args: PackedFloat32Array) -> i32 {
+        self.schedule_order(squad_id, Order::Retreat, trigger, &trigger_args)
+    }
+
+    /// Drop a squad's scheduled orders.
+    #[func]
+    fn cancel_scheduled_orders(&mut self, squad_id: i32) -> i32 {
+        self.issue(|sim| sim.cancel_scheduled_orders(id_from_ffi(squad_id)?))
+    }
+
+    /// Fire `shells` rounds of artillery over `duration` seconds.
+    /// `ORDER_RESULT_INVALID_ID` if the ID is taken.
+    ///
+    /// - `faction`: 0 = Blue, 1 = Red
+    #[func]
+    fn fire_barrage(&mut self, barrage_id: i32, faction: i32, x: f32, y: f32, shells: i32, duration: f32) -> i32 {
+        let faction_enum = if faction == 0 {
+            tbg_sim::components::Faction::Blue
+        } else {
+            tbg_sim::components::Faction::Red
+        };
+        self.issue(|sim| {
+            let shells = u32::try_from(shells).map_err(|_| SimError::InvalidValue(shells as f32))?;
+            sim.fire_barrage(id_from_ffi(barrage_id)?, faction_enum, x, y, shells, duration)
+        })
+    }
+
+    /// Create a platoon or company (see `COMMAND_LEVEL_*` in godot_bridge).
+    ///
+    /// - `faction`: 0 = Blue, 1 = Red
+    /// - `parent_id`: Company to put a platoon under, or -1 for none
+    #[func]
+    fn create_command_group(&mut self, group_id: i32, level: i32, faction: i32, parent_id: i32) -> i32 {
+        let faction_enum = if faction == 0 {
+            tbg_sim::components::Faction::Blue
+        } else {
+            tbg_sim::components::Faction::Red
+        };
+        self.issue(|sim| {
+            let parent = if parent_id == -1 { None } else { Some(id_from_ffi(parent_id)?) };
+            sim.create_command_group(id_from_ffi(group_id)?, command_level_from_id(level)?, faction_enum, parent)
+        })
+    }
+
+    /// Put a squad under a platoon or company.
+    #[func]
+    fn assign_squad(&mut self, squad_id: i32, group_id: i32) -> i32 {
+        self.issue(|sim| sim.assign_squad(id_from_ffi(squad_id)?, id_from_ffi(group_id)?))
+    }
+
+    /// IDs of all squads under a platoon or company, for selection. Empty
+    /// for invalid IDs.
+    #[func]
+    fn get_group_squads(&mut self, group_id: i32) -> PackedInt32Array {
+        match (&mut self.sim, id_from_ffi(group_id)) {
+            (Some(sim), Ok(group_id)) => {
+                let ids: Vec<i32> = sim.group_squads(group_id).into_iter().map(|id| id as i32).collect();
+                PackedInt32Array::from(ids.as_slice())
+            }
+            _ => PackedInt32Array::new(),
         }
     }
 
+    /// Move a platoon or company; its platoons spread out in one line.
+    #[func]
+    fn order_group_move(&mut self, group_id: i32, x: f32, y: f32) -> i32 {
+        self.issue(|sim| sim.order_group(id_from_ffi(group_id)?, Order::MoveTo { x, y }))
+    }
+
+    /// Attack-move a platoon or company; its platoons spread out in one line.
+    #[func]
+    fn order_group_attack_move(&mut self, group_id: i32, x: f32, y: f32) -> i32 {
+        self.issue(|sim| sim.order_group(id_from_ffi(group_id)?, Order::AttackMove { x, y }))
+    }
+
+    /// Order every squad of a platoon or company to hold.
+    #[func]
+    fn order_group_hold(&mut self, group_id: i32) -> i32 {
+        self.issue(|sim| sim.order_group(id_from_ffi(group_id)?, Order::Hold))
+    }
+
+    /// Order every squad of a platoon or company to retreat.
+    #[func]
+    fn order_group_retreat(&mut self, group_id: i32) -> i32 {
+        self.issue(|sim| sim.order_group(id_from_ffi(group_id)?, Order::Retreat))
+    }
+
+    /// Set the point a group's AI squads advance on.
+    #[func]
+    fn set_group_objective(&mut self, group_id: i32, x: f32, y: f32) -> i32 {
+        self.issue(|sim| sim.set_group_objective(id_from_ffi(group_id)?, x, y))
+    }
+
+    /// Add a rally point for retreating squads. `ORDER_RESULT_INVALID_ID`
+    /// if the ID is taken.
+    ///
+    /// - `faction`: 0 = Blue, 1 = Red
+    #[func]
+    fn add_rally_point(&mut self, rally_id: i32, faction: i32, x: f32, y: f32) -> i32 {
+        let faction_enum = if faction == 0 {
+            tbg_sim::components::Faction::Blue
+        } else {
+            tbg_sim::components::Faction::Red
+        };
+        self.issue(|sim| sim.add_rally_point(id_from_ffi(rally_id)?, faction_enum, x, y))
+    }
+
+    /// Remove a rally point. `ORDER_RESULT_INVALID_ID` if it doesn't exist.
+    #[func]
+    fn remove_rally_point(&mut self, rally_id: i32) -> i32 {
+        self.issue(|sim| sim.remove_rally_point(id_from_ffi(rally_id)?))
+    }
+
+    /// Order a squad to occupy a building.
+    #[func]
+    fn order_garrison(&mut self, squad_id: i32, building_id: i32) -> i32 {
+        self.issue(|sim| sim.order_garrison(id_from_ffi(squad_id)?, id_from_ffi(building_id)?))
+    }
+
+    /// Order a squad to leave its building.
+    #[func]
+    fn order_ungarrison(&mut self, squad_id: i32) -> i32 {
+        self.issue(|sim| sim.order_ungarrison(id_from_ffi(squad_id)?))
+    }
+
     /// Spawn a terrain damage event (crater).
     #[func]
     fn spawn_crater(&mut self, x: f32, y: f32, radius: f32, depth: f32) {
@@ -160,6 +425,28 @@ impl SimWorldBridge {
         }
     }
 
+    /// Spawn a smoke cloud.
+    #[func]
+    fn spawn_smoke(&mut self, x: f32, y: f32, radius: f32, lifetime: f32) {
+        if let Some(ref mut sim) = self.sim {
+            sim.spawn_smoke(x, y, radius, lifetime);
+        }
+    }
+
+    /// Have a squad throw a smoke grenade toward a point.
+    #[func]
+    fn throw_smoke(&mut self, squad_id: i32, target_x: f32, target_y: f32) -> i32 {
+        self.issue(|sim| sim.throw_smoke(id_from_ffi(squad_id)?, target_x, target_y))
+    }
+
+    /// Set the wind that smoke drifts with.
+    #[func]
+    fn set_wind(&mut self, vx: f32, vy: f32) {
+        if let Some(ref mut sim) = self.sim {
+            sim.set_wind(vx, vy);
+        }
+    }
+
     /// Get terrain snapshot as JSON.
     #[func]
     fn get_terrain_json(&self) -> GString {
@@ -277,6 +564,31 @@ impl SimWorldBridge {
     }
 }
 
+impl SimWorldBridge {
+    /// Give an order and return its `ORDER_RESULT_*` code, keeping the
+    /// reason for a rejection for `get_last_order_error`.
+    fn issue(&mut self, order: impl FnOnce(&mut SimWorld) -> OrderResult) -> i32 {
+        let Some(sim) = &mut self.sim else {
+            self.last_order_error = "simulation not initialized".to_string();
+            return ORDER_RESULT_NO_WORLD;
+        };
+        let result = order(sim);
+        self.last_order_error = result.err().map(|e| e.to_string()).unwrap_or_default();
+        order_result_to_id(&result)
+    }
+
+    /// Schedule an order behind an FFI trigger ID and its arguments.
+    fn schedule_order(&mut self, squad_id: i32, order: Order, trigger: i32, trigger_args: &PackedFloat32Array) -> i32 {
+        let trigger = order_trigger_from_id(trigger, trigger_args.as_slice());
+        self.issue(|sim| sim.schedule_order(id_from_ffi(squad_id)?, trigger?, order))
+    }
+}
+
+/// Convert squad IDs from FFI, rejecting values that can't be IDs.
+fn ids_from_ffi(ids: &PackedInt32Array) -> Result<Vec<u32>, SimError> {
+    ids.as_slice().iter().map(|&id| id_from_ffi(id)).collect()
+}
+
 // ============================================================================
 // RustSimulation - Lightweight class with efficient flat buffer API
 // ============================================================================
@@ -387,7 +699,8 @@ impl RustSimulation {
     ///   - [+6] size, [+7] health, [+8] health_max
     ///   - [+9] morale, [+10] suppression
     ///   - [+11] is_alive (1.0/0.0), [+12] is_routing (1.0/0.0)
-    ///   - [+13] order_type (0=Hold, 1=Move, 2=Attack, 3=Retreat)
+    ///   - [+13] order_type (0=Hold, 1=Move, 2=Attack, 3=Retreat, 4=Suppress,
+    ///     5=Defend, 6=Patrol, 7=Garrison, 8=Ungarrison)
     ///
     /// This is the primary method for extracting simulation state for visualization.
     /// It is more efficient than JSON serialization.
@@ -422,32 +735,36 @@ impl RustSimulation {
     ///
     /// The squad will move to the target position.
     #[func]
-    fn issue_move_order(&mut self, squad_id: i32, x: f32, y: f32) {
-        self.sim.order_move(squad_id as u32, x, y);
+    fn issue_move_order(&mut self, squad_id: i32, x: f32, y: f32) -> i32 {
+        let result = id_from_ffi(squad_id).and_then(|id| self.sim.order_move(id, x, y));
+        order_result_to_id(&result)
     }
 
     /// Issue an attack-move order to a squad.
     ///
     /// The squad will move to the target, engaging enemies along the way.
     #[func]
-    fn issue_attack_move_order(&mut self, squad_id: i32, x: f32, y: f32) {
-        self.sim.order_attack_move(squad_id as u32, x, y);
+    fn issue_attack_move_order(&mut self, squad_id: i32, x: f32, y: f32) -> i32 {
+        let result = id_from_ffi(squad_id).and_then(|id| self.sim.order_attack_move(id, x, y));
+        order_result_to_id(&result)
     }
 
     /// Issue a hold order to a squad.
     ///
     /// The squad will stop moving and hold position.
     #[func]
-    fn issue_hold_order(&mut self, squad_id: i32) {
-        self.sim.order_hold(squad_id as u32);
+    fn issue_hold_order(&mut self, squad_id: i32) -> i32 {
+        let result = id_from_ffi(squad_id).and_then(|id| self.sim.order_hold(id));
+        order_result_to_id(&result)
     }
 
     /// Issue a retreat order to a squad.
     ///
     /// The squad will retreat from combat.
     #[func]
-    fn issue_retreat_order(&mut self, squad_id: i32) {
-        self.sim.order_retreat(squad_id as u32);
+    fn issue_retreat_order(&mut self, squad_id: i32) -> i32 {
+        let result = id_from_ffi(squad_id).and_then(|id| self.sim.order_retreat(id));
+        order_result_to_id(&result)
     }
 
     // ========================================================================
@@ -16,12 +16,12 @@ fn main() {
     // Issue move orders to Blue squads
     println!("\n--- Issuing move orders to Blue squads ---\n");
     for i in 0..6 {
-        sim.order_move(i, 0.0, -25.0 + (i as f32) * 10.0);
+        sim.order_move(i, 0.0, -25.0 + (i as f32) * 10.0).expect("Blue squad should take orders");
     }
 
     // Issue move orders to Red squads
     for i in 0..6 {
-        sim.order_attack_move(100 + i, 0.0, -25.0 + (i as f32) * 10.0);
+        sim.order_attack_move(100 + i, 0.0, -25.0 + (i as f32) * 10.0).expect("Red squad should take orders");
     }
 
     // Run simulation for 200 ticks (10 seconds - enough for combat)
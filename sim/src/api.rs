@@ -17,11 +17,39 @@
 //! - **Parallel Systems**: Independent systems run in parallel across CPU cores
 
 use crate::components::*;
+use crate::error::{OrderResult, SimError};
+use crate::los::{LineOfSight, los_update_system};
 use crate::spatial::{SpatialGrid, spatial_grid_update_system};
 use crate::systems::*;
-use crate::terrain::{TerrainGrid, TerrainSnapshot, Crater};
+use crate::terrain::{TerrainGrid, TerrainResource, TerrainSnapshot, Crater};
 use crate::world::Snapshot;
 use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+use std::sync::{PoisonError, RwLockReadGuard, RwLockWriteGuard};
+
+/// Crater radius of a single shell from `call_barrage`.
+const SHELL_CRATER_RADIUS: f32 = 4.0;
+
+/// Crater depth of a single shell from `call_barrage`.
+const SHELL_CRATER_DEPTH: f32 = 1.5;
+
+/// Ok if any of the results is, else the first error (`NoSquads` if there
+/// are none).
+fn any_ok(results: Vec<OrderResult>) -> OrderResult {
+    if results.iter().any(|r| r.is_ok()) {
+        return Ok(());
+    }
+    results.into_iter().next().unwrap_or(Err(SimError::NoSquads))
+}
+
+/// Ok if an area order's radius is a positive, finite distance.
+fn check_radius(radius: f32) -> OrderResult {
+    if radius.is_finite() && radius > 0.0 {
+        Ok(())
+    } else {
+        Err(SimError::InvalidValue(radius))
+    }
+}
 
 /// The main simulation world container.
 ///
@@ -35,14 +63,15 @@ pub struct SimWorld {
     schedule: Schedule,
     tick: u64,
     time: f32,
-    /// Terrain grid for heightmap and terrain effects.
-    terrain: TerrainGrid,
     /// New craters since last snapshot (cleared after snapshot).
     new_craters: Vec<Crater>,
     /// Flag indicating terrain was modified.
     terrain_dirty: bool,
     /// Accumulated time for fixed timestep.
     time_accumulator: f32,
+    /// Fire missions called so far, salting the scatter of each so that
+    /// missions called on the same tick land differently.
+    fire_missions: u64,
 }
 
 impl SimWorld {
@@ -58,12 +87,26 @@ impl SimWorld {
         // Core resources
         world.insert_resource(DeltaTime(config.fixed_timestep));
         world.insert_resource(SpatialGrid::new(20.0)); // 20 unit cells
+        world.insert_resource(LineOfSight::new());
+        world.insert_resource(CoverZones::default());
+        world.insert_resource(CoverProviderIndex::default());
+
+        // Terrain grid: 200x200 cells, 2 units per cell = 400x400 world units.
+        // Lives in the ECS so systems (movement, combat, LOS) and the API share it.
+        world.insert_resource(TerrainResource::new(TerrainGrid::new_with_features(200, 200, 2.0)));
         
         // Performance resources
         world.insert_resource(SimTick(0));
         world.insert_resource(config);
         world.insert_resource(SectorCombatData::default());
         world.insert_resource(PendingCombatResults::default());
+        world.insert_resource(AfterActionReport::default());
+        world.insert_resource(LiftedBarrages::default());
+        world.insert_resource(CloseAssaults::default());
+        world.insert_resource(TerrainDamageBuffer::default());
+        world.insert_resource(Wind::default());
+        world.insert_resource(Pathfinder::default());
+        world.insert_resource(FlowFields::default());
 
         // Build schedule with parallel system groups
         // See sim/src/systems/mod.rs for detailed data access documentation
@@ -81,6 +124,8 @@ impl SimWorld {
         // All write to different resources/components, so they run in PARALLEL.
         schedule.add_systems((
             spatial_grid_update_system,  // writes: SpatialGrid (resource)
+            los_update_system,           // writes: LineOfSight (resource)
+            cover_provider_index_system, // writes: CoverProviderIndex (resource)
             lod_assignment_system,       // writes: SimLod (component)
             sector_assignment_system,    // writes: SectorId (component)
             activity_flags_system,       // writes: ActivityFlags (component)
@@ -96,7 +141,7 @@ impl SimWorld {
         schedule.add_systems((
             threat_awareness_system,     // writes: ThreatAwareness
             nearby_friendlies_system,    // writes: NearbyFriendlies
-        ).after(spatial_grid_update_system));
+        ).after(spatial_grid_update_system).after(los_update_system));
         
         schedule.add_systems(
             behavior_state_system        // writes: BehaviorState, reads: ThreatAwareness
@@ -112,6 +157,7 @@ impl SimWorld {
         schedule.add_systems((
             ai_order_system,             // writes: Order
             flocking_system,             // writes: Velocity
+            ai_fire_stance_system,       // writes: FireStance
         ).after(behavior_state_system));
         
         // =========================================================================
@@ -120,15 +166,42 @@ impl SimWorld {
         // Combat is split into gather (parallelizable) and apply (sequential) phases.
         // Other systems remain chained for correctness.
         schedule.add_systems((
+            order_trigger_system,
+            command_ai_system,
+            engage_target_system,
+            retreat_system,
+            defense_system,
+            formation_system,
+            flow_field_system,
+            pathfinding_system,
             order_system,
             movement_system,
-        ).chain().after(flocking_system));
+        ).chain().after(flocking_system).after(ai_order_system).after(ai_fire_stance_system));
+
+        // Squads enter and leave buildings; occupants are pinned in place
+        schedule.add_systems(garrison_system.after(movement_system));
+
+        // Cover status at post-movement positions, read by combat
+        schedule.add_systems(
+            cover_detection_system
+                .after(garrison_system)
+                .after(cover_provider_index_system)
+        );
+        schedule.add_systems(fatigue_system.after(movement_system));
+
+        // Close assaults between squads in contact; engaged squads skip ranged fire
+        schedule.add_systems(
+            assault_system
+                .after(cover_detection_system)
+                .after(fatigue_system)
+        );
         
         // Combat gather phase - reads entities, writes to PendingCombatResults resource
         // Can run in parallel with other read-only systems
         schedule.add_systems(
             combat_gather_system
-                .after(movement_system)
+                .after(assault_system)
+                .after(los_update_system)
         );
         
         // Combat apply phase - applies pending damage, must be sequential
@@ -137,87 +210,61 @@ impl SimWorld {
                 .after(combat_gather_system)
         );
         
+        // Low-LOD squads fight through per-sector firepower pools
+        schedule.add_systems(sector_combat_system.after(combat_apply_system));
+
+        // Attributed fire goes to the combat log, if enabled
+        schedule.add_systems(combat_log_system.after(combat_apply_system).after(sector_combat_system));
+
         // Post-combat systems
         schedule.add_systems((
             suppression_decay_system,
             morale_system,
+            command_morale_system,
             rout_system,
-        ).chain().after(combat_apply_system));
+        ).chain().after(combat_apply_system).after(sector_combat_system));
         
         // =========================================================================
         // GROUP 5: Environment (After Group 4) - PARALLEL
         // =========================================================================
         // Terrain and destructible updates operate on different entity types.
+        // Explosions are applied to destructibles and squads of every faction,
+        // then despawned so each is applied exactly once. Collapses spawned by
+        // destruction_state_system are applied on the next tick.
         schedule.add_systems((
-            terrain_damage_to_destructibles_system,
+            (
+                terrain_damage_to_destructibles_system,
+                blast_damage_system,     // writes: Health, Suppression, Morale, AfterActionReport
+            ),
+            clear_terrain_damage_system,
             destruction_state_system,
         ).chain().after(rout_system));
-
-        // Create terrain grid: 200x200 cells, 2 units per cell = 400x400 world units
-        let terrain = TerrainGrid::new_with_features(200, 200, 2.0);
+        schedule.add_systems(smoke_system.after(rout_system)); // writes: SmokeCloud, Position (clouds)
 
         Self {
             world,
             schedule,
             tick: 0,
             time: 0.0,
-            terrain,
             new_craters: Vec::new(),
             terrain_dirty: true, // Initial terrain needs to be sent
             time_accumulator: 0.0,
+            fire_missions: 0,
         }
     }
 
     /// Create a test world with some squads for demonstration.
     pub fn new_default_test_world() -> Self {
         let mut sim = Self::new();
-        let sector_size = 40.0; // Default sector size
 
         // Spawn Blue faction squads on the left (player-controlled, no AI)
         for i in 0..6 {
-            let x = -50.0;
-            let y = -25.0 + (i as f32) * 10.0;
-            sim.world.spawn((
-                SquadBundle {
-                    squad_id: SquadId(i),
-                    faction: Faction::Blue,
-                    position: Position::new(x, y),
-                    velocity: Velocity::default(),
-                    health: Health::new(100.0),
-                    stats: SquadStats::default(),
-                    morale: Morale::default(),
-                    suppression: Suppression::default(),
-                    order: Order::Hold,
-                },
-                // Performance components
-                SimLod::default(),
-                SectorId::from_position(x, y, sector_size),
-                ActivityFlags::default(),
-            ));
+            sim.spawn_squad(i, Faction::Blue, -50.0, -25.0 + (i as f32) * 10.0);
         }
 
         // Spawn Red faction squads on the right (AI-controlled)
         for i in 0..6 {
-            let x = 50.0;
-            let y = -25.0 + (i as f32) * 10.0;
-            sim.world.spawn((
-                SquadBundle {
-                    squad_id: SquadId(100 + i),
-                    faction: Faction::Red,
-                    position: Position::new(x, y),
-                    velocity: Velocity::default(),
-                    health: Health::new(100.0),
-                    stats: SquadStats::default(),
-                    morale: Morale::default(),
-                    suppression: Suppression::default(),
-                    order: Order::Hold,
-                },
-                AIBundle::default(),
-                // Performance components
-                SimLod::default(),
-                SectorId::from_position(x, y, sector_size),
-                ActivityFlags::default(),
-            ));
+            sim.spawn_ai_squad(100 + i, Faction::Red, 50.0, -25.0 + (i as f32) * 10.0);
         }
 
         // Spawn some trees in forest patches
@@ -263,7 +310,7 @@ impl SimWorld {
         }
 
         // Update terrain (crater aging, etc.) - runs every frame
-        self.terrain.update(dt);
+        self.terrain_write().update(dt);
     }
 
     /// Run a single fixed timestep update.
@@ -274,8 +321,14 @@ impl SimWorld {
         }
 
         // Increment simulation tick
-        if let Some(mut tick_res) = self.world.get_resource_mut::<SimTick>() {
+        let tick = self.world.get_resource_mut::<SimTick>().map(|mut tick_res| {
             tick_res.increment();
+            tick_res.0
+        });
+
+        // Shells due from barrages in progress land this tick
+        if let Some(tick) = tick {
+            self.fire_barrages(tick);
         }
 
         // Run all systems
@@ -307,7 +360,7 @@ impl SimWorld {
             self.time_accumulator -= fixed_dt;
         }
 
-        self.terrain.update(dt);
+        self.terrain_write().update(dt);
         total_duration
     }
 
@@ -319,8 +372,11 @@ impl SimWorld {
         snapshot.new_craters = self.new_craters.clone();
         snapshot.terrain_dirty = self.terrain_dirty;
         
-        // Clear new craters after snapshot
+        // Clear new craters and reported terrain damage after snapshot
         self.new_craters.clear();
+        if let Some(mut buffer) = self.world.get_resource_mut::<TerrainDamageBuffer>() {
+            buffer.clear();
+        }
         self.terrain_dirty = false;
         
         snapshot
@@ -333,7 +389,7 @@ impl SimWorld {
 
     /// Get a full terrain snapshot (for initial load or when terrain_dirty).
     pub fn terrain_snapshot(&self) -> TerrainSnapshot {
-        TerrainSnapshot::from_grid(&self.terrain)
+        TerrainSnapshot::from_grid(&self.terrain())
     }
 
     /// Get terrain snapshot as JSON.
@@ -352,60 +408,592 @@ impl SimWorld {
     }
 
     /// Issue a move order to a squad.
-    pub fn order_move(&mut self, squad_id: u32, target_x: f32, target_y: f32) {
-        let mut query = self.world.query::<(&SquadId, &mut Order)>();
-        for (id, mut order) in query.iter_mut(&mut self.world) {
-            if id.0 == squad_id {
-                *order = Order::MoveTo { x: target_x, y: target_y };
-                break;
+    pub fn order_move(&mut self, squad_id: u32, target_x: f32, target_y: f32) -> OrderResult {
+        self.set_order(squad_id, Order::MoveTo { x: target_x, y: target_y })
+    }
+
+    /// Issue an attack-move order to a squad.
+    pub fn order_attack_move(&mut self, squad_id: u32, target_x: f32, target_y: f32) -> OrderResult {
+        self.set_order(squad_id, Order::AttackMove { x: target_x, y: target_y })
+    }
+
+    /// Issue a hold order to a squad.
+    pub fn order_hold(&mut self, squad_id: u32) -> OrderResult {
+        self.set_order(squad_id, Order::Hold)
+    }
+
+    /// Issue a retreat order to a squad.
+    pub fn order_retreat(&mut self, squad_id: u32) -> OrderResult {
+        self.set_order(squad_id, Order::Retreat)
+    }
+
+    /// Order a squad to hold and lay suppressive fire on a circular area.
+    /// Enemies in the area are suppressed even when not spotted.
+    pub fn order_suppress_area(&mut self, squad_id: u32, x: f32, y: f32, radius: f32) -> OrderResult {
+        self.set_order(squad_id, Order::SuppressArea { x, y, radius })
+    }
+
+    /// Order a squad to defend a circular area. It stays inside, taking the
+    /// best cover there against enemies that threaten the area.
+    pub fn order_defend_area(&mut self, squad_id: u32, x: f32, y: f32, radius: f32) -> OrderResult {
+        self.set_order(squad_id, Order::DefendArea { x, y, radius })
+    }
+
+    /// Order a squad to patrol a loop through `waypoints`, stopping to fight
+    /// any contacts. At most `MAX_PATROL_WAYPOINTS` are used.
+    pub fn order_patrol(&mut self, squad_id: u32, waypoints: &[(f32, f32)]) -> OrderResult {
+        self.set_order(squad_id, Order::Patrol { waypoints: Waypoints::new(waypoints) })
+    }
+
+    /// Order a squad to move into a building and occupy it.
+    ///
+    /// A squad that finds the building full or destroyed on arrival holds
+    /// outside.
+    pub fn order_garrison(&mut self, squad_id: u32, building_id: u32) -> OrderResult {
+        let mut buildings = self.world.query_filtered::<(&DestructibleId, &Position), With<Building>>();
+        let Some(target) = buildings.iter(&self.world)
+            .find(|(id, _)| id.0 == building_id)
+            .map(|(_, pos)| *pos)
+        else {
+            return Err(SimError::UnknownBuilding(building_id));
+        };
+        self.set_order(squad_id, Order::Garrison { building: building_id, x: target.x, y: target.y })
+    }
+
+    /// Order a squad to leave the building it occupies.
+    pub fn order_ungarrison(&mut self, squad_id: u32) -> OrderResult {
+        self.set_order(squad_id, Order::Ungarrison)
+    }
+
+    /// Set a squad's fire priority for target selection.
+    pub fn set_fire_priority(&mut self, squad_id: u32, priority: FirePriority) -> OrderResult {
+        let entity = self.controllable_squad(squad_id)?;
+        self.world.entity_mut(entity).insert(priority);
+        Ok(())
+    }
+
+    /// Set a squad's rules of engagement. The AI no longer changes the
+    /// stance of a squad it was set for (see `clear_fire_stance`).
+    ///
+    /// An `EngageTarget` stance must name a live squad of the other side,
+    /// as for `order_attack_target`.
+    pub fn set_fire_stance(&mut self, squad_id: u32, stance: FireStance) -> OrderResult {
+        let entity = self.controllable_squad(squad_id)?;
+        if let FireStance::EngageTarget(target_id) = stance {
+            self.enemy_target(entity, target_id)?;
+        }
+        self.world.entity_mut(entity).insert((stance, ExplicitFireStance));
+        Ok(())
+    }
+
+    /// Hand a squad's rules of engagement back to the AI. The stance stays
+    /// as it is until the AI changes it.
+    pub fn clear_fire_stance(&mut self, squad_id: u32) -> OrderResult {
+        let entity = self.controllable_squad(squad_id)?;
+        self.world.entity_mut(entity).remove::<ExplicitFireStance>();
+        Ok(())
+    }
+
+    /// Order a squad to attack an enemy squad: it closes on the target and
+    /// fires only at it until the target is dead or the stance is changed.
+    ///
+    /// The target must be a live squad of the other side.
+    pub fn order_attack_target(&mut self, squad_id: u32, target_id: u32) -> OrderResult {
+        let squad = self.controllable_squad(squad_id)?;
+        let target = self.enemy_target(squad, target_id)?;
+        let target_pos = self.world.get::<Position>(target).copied().ok_or(SimError::InvalidTarget(target_id))?;
+        self.set_order(squad_id, Order::AttackMove { x: target_pos.x, y: target_pos.y })?;
+        self.world.entity_mut(squad).insert(FireStance::EngageTarget(target_id));
+        Ok(())
+    }
+
+    /// Set a squad's unit class (MG team, officers, vehicle...). Squads
+    /// that can't take orders may still be given one, as long as they are
+    /// alive.
+    pub fn set_unit_class(&mut self, squad_id: u32, class: UnitClass) -> OrderResult {
+        let entity = self.live_squad(squad_id)?;
+        self.world.entity_mut(entity).insert(class);
+        Ok(())
+    }
+
+    /// A live squad of the other side from `squad`, to be attacked.
+    fn enemy_target(&mut self, squad: Entity, target_id: u32) -> Result<Entity, SimError> {
+        let target = self.find_squad(target_id).ok_or(SimError::UnknownSquad(target_id))?;
+        if !self.world.get::<Health>(target).is_some_and(|h| h.is_alive()) {
+            return Err(SimError::DeadSquad(target_id));
+        }
+        if self.world.get::<Faction>(squad) == self.world.get::<Faction>(target) {
+            return Err(SimError::InvalidTarget(target_id));
+        }
+        Ok(target)
+    }
+
+    /// Find a squad entity by ID.
+    fn find_squad(&mut self, squad_id: u32) -> Option<Entity> {
+        let mut query = self.world.query::<(Entity, &SquadId)>();
+        query.iter(&self.world)
+            .find(|(_, id)| id.0 == squad_id)
+            .map(|(e, _)| e)
+    }
+
+    /// Find a squad that hasn't been wiped out.
+    fn live_squad(&mut self, squad_id: u32) -> Result<Entity, SimError> {
+        let entity = self.find_squad(squad_id).ok_or(SimError::UnknownSquad(squad_id))?;
+        if !self.world.get::<Health>(entity).is_some_and(|h| h.is_alive()) {
+            return Err(SimError::DeadSquad(squad_id));
+        }
+        Ok(entity)
+    }
+
+    /// Find a squad that can take orders: alive, not surrendered and not
+    /// routing.
+    fn controllable_squad(&mut self, squad_id: u32) -> Result<Entity, SimError> {
+        let entity = self.live_squad(squad_id)?;
+        let squad = self.world.entity(entity);
+        if squad.contains::<Surrendered>() || squad.get::<Morale>().is_some_and(|m| m.is_broken()) {
+            return Err(SimError::NotControllable(squad_id));
+        }
+        Ok(entity)
+    }
+
+    /// Check that a squad can be sent to a point.
+    fn check_destination(&self, x: f32, y: f32) -> OrderResult {
+        let terrain = self.terrain();
+        if !terrain.contains(x, y) {
+            return Err(SimError::OutOfBounds { x, y });
+        }
+        if !terrain.get_terrain_at(x, y).terrain_type.is_passable() {
+            return Err(SimError::Impassable { x, y });
+        }
+        Ok(())
+    }
+
+    /// Check the points an order sends a squad to or fires at.
+    fn check_order(&self, order: &Order) -> OrderResult {
+        match *order {
+            Order::Hold | Order::Retreat | Order::Ungarrison | Order::Garrison { .. } => Ok(()),
+            Order::MoveTo { x, y } | Order::AttackMove { x, y } => self.check_destination(x, y),
+            Order::DefendArea { x, y, radius } => {
+                check_radius(radius)?;
+                self.check_destination(x, y)
+            }
+            Order::SuppressArea { x, y, radius } => {
+                check_radius(radius)?;
+                if self.terrain().contains(x, y) {
+                    Ok(())
+                } else {
+                    Err(SimError::OutOfBounds { x, y })
+                }
+            }
+            Order::Patrol { waypoints } => {
+                if waypoints.is_empty() {
+                    return Err(SimError::NoWaypoints);
+                }
+                waypoints.as_slice().iter().try_for_each(|&(x, y)| self.check_destination(x, y))
             }
         }
     }
 
-    /// Issue an attack-move order to a squad.
-    pub fn order_attack_move(&mut self, squad_id: u32, target_x: f32, target_y: f32) {
-        let mut query = self.world.query::<(&SquadId, &mut Order)>();
-        for (id, mut order) in query.iter_mut(&mut self.world) {
-            if id.0 == squad_id {
-                *order = Order::AttackMove { x: target_x, y: target_y };
-                break;
+    /// Replace a squad's order, dropping any queued orders.
+    fn set_order(&mut self, squad_id: u32, new_order: Order) -> OrderResult {
+        let entity = self.controllable_squad(squad_id)?;
+        self.check_order(&new_order)?;
+        replace_order(self.world.entity_mut(entity), new_order);
+        Ok(())
+    }
+
+    /// Append an order to a squad's queue (shift-queue). It starts when the
+    /// orders ahead of it complete; a holding squad starts it at once.
+    /// `facing` (radians) is taken once the order completes.
+    pub fn queue_order(&mut self, squad_id: u32, order: Order, facing: Option<f32>) -> OrderResult {
+        let entity = self.controllable_squad(squad_id)?;
+        self.check_order(&order)?;
+        let mut squad = self.world.entity_mut(entity);
+        squad.remove::<FormationMember>();
+        match squad.get_mut::<OrderQueue>() {
+            Some(mut queue) => queue.push(order, facing),
+            None => {
+                let mut queue = OrderQueue::default();
+                queue.push(order, facing);
+                squad.insert(queue);
             }
         }
+        if !squad.contains::<Facing>() {
+            squad.insert(Facing::default());
+        }
+        Ok(())
     }
 
-    /// Issue a hold order to a squad.
-    pub fn order_hold(&mut self, squad_id: u32) {
-        let mut query = self.world.query::<(&SquadId, &mut Order)>();
-        for (id, mut order) in query.iter_mut(&mut self.world) {
-            if id.0 == squad_id {
-                *order = Order::Hold;
-                break;
+    /// Move squads to a destination as a group in formation, facing the
+    /// direction of travel. Squads take slots in the order given.
+    ///
+    /// Squads that can't take orders are left out; fails only if none can.
+    pub fn order_formation_move(&mut self, squad_ids: &[u32], x: f32, y: f32, formation: Formation) -> OrderResult {
+        self.start_formation(squad_ids, (x, y), formation, None, false, None)
+    }
+
+    /// March squads to a destination in column and deploy into line within
+    /// `DEPLOY_DISTANCE` of it.
+    ///
+    /// Squads that can't take orders are left out; fails only if none can.
+    pub fn order_march_and_deploy(&mut self, squad_ids: &[u32], x: f32, y: f32) -> OrderResult {
+        self.start_formation(squad_ids, (x, y), Formation::Column, Some(Formation::Line), false, None)
+    }
+
+    /// Form the squads that can take orders into a new group anchored at
+    /// their center, facing `facing` or else the destination. Fails with
+    /// the first squad's error if none can.
+    fn start_formation(
+        &mut self,
+        squad_ids: &[u32],
+        destination: (f32, f32),
+        formation: Formation,
+        deploy: Option<Formation>,
+        attack: bool,
+        facing: Option<f32>,
+    ) -> OrderResult {
+        self.check_destination(destination.0, destination.1)?;
+        let mut first_error = None;
+        let mut ids = Vec::new();
+        let mut squads = Vec::new();
+        for &id in squad_ids {
+            match self.controllable_squad(id) {
+                Ok(entity) => {
+                    ids.push(id);
+                    squads.push(entity);
+                }
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+        let Some(anchor) = self.squad_centroid(&ids) else {
+            return Err(first_error.unwrap_or(SimError::NoSquads));
+        };
+
+        let group = FormationGroup::new(formation, anchor, destination);
+        let group = self.world.spawn(FormationGroup {
+            deploy,
+            attack,
+            facing: facing.unwrap_or(group.facing),
+            ..group
+        }).id();
+        for (rank, entity) in squads.into_iter().enumerate() {
+            let Ok(mut squad) = self.world.get_entity_mut(entity) else { continue };
+            if let Some(mut queue) = squad.get_mut::<OrderQueue>() {
+                queue.clear();
             }
+            squad.insert(FormationMember { group, rank: rank as u32 });
         }
+        Ok(())
     }
 
-    /// Issue a retreat order to a squad.
-    pub fn order_retreat(&mut self, squad_id: u32) {
-        let mut query = self.world.query::<(&SquadId, &mut Order)>();
-        for (id, mut order) in query.iter_mut(&mut self.world) {
-            if id.0 == squad_id {
-                *order = Order::Retreat;
-                break;
+    /// Drop a squad's queued orders. The current order is kept.
+    pub fn clear_order_queue(&mut self, squad_id: u32) -> OrderResult {
+        let entity = self.find_squad(squad_id).ok_or(SimError::UnknownSquad(squad_id))?;
+        if let Some(mut queue) = self.world.get_mut::<OrderQueue>(entity) {
+            queue.clear();
+        }
+        Ok(())
+    }
+
+    /// A squad's queued orders, next first.
+    pub fn order_queue(&mut self, squad_id: u32) -> Vec<QueuedOrder> {
+        self.find_squad(squad_id)
+            .and_then(|entity| self.world.get::<OrderQueue>(entity))
+            .map(|queue| queue.pending.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Give a squad an order to take once `trigger` is met. It then replaces
+    /// the squad's current and queued orders. A squad can wait on several
+    /// triggers at once.
+    ///
+    /// The squad or barrage a trigger waits on must exist, and a morale
+    /// threshold must be a number.
+    pub fn schedule_order(&mut self, squad_id: u32, trigger: OrderTrigger, order: Order) -> OrderResult {
+        let entity = self.controllable_squad(squad_id)?;
+        self.check_order(&order)?;
+        self.check_trigger(&trigger)?;
+        let mut squad = self.world.entity_mut(entity);
+        let scheduled = ScheduledOrder { trigger, order };
+        match squad.get_mut::<ScheduledOrders>() {
+            Some(mut orders) => orders.0.push(scheduled),
+            None => {
+                squad.insert(ScheduledOrders(vec![scheduled]));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a trigger can be met: what it waits on exists.
+    fn check_trigger(&mut self, trigger: &OrderTrigger) -> OrderResult {
+        match *trigger {
+            OrderTrigger::AtTick(_) | OrderTrigger::SectorCaptured { .. } => Ok(()),
+            OrderTrigger::Arrived { squad } => self.find_squad(squad).map(|_| ()).ok_or(SimError::UnknownSquad(squad)),
+            OrderTrigger::MoraleBelow { squad, threshold } => {
+                if threshold.is_nan() {
+                    return Err(SimError::InvalidTrigger);
+                }
+                self.find_squad(squad).map(|_| ()).ok_or(SimError::UnknownSquad(squad))
+            }
+            OrderTrigger::BarrageLifted { barrage } => {
+                self.barrage_lifted(barrage).map(|_| ()).ok_or(SimError::InvalidId(barrage as i64))
+            }
+        }
+    }
+
+    /// Drop a squad's scheduled orders. Its current and queued orders are
+    /// kept.
+    pub fn cancel_scheduled_orders(&mut self, squad_id: u32) -> OrderResult {
+        let entity = self.find_squad(squad_id).ok_or(SimError::UnknownSquad(squad_id))?;
+        if let Some(mut orders) = self.world.get_mut::<ScheduledOrders>(entity) {
+            orders.0.clear();
+        }
+        Ok(())
+    }
+
+    /// A squad's scheduled orders, in the order they were given.
+    pub fn scheduled_orders(&mut self, squad_id: u32) -> Vec<ScheduledOrder> {
+        self.find_squad(squad_id)
+            .and_then(|entity| self.world.get::<ScheduledOrders>(entity))
+            .map(|orders| orders.0.clone())
+            .unwrap_or_default()
+    }
+
+    /// Waypoints a squad has still to pass on its route, ending at its
+    /// goal. Empty if it isn't going anywhere or its route hasn't been
+    /// found yet.
+    pub fn squad_path(&mut self, squad_id: u32) -> Vec<(f32, f32)> {
+        self.find_squad(squad_id)
+            .and_then(|entity| self.world.get::<NavPath>(entity))
+            .map(|path| path.remaining().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Create a platoon or company. A platoon may sit under a company of
+    /// the same faction.
+    ///
+    /// Fails with `InvalidId` if the ID is taken or the parent isn't a
+    /// level above, `UnknownGroup` if the parent doesn't exist and
+    /// `FactionMismatch` if it belongs to the other side.
+    pub fn create_command_group(&mut self, id: u32, level: CommandLevel, faction: Faction, parent: Option<u32>) -> OrderResult {
+        if self.find_command_group(id).is_some() {
+            return Err(SimError::InvalidId(id as i64));
+        }
+        if let Some(parent_id) = parent {
+            let parent_group = self.find_command_group(parent_id)
+                .and_then(|e| self.world.get::<CommandGroup>(e))
+                .ok_or(SimError::UnknownGroup(parent_id))?;
+            if parent_group.faction != faction {
+                return Err(SimError::FactionMismatch(parent_id));
+            }
+            if parent_group.level <= level {
+                return Err(SimError::InvalidId(parent_id as i64));
+            }
+        }
+        let mut group = self.world.spawn(CommandGroup::new(id, level, faction));
+        if let Some(parent_id) = parent {
+            group.insert(CommandParent(parent_id));
+        }
+        Ok(())
+    }
+
+    /// Put a squad under a command group, replacing its previous parent.
+    ///
+    /// Both must exist and be on the same side.
+    pub fn assign_squad(&mut self, squad_id: u32, group_id: u32) -> OrderResult {
+        let squad = self.find_squad(squad_id).ok_or(SimError::UnknownSquad(squad_id))?;
+        let group = self.find_command_group(group_id).ok_or(SimError::UnknownGroup(group_id))?;
+        let faction = self.world.get::<CommandGroup>(group).map(|g| g.faction);
+        if self.world.get::<Faction>(squad).copied() != faction {
+            return Err(SimError::FactionMismatch(group_id));
+        }
+        self.world.entity_mut(squad).insert(CommandParent(group_id));
+        Ok(())
+    }
+
+    /// IDs of all squads under a command group, directly or through its
+    /// platoons, in ascending order.
+    pub fn group_squads(&mut self, group_id: u32) -> Vec<u32> {
+        let parents = self.command_parents();
+        let mut query = self.world.query::<(&SquadId, &CommandParent)>();
+        let mut squads: Vec<u32> = query.iter(&self.world)
+            .filter(|(_, parent)| lineage(parent.0, &parents).any(|g| g == group_id))
+            .map(|(id, _)| id.0)
+            .collect();
+        squads.sort_unstable();
+        squads
+    }
+
+    /// Give an order to every squad under a command group.
+    ///
+    /// Moves and attack-moves are split up: each platoon (and the group's
+    /// own squads, if any) takes a stretch of one line facing the
+    /// destination and moves up in line formation. Other orders go to each
+    /// squad as given.
+    ///
+    /// Squads that can't take orders are left out; fails only if none can.
+    pub fn order_group(&mut self, group_id: u32, order: Order) -> OrderResult {
+        if self.find_command_group(group_id).is_none() {
+            return Err(SimError::UnknownGroup(group_id));
+        }
+        self.check_order(&order)?;
+        let (destination, attack) = match order {
+            Order::MoveTo { x, y } => ((x, y), false),
+            Order::AttackMove { x, y } => ((x, y), true),
+            _ => {
+                let results: Vec<OrderResult> = self.group_squads(group_id)
+                    .into_iter()
+                    .map(|id| self.set_order(id, order))
+                    .collect();
+                return any_ok(results);
             }
+        };
+
+        // Platoons in ID order, then squads directly under the group
+        let mut child_groups: Vec<u32> = {
+            let mut query = self.world.query::<(&CommandGroup, &CommandParent)>();
+            query.iter(&self.world)
+                .filter(|(_, parent)| parent.0 == group_id)
+                .map(|(group, _)| group.id)
+                .collect()
+        };
+        child_groups.sort_unstable();
+        let mut units: Vec<Vec<u32>> = child_groups.into_iter().map(|g| self.group_squads(g)).collect();
+        let direct: Vec<u32> = {
+            let mut query = self.world.query::<(&SquadId, &CommandParent)>();
+            let mut direct: Vec<u32> = query.iter(&self.world)
+                .filter(|(_, parent)| parent.0 == group_id)
+                .map(|(id, _)| id.0)
+                .collect();
+            direct.sort_unstable();
+            direct
+        };
+        units.push(direct);
+        units.retain(|unit| !unit.is_empty());
+
+        let all: Vec<u32> = units.iter().flatten().copied().collect();
+        let Some((cx, cy)) = self.squad_centroid(&all) else {
+            return Err(SimError::NoSquads);
+        };
+        let facing = (destination.1 - cy).atan2(destination.0 - cx);
+        let (sin, cos) = facing.sin_cos();
+
+        // Units side by side along the line, one extra slot between them
+        let slots = (all.len() + units.len() - 1) as f32;
+        let mut start = 0.0;
+        let mut results = Vec::new();
+        for unit in &units {
+            let right = (start + (unit.len() - 1) as f32 / 2.0 - (slots - 1.0) / 2.0) * FORMATION_SPACING;
+            let unit_destination = (destination.0 + right * sin, destination.1 - right * cos);
+            results.push(self.start_formation(unit, unit_destination, Formation::Line, None, attack, Some(facing)));
+            start += unit.len() as f32 + 1.0;
+        }
+        any_ok(results)
+    }
+
+    /// Set the point a group's AI squads advance on. Their line faces the
+    /// objective from where the group stands now.
+    ///
+    /// Fails if the group doesn't exist.
+    pub fn set_group_objective(&mut self, group_id: u32, x: f32, y: f32) -> OrderResult {
+        let group = self.find_command_group(group_id).ok_or(SimError::UnknownGroup(group_id))?;
+        let squads = self.group_squads(group_id);
+        let facing = self.squad_centroid(&squads)
+            .map(|(cx, cy)| (y - cy).atan2(x - cx))
+            .unwrap_or(0.0);
+        self.world.entity_mut(group).insert(CommandObjective { x, y, facing });
+        Ok(())
+    }
+
+    /// Remove a group's objective.
+    pub fn clear_group_objective(&mut self, group_id: u32) {
+        if let Some(group) = self.find_command_group(group_id) {
+            self.world.entity_mut(group).remove::<CommandObjective>();
+        }
+    }
+
+    /// Find a command group entity by ID.
+    fn find_command_group(&mut self, group_id: u32) -> Option<Entity> {
+        let mut query = self.world.query::<(Entity, &CommandGroup)>();
+        query.iter(&self.world)
+            .find(|(_, group)| group.id == group_id)
+            .map(|(e, _)| e)
+    }
+
+    /// Parent of each command group, by ID.
+    fn command_parents(&mut self) -> HashMap<u32, Option<u32>> {
+        let mut query = self.world.query::<(&CommandGroup, Option<&CommandParent>)>();
+        query.iter(&self.world).map(|(g, p)| (g.id, p.map(|p| p.0))).collect()
+    }
+
+    /// Mean position of the given squads, if any exist.
+    fn squad_centroid(&mut self, squad_ids: &[u32]) -> Option<(f32, f32)> {
+        let squads: Vec<Entity> = squad_ids.iter().filter_map(|&id| self.find_squad(id)).collect();
+        let positions: Vec<Position> = squads.iter()
+            .filter_map(|&e| self.world.get::<Position>(e).copied())
+            .collect();
+        if positions.is_empty() {
+            return None;
+        }
+        let n = positions.len() as f32;
+        Some((
+            positions.iter().map(|p| p.x).sum::<f32>() / n,
+            positions.iter().map(|p| p.y).sum::<f32>() / n,
+        ))
+    }
+
+    /// Add a rally point for a faction's retreating squads.
+    ///
+    /// Fails with `InvalidId` if the ID is taken.
+    pub fn add_rally_point(&mut self, id: u32, faction: Faction, x: f32, y: f32) -> OrderResult {
+        if self.find_rally_point(id).is_some() {
+            return Err(SimError::InvalidId(id as i64));
         }
+        self.world.spawn((RallyPoint { id, faction }, Position::new(x, y)));
+        Ok(())
+    }
+
+    /// Remove a rally point. Fails with `InvalidId` if it doesn't exist.
+    pub fn remove_rally_point(&mut self, id: u32) -> OrderResult {
+        let entity = self.find_rally_point(id).ok_or(SimError::InvalidId(id as i64))?;
+        self.world.despawn(entity);
+        Ok(())
+    }
+
+    /// Find a rally point entity by ID.
+    fn find_rally_point(&mut self, id: u32) -> Option<Entity> {
+        let mut query = self.world.query::<(Entity, &RallyPoint)>();
+        query.iter(&self.world)
+            .find(|(_, rally)| rally.id == id)
+            .map(|(e, _)| e)
     }
 
     /// Spawn a terrain damage event (e.g., from artillery).
+    ///
+    /// The crater is applied to the shared terrain resource immediately, so
+    /// systems see the new terrain on the next tick. The explosion is
+    /// unattributed; use `spawn_blast` to record who fired it.
     pub fn spawn_crater(&mut self, x: f32, y: f32, radius: f32, depth: f32) {
+        self.spawn_blast(x, y, radius, depth, BlastSource::default());
+    }
+
+    /// Spawn an explosion with attribution (artillery, grenade, demolition).
+    ///
+    /// Deforms the terrain and damages squads of every faction and
+    /// destructibles within the blast radius on the next tick. Hits on the
+    /// source's own faction are recorded as friendly fire.
+    pub fn spawn_blast(&mut self, x: f32, y: f32, radius: f32, depth: f32, source: BlastSource) {
         // Apply to terrain grid
-        self.terrain.apply_crater(x, y, radius, depth);
+        self.terrain_write().apply_crater(x, y, radius, depth);
         
         // Track new crater for snapshot
         self.new_craters.push(Crater { x, y, radius, depth, age: 0.0 });
-        self.terrain_dirty = true;
+        self.terrain_changed(Some((x, y, radius)));
         
         // Also spawn ECS event for other systems
-        self.world.spawn(TerrainDamageEvent { x, y, radius, depth });
+        self.world.spawn((TerrainDamageEvent { x, y, radius, depth }, source));
     }
 
     /// Spawn an artillery barrage.
@@ -413,39 +1001,259 @@ impl SimWorld {
         let crater_radius = 3.0 + spread * 0.1;
         let crater_depth = 1.5;
         
-        self.terrain.apply_barrage(center_x, center_y, spread, count, crater_radius, crater_depth);
+        let new_craters: Vec<Crater> = {
+            let mut terrain = self.terrain_write();
+            terrain.apply_barrage(center_x, center_y, spread, count, crater_radius, crater_depth);
+            terrain.craters.iter().rev().take(count).copied().collect()
+        };
         
+        // Every shell is an explosion
+        for crater in &new_craters {
+            self.world.spawn((
+                TerrainDamageEvent { x: crater.x, y: crater.y, radius: crater.radius, depth: crater.depth },
+                BlastSource::default(),
+            ));
+        }
+
         // Track all new craters
-        for crater in self.terrain.craters.iter().rev().take(count) {
-            self.new_craters.push(*crater);
+        for crater in &new_craters {
+            self.terrain_changed(Some((crater.x, crater.y, crater.radius)));
         }
-        self.terrain_dirty = true;
+        self.new_craters.extend(new_craters);
+    }
+
+    /// Call an indirect-fire barrage for a faction.
+    ///
+    /// Shells scatter around the aim point according to the configured
+    /// `DispersionConfig`; they hit whoever is there, including the caller's
+    /// own squads. Returns the impact points.
+    pub fn call_barrage(&mut self, faction: Faction, target_x: f32, target_y: f32, count: usize) -> Vec<(f32, f32)> {
+        let seed = self.next_shell_seed();
+        self.land_shells(faction, target_x, target_y, count, seed)
+    }
+
+    /// Land `count` shells scattered around the aim point with the given seed.
+    fn land_shells(&mut self, faction: Faction, target_x: f32, target_y: f32, count: usize, seed: u32) -> Vec<(f32, f32)> {
+        let dispersion = self.world
+            .get_resource::<SimConfig>()
+            .map(|c| c.dispersion)
+            .unwrap_or_default();
+        let impacts = dispersion.shell_impacts(faction, target_x, target_y, count, seed);
+
+        let source = BlastSource { faction: Some(faction), kind: BlastKind::Artillery };
+        for &(x, y) in &impacts {
+            self.spawn_blast(x, y, SHELL_CRATER_RADIUS, SHELL_CRATER_DEPTH, source);
+        }
+        impacts
+    }
+
+    /// Fire a barrage for a faction, `shells` rounds spread evenly over
+    /// `duration` seconds from the next tick. Shells scatter like
+    /// `call_barrage`. Orders can wait for it to lift with
+    /// `OrderTrigger::BarrageLifted`.
+    ///
+    /// Fails with `InvalidId` if the ID is taken.
+    pub fn fire_barrage(&mut self, id: u32, faction: Faction, target_x: f32, target_y: f32, shells: u32, duration: f32) -> OrderResult {
+        if self.barrage_lifted(id).is_some() {
+            return Err(SimError::InvalidId(id as i64));
+        }
+        let fixed_dt = self.world
+            .get_resource::<SimConfig>()
+            .map(|c| c.fixed_timestep)
+            .unwrap_or(1.0 / 30.0);
+        let start_tick = self.world.get_resource::<SimTick>().map(|t| t.0).unwrap_or(self.tick) + 1;
+        let ticks = (duration.max(0.0) / fixed_dt).ceil().max(1.0) as u64;
+        self.world.spawn(Barrage {
+            id,
+            faction,
+            x: target_x,
+            y: target_y,
+            shells,
+            fired: 0,
+            start_tick,
+            end_tick: start_tick + ticks,
+        });
+        Ok(())
+    }
+
+    /// Whether a barrage has lifted. None if it doesn't exist.
+    pub fn barrage_lifted(&mut self, id: u32) -> Option<bool> {
+        if self.world.get_resource::<LiftedBarrages>().is_some_and(|l| l.0.contains(&id)) {
+            return Some(true);
+        }
+        let mut query = self.world.query::<&Barrage>();
+        query.iter(&self.world).find(|b| b.id == id).map(|b| b.is_lifted())
+    }
+
+    /// Land the shells of each barrage in progress that are due by `tick`,
+    /// despawning the barrages that have lifted.
+    fn fire_barrages(&mut self, tick: u64) {
+        let mut volleys = Vec::new();
+        let mut lifted = Vec::new();
+        let mut query = self.world.query::<(Entity, &mut Barrage)>();
+        for (entity, mut barrage) in query.iter_mut(&mut self.world) {
+            let due = barrage.shells_due(tick);
+            if due > barrage.fired {
+                volleys.push((barrage.id, barrage.faction, barrage.x, barrage.y, (due - barrage.fired) as usize));
+                barrage.fired = due;
+            }
+            if barrage.is_lifted() {
+                lifted.push((entity, barrage.id));
+            }
+        }
+        for (entity, id) in lifted {
+            self.world.despawn(entity);
+            self.world.get_resource_or_insert_with(LiftedBarrages::default).0.insert(id);
+        }
+        // Salted by barrage ID, above the range of the fire-mission count, so
+        // each barrage scatters the same whatever order they are visited in
+        for (id, faction, x, y, count) in volleys {
+            self.land_shells(faction, x, y, count, shell_seed(tick, (1 << 32) | id as u64));
+        }
+    }
+
+    /// Scatter seed for the next fire mission called this tick.
+    fn next_shell_seed(&mut self) -> u32 {
+        self.fire_missions += 1;
+        shell_seed(self.tick, self.fire_missions)
+    }
+
+    /// Fire artillery smoke rounds for a faction.
+    ///
+    /// Rounds scatter like `call_barrage` but leave smoke instead of
+    /// craters. Returns the impact points.
+    pub fn call_smoke_barrage(&mut self, faction: Faction, target_x: f32, target_y: f32, count: usize) -> Vec<(f32, f32)> {
+        let dispersion = self.world
+            .get_resource::<SimConfig>()
+            .map(|c| c.dispersion)
+            .unwrap_or_default();
+        let seed = self.next_shell_seed();
+        let impacts = dispersion.shell_impacts(faction, target_x, target_y, count, seed);
+        for &(x, y) in &impacts {
+            self.spawn_smoke(x, y, SMOKE_SHELL_RADIUS, SMOKE_SHELL_LIFETIME);
+        }
+        impacts
+    }
+
+    /// Spawn a smoke cloud.
+    pub fn spawn_smoke(&mut self, x: f32, y: f32, radius: f32, lifetime: f32) {
+        self.world.spawn((Position::new(x, y), SmokeCloud::new(radius, lifetime)));
+    }
+
+    /// Have a squad throw a smoke grenade toward a point.
+    ///
+    /// The grenade lands at most `SMOKE_GRENADE_RANGE` from the squad. A
+    /// routing squad can still throw one; a surrendered one can't.
+    pub fn throw_smoke(&mut self, squad_id: u32, target_x: f32, target_y: f32) -> OrderResult {
+        let entity = self.live_squad(squad_id)?;
+        let mut squad = self.world.entity_mut(entity);
+        if squad.contains::<Surrendered>() {
+            return Err(SimError::NotControllable(squad_id));
+        }
+        let pos = squad.get::<Position>().copied().ok_or(SimError::UnknownSquad(squad_id))?;
+        match squad.get_mut::<SmokeGrenades>() {
+            Some(mut grenades) if grenades.0 > 0 => grenades.0 -= 1,
+            _ => return Err(SimError::NoSmokeGrenades(squad_id)),
+        }
+
+        let dx = target_x - pos.x;
+        let dy = target_y - pos.y;
+        let dist = (dx * dx + dy * dy).sqrt();
+        let scale = if dist > SMOKE_GRENADE_RANGE { SMOKE_GRENADE_RANGE / dist } else { 1.0 };
+        self.spawn_smoke(pos.x + dx * scale, pos.y + dy * scale, SMOKE_GRENADE_RADIUS, SMOKE_GRENADE_LIFETIME);
+        Ok(())
+    }
+
+    /// Set the wind that smoke drifts with (world units per second).
+    pub fn set_wind(&mut self, vx: f32, vy: f32) {
+        self.world.insert_resource(Wind { vx, vy });
+    }
+
+    /// Battle statistics for the after-action report.
+    pub fn after_action_report(&self) -> Option<&AfterActionReport> {
+        self.world.get_resource::<AfterActionReport>()
+    }
+
+    /// Start recording attributed fire, keeping at most `capacity`
+    /// undrained entries.
+    pub fn enable_combat_log(&mut self, capacity: usize) {
+        self.world.insert_resource(CombatLog::with_capacity(capacity));
+    }
+
+    /// Stop recording attributed fire and discard undrained entries.
+    pub fn disable_combat_log(&mut self) {
+        self.world.remove_resource::<CombatLog>();
+    }
+
+    /// Take all combat log entries recorded since the last drain.
+    ///
+    /// Empty if the combat log is not enabled.
+    pub fn drain_combat_log(&mut self) -> Vec<CombatLogEntry> {
+        self.world
+            .get_resource_mut::<CombatLog>()
+            .map(|mut log| log.drain())
+            .unwrap_or_default()
+    }
+
+    /// Drain the combat log as a JSON array.
+    pub fn drain_combat_log_json(&mut self) -> String {
+        serde_json::to_string(&self.drain_combat_log()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Direct-fire statistics of a squad (hits, damage, kills).
+    pub fn combat_stats(&mut self, squad_id: u32) -> Option<CombatStats> {
+        let entity = self.find_squad(squad_id)?;
+        self.world.get::<CombatStats>(entity).copied()
     }
 
     /// Get movement speed multiplier at a position.
     pub fn get_movement_multiplier(&self, x: f32, y: f32) -> f32 {
-        self.terrain.get_movement_multiplier(x, y)
+        self.terrain_resource().get_movement_multiplier(x, y)
     }
 
     /// Get cover value at a position.
     pub fn get_cover_at(&self, x: f32, y: f32) -> f32 {
-        self.terrain.get_cover_at(x, y)
+        self.terrain_resource().get_cover_at(x, y)
     }
 
     /// Get terrain height at a position.
     pub fn get_height_at(&self, x: f32, y: f32) -> f32 {
-        self.terrain.get_height_at(x, y)
+        self.terrain_resource().get_height_at(x, y)
+    }
+
+    /// Get the shared terrain resource.
+    pub fn terrain_resource(&self) -> &TerrainResource {
+        self.world.resource::<TerrainResource>()
+    }
+
+    /// Get read access to the terrain grid.
+    pub fn terrain(&self) -> RwLockReadGuard<'_, TerrainGrid> {
+        self.terrain_resource().0.read().unwrap_or_else(PoisonError::into_inner)
     }
 
-    /// Get terrain grid reference.
-    pub fn terrain(&self) -> &TerrainGrid {
-        &self.terrain
+    /// Get write access to the terrain grid (marks terrain as dirty).
+    ///
+    /// Changes are visible to systems on the next tick.
+    pub fn terrain_mut(&mut self) -> RwLockWriteGuard<'_, TerrainGrid> {
+        self.terrain_changed(None);
+        self.terrain_write()
     }
 
-    /// Get mutable terrain grid reference.
-    pub fn terrain_mut(&mut self) -> &mut TerrainGrid {
+    /// Mark the terrain dirty for the client and the flow fields crossing
+    /// the changed area (center and radius, or anywhere if `None`) stale.
+    fn terrain_changed(&mut self, area: Option<(f32, f32, f32)>) {
         self.terrain_dirty = true;
-        &mut self.terrain
+        if let Some(mut flow_fields) = self.world.get_resource_mut::<FlowFields>() {
+            match area {
+                Some((x, y, radius)) => flow_fields.invalidate_area(x, y, radius),
+                None => flow_fields.invalidate(),
+            }
+        }
+    }
+
+    /// Write access to the terrain grid without touching the dirty flag.
+    fn terrain_write(&self) -> RwLockWriteGuard<'_, TerrainGrid> {
+        self.terrain_resource().0.write().unwrap_or_else(PoisonError::into_inner)
     }
 
     /// Spawn a tree at the given position.
@@ -475,8 +1283,9 @@ impl SimWorld {
         query.iter(&self.world).count()
     }
 
-    /// Spawn an AI-controlled squad with performance components.
-    pub fn spawn_ai_squad(&mut self, id: u32, faction: Faction, x: f32, y: f32) {
+    /// Spawn a player-controlled squad with the components every squad
+    /// carries.
+    fn spawn_squad(&mut self, id: u32, faction: Faction, x: f32, y: f32) -> Entity {
         // Get sector size from config
         let sector_size = self.world
             .get_resource::<SimConfig>()
@@ -495,12 +1304,25 @@ impl SimWorld {
                 suppression: Suppression::default(),
                 order: Order::Hold,
             },
-            AIBundle::default(),
             // Performance components
             SimLod::default(),
             SectorId::from_position(x, y, sector_size),
             ActivityFlags::default(),
-        ));
+            InCover::default(),
+            Fatigue::default(),
+            CombatStats::default(),
+            SmokeGrenades::default(),
+            OrderQueue::default(),
+            Facing::default(),
+            FireStance::default(),
+            IncomingFire::default(),
+        )).id()
+    }
+
+    /// Spawn an AI-controlled squad with performance components.
+    pub fn spawn_ai_squad(&mut self, id: u32, faction: Faction, x: f32, y: f32) {
+        let entity = self.spawn_squad(id, faction, x, y);
+        self.world.entity_mut(entity).insert(AIBundle::default());
     }
 
     /// Enable AI control for an existing squad.
@@ -629,7 +1451,7 @@ mod tests {
     #[test]
     fn test_move_order() {
         let mut sim = SimWorld::new_default_test_world();
-        sim.order_move(0, 100.0, 50.0);
+        sim.order_move(0, 100.0, 50.0).unwrap();
 
         // Step a few times
         for _ in 0..10 {
@@ -643,6 +1465,578 @@ mod tests {
         assert!(squad.x > -50.0);
     }
 
+    /// Paint a rectangle of terrain cells (world coordinates) with a terrain type.
+    fn paint_terrain(sim: &mut SimWorld, min: (f32, f32), max: (f32, f32), terrain_type: crate::terrain::TerrainType) {
+        let mut terrain = sim.terrain_mut();
+        let (gx0, gy0) = terrain.world_to_grid(min.0, min.1);
+        let (gx1, gy1) = terrain.world_to_grid(max.0, max.1);
+        for gy in gy0..=gy1 {
+            for gx in gx0..=gx1 {
+                if let Some(cell) = terrain.get_cell_mut(gx, gy) {
+                    cell.terrain_type = terrain_type;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mud_slows_movement() {
+        use crate::terrain::TerrainType;
+
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+        sim.spawn_ai_squad(2, Faction::Blue, 0.0, -40.0);
+        sim.disable_ai(1);
+        sim.disable_ai(2);
+        paint_terrain(&mut sim, (-5.0, 30.0), (60.0, 50.0), TerrainType::Mud);
+
+        sim.order_move(1, 50.0, 40.0).unwrap();
+        sim.order_move(2, 50.0, -40.0).unwrap();
+        for _ in 0..30 {
+            sim.step(1.0 / 30.0);
+        }
+
+        let snapshot = sim.snapshot();
+        let in_mud = snapshot.squads.iter().find(|s| s.id == 1).unwrap();
+        let on_open = snapshot.squads.iter().find(|s| s.id == 2).unwrap();
+        assert!(in_mud.x > 0.0, "Squad in mud should still move");
+        assert!(
+            in_mud.x < on_open.x * 0.5,
+            "Mud should slow movement: mud x={}, open x={}", in_mud.x, on_open.x
+        );
+    }
+
+    #[test]
+    fn test_trench_reduces_damage() {
+        use crate::terrain::TerrainType;
+
+        // Run the same firefight twice, once with the Blue squad in a trench
+        let run = |trench: bool| -> f32 {
+            let mut sim = SimWorld::new();
+            sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+            sim.spawn_ai_squad(2, Faction::Red, 30.0, 40.0);
+            sim.disable_ai(1);
+            sim.disable_ai(2);
+            if trench {
+                paint_terrain(&mut sim, (-2.0, 38.0), (2.0, 42.0), TerrainType::Trench);
+            }
+            for _ in 0..30 {
+                sim.step(1.0 / 30.0);
+            }
+            let snapshot = sim.snapshot();
+            let blue = snapshot.squads.iter().find(|s| s.id == 1).unwrap();
+            blue.health_max - blue.health
+        };
+
+        let open_damage = run(false);
+        let trench_damage = run(true);
+        assert!(open_damage > 0.0, "Blue should take fire in the open");
+        assert!(
+            trench_damage < open_damage * 0.5,
+            "Trench should reduce damage: trench={}, open={}", trench_damage, open_damage
+        );
+    }
+
+    #[test]
+    fn test_explicit_fire_stance_survives_ai() {
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+        sim.spawn_ai_squad(2, Faction::Blue, 0.0, -40.0);
+        sim.spawn_ai_squad(3, Faction::Red, 30.0, 40.0);
+        sim.spawn_ai_squad(4, Faction::Red, 30.0, -40.0);
+        assert_eq!(sim.set_fire_stance(1, FireStance::HoldFire), Ok(()));
+        assert_eq!(sim.set_fire_stance(2, FireStance::ReturnFire), Ok(()));
+        for _ in 0..10 {
+            sim.step(1.0 / 30.0);
+        }
+
+        let snapshot = sim.snapshot();
+        let stance = |id: u32| snapshot.squads.iter().find(|s| s.id == id).unwrap().fire_stance.clone();
+        assert_eq!(stance(1), "HoldFire");
+        assert_eq!(stance(2), "ReturnFire");
+
+        // Engaging needs a live enemy, as for an attack-target order
+        assert_eq!(sim.set_fire_stance(1, FireStance::EngageTarget(2)), Err(SimError::InvalidTarget(2)));
+        assert_eq!(sim.set_fire_stance(1, FireStance::EngageTarget(99)), Err(SimError::UnknownSquad(99)));
+        assert_eq!(sim.set_fire_stance(1, FireStance::EngageTarget(3)), Ok(()));
+
+        // Cleared, the stance is the AI's again
+        assert_eq!(sim.clear_fire_stance(1), Ok(()));
+        let squad = sim.find_squad(1).unwrap();
+        assert!(!sim.world.entity(squad).contains::<ExplicitFireStance>());
+        assert_eq!(sim.clear_fire_stance(99), Err(SimError::UnknownSquad(99)));
+    }
+
+    #[test]
+    fn test_flanking_bypasses_trench() {
+        // Blue is dug in along a north-south trench facing east (+x)
+        let run = |red_x: f32, red_y: f32| -> f32 {
+            let mut sim = SimWorld::new();
+            sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+            sim.spawn_ai_squad(2, Faction::Red, red_x, red_y);
+            sim.disable_ai(1);
+            sim.disable_ai(2);
+            sim.terrain_mut().dig_trench(0.0, 36.0, 0.0, 44.0, 0.0);
+            for _ in 0..30 {
+                sim.step(1.0 / 30.0);
+            }
+            let snapshot = sim.snapshot();
+            let blue = snapshot.squads.iter().find(|s| s.id == 1).unwrap();
+            blue.health_max - blue.health
+        };
+
+        let frontal_damage = run(30.0, 40.0);
+        let flank_damage = run(0.0, 70.0);
+        assert!(
+            flank_damage > frontal_damage * 1.5,
+            "Flanking fire should bypass the parapet: flank={}, frontal={}", flank_damage, frontal_damage
+        );
+    }
+
+    #[test]
+    fn test_smoke_grenade_screens_squad() {
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, -25.0, 40.0);
+        sim.spawn_ai_squad(2, Faction::Red, 25.0, 40.0);
+        sim.disable_ai(1);
+        sim.disable_ai(2);
+
+        // Thrown toward the enemy, landing at most a grenade's throw away
+        assert_eq!(sim.throw_smoke(1, 25.0, 40.0), Ok(()));
+        assert_eq!(sim.throw_smoke(1, -5.0, 40.0), Ok(()));
+        assert_eq!(sim.throw_smoke(1, -5.0, 40.0), Err(SimError::NoSmokeGrenades(1)));
+
+        sim.step(1.0 / 30.0);
+        let snapshot = sim.snapshot();
+        assert_eq!(snapshot.smoke.len(), 2);
+        assert!(snapshot.smoke.iter().all(|s| s.x <= -25.0 + SMOKE_GRENADE_RANGE + 0.01));
+
+        let los = sim.world().resource::<LineOfSight>();
+        assert!(!los.trace(None, -25.0, 40.0, 25.0, 40.0));
+        let health_before = sim.snapshot().squads.iter().map(|s| s.health).sum::<f32>();
+        for _ in 0..30 {
+            sim.step(1.0 / 30.0);
+        }
+        let health_after = sim.snapshot().squads.iter().map(|s| s.health).sum::<f32>();
+        assert_eq!(health_before, health_after, "nobody can fire through the smoke");
+    }
+
+    #[test]
+    fn test_garrisoned_squad_fires_unseen_from_building() {
+        let mut sim = SimWorld::new();
+        sim.spawn_building(500, -25.0, 40.0);
+        sim.spawn_ai_squad(1, Faction::Blue, -25.0, 40.0);
+        sim.spawn_ai_squad(2, Faction::Red, 25.0, 40.0);
+        sim.disable_ai(1);
+        sim.disable_ai(2);
+
+        assert_eq!(sim.order_garrison(1, 500), Ok(()));
+        assert_eq!(sim.order_garrison(1, 501), Err(SimError::UnknownBuilding(501)));
+        for _ in 0..30 {
+            sim.step(1.0 / 30.0);
+        }
+
+        let snapshot = sim.snapshot();
+        let squad = |id: u32| snapshot.squads.iter().find(|s| s.id == id).unwrap();
+        assert_eq!(squad(1).garrison, Some(500));
+        assert_eq!(squad(2).garrison, None);
+        // Red is beyond spotting range of the building; Blue fires freely
+        assert_eq!(squad(1).health, squad(1).health_max);
+        assert!(squad(2).health < squad(2).health_max);
+
+        sim.order_ungarrison(1).unwrap();
+        sim.step(1.0 / 30.0);
+        let snapshot = sim.snapshot();
+        assert_eq!(snapshot.squads.iter().find(|s| s.id == 1).unwrap().garrison, None);
+    }
+
+    #[test]
+    fn test_rejected_orders_say_why() {
+        use crate::terrain::TerrainType;
+
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+        sim.spawn_ai_squad(2, Faction::Blue, 10.0, 40.0);
+        sim.spawn_ai_squad(3, Faction::Red, 20.0, 40.0);
+        paint_terrain(&mut sim, (40.0, 30.0), (60.0, 50.0), TerrainType::Water);
+
+        assert_eq!(sim.order_move(9, 0.0, 0.0), Err(SimError::UnknownSquad(9)));
+        assert_eq!(sim.order_move(1, 500.0, 0.0), Err(SimError::OutOfBounds { x: 500.0, y: 0.0 }));
+        assert_eq!(sim.order_move(1, 50.0, 40.0), Err(SimError::Impassable { x: 50.0, y: 40.0 }));
+        assert_eq!(sim.order_suppress_area(1, 50.0, 40.0, 10.0), Ok(()), "can fire into water");
+        assert_eq!(sim.order_suppress_area(1, 20.0, 40.0, -5.0), Err(SimError::InvalidValue(-5.0)));
+        assert_eq!(sim.order_defend_area(1, 20.0, 40.0, 0.0), Err(SimError::InvalidValue(0.0)));
+        assert!(matches!(sim.order_defend_area(1, 20.0, 40.0, f32::NAN), Err(SimError::InvalidValue(_))));
+        assert_eq!(
+            sim.order_patrol(1, &[(0.0, 40.0), (50.0, 40.0)]),
+            Err(SimError::Impassable { x: 50.0, y: 40.0 })
+        );
+        assert_eq!(sim.order_attack_target(1, 2), Err(SimError::InvalidTarget(2)));
+        assert_eq!(sim.order_attack_target(1, 3), Ok(()));
+
+        let squad = sim.find_squad(2).unwrap();
+        sim.world.get_mut::<Morale>(squad).unwrap().value = 0.0;
+        assert_eq!(sim.order_hold(2), Err(SimError::NotControllable(2)));
+        assert_eq!(sim.set_fire_priority(2, FirePriority::Closest), Err(SimError::NotControllable(2)));
+        assert_eq!(sim.set_unit_class(2, UnitClass::MachineGun), Ok(()), "routing squads keep their kit");
+        sim.world.get_mut::<Health>(squad).unwrap().current = 0.0;
+        assert_eq!(sim.order_hold(2), Err(SimError::DeadSquad(2)));
+        assert_eq!(sim.set_unit_class(2, UnitClass::Rifle), Err(SimError::DeadSquad(2)));
+        assert_eq!(sim.set_fire_priority(9, FirePriority::Closest), Err(SimError::UnknownSquad(9)));
+
+        // A formation leaves out squads that can't move
+        assert_eq!(sim.order_formation_move(&[1, 2], 0.0, 60.0, Formation::Line), Ok(()));
+        assert_eq!(sim.order_formation_move(&[2], 0.0, 60.0, Formation::Line), Err(SimError::DeadSquad(2)));
+        assert_eq!(sim.order_formation_move(&[], 0.0, 60.0, Formation::Line), Err(SimError::NoSquads));
+    }
+
+    #[test]
+    fn test_squad_walks_round_water_to_its_goal() {
+        use crate::terrain::TerrainType;
+
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 20.0, 40.0);
+        sim.disable_ai(1);
+        paint_terrain(&mut sim, (36.0, 20.0), (44.0, 60.0), TerrainType::Water);
+
+        sim.order_move(1, 60.0, 40.0).unwrap();
+        sim.step(1.0 / 30.0);
+        let path = sim.squad_path(1);
+        assert_eq!(path.last(), Some(&(60.0, 40.0)));
+        assert!(path.len() > 1, "route should bend round the water: {path:?}");
+
+        for _ in 0..900 {
+            sim.step(1.0 / 30.0);
+            let squad = &sim.snapshot().squads[0];
+            assert_ne!(sim.terrain().get_terrain_at(squad.x, squad.y).terrain_type, TerrainType::Water);
+        }
+        let squad = &sim.snapshot().squads[0];
+        assert!((squad.x - 60.0).abs() < 1.0 && (squad.y - 40.0).abs() < 1.0, "stopped at ({}, {})", squad.x, squad.y);
+    }
+
+    #[test]
+    fn test_mass_move_steers_by_shared_flow_field() {
+        use crate::terrain::TerrainType;
+
+        let mut sim = SimWorld::new();
+        for id in 0..DEFAULT_FLOW_FIELD_MIN_SQUADS as u32 {
+            sim.spawn_ai_squad(id, Faction::Blue, 20.0, 30.0 + id as f32 * 2.0);
+            sim.disable_ai(id);
+        }
+        paint_terrain(&mut sim, (36.0, 20.0), (44.0, 60.0), TerrainType::Water);
+
+        for id in 0..DEFAULT_FLOW_FIELD_MIN_SQUADS as u32 {
+            sim.order_move(id, 60.0, 40.0).unwrap();
+        }
+        sim.step(1.0 / 30.0);
+        let target = crate::systems::pathfinding::cell_at(&sim.terrain(), 60.0, 40.0);
+        assert!(sim.world.resource::<FlowFields>().field(target).is_some());
+        assert!(sim.squad_path(0).is_empty(), "no squad needs a route of its own");
+        assert_eq!(sim.world.resource::<Pathfinder>().pending(), 0);
+
+        // New craters leave the field in use until its rebuild is due
+        sim.spawn_crater(-100.0, -100.0, 3.0, 1.0);
+        sim.step(1.0 / 30.0);
+        assert!(sim.world.resource::<FlowFields>().is_stale(target));
+        assert!(sim.world.resource::<FlowFields>().field(target).is_some());
+        for _ in 0..DEFAULT_FLOW_REBUILD_INTERVAL {
+            sim.step(1.0 / 30.0);
+        }
+        assert!(!sim.world.resource::<FlowFields>().is_stale(target));
+
+        for _ in 0..900 {
+            sim.step(1.0 / 30.0);
+            for squad in &sim.snapshot().squads {
+                assert_ne!(sim.terrain().get_terrain_at(squad.x, squad.y).terrain_type, TerrainType::Water);
+            }
+        }
+        for squad in &sim.snapshot().squads {
+            assert!((squad.x - 60.0).abs() < 1.0 && (squad.y - 40.0).abs() < 1.0, "squad {} stopped at ({}, {})", squad.id, squad.x, squad.y);
+        }
+    }
+
+    #[test]
+    fn test_queued_orders_run_in_sequence_and_end_facing() {
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+        sim.disable_ai(1);
+
+        assert_eq!(sim.queue_order(1, Order::MoveTo { x: 10.0, y: 40.0 }, None), Ok(()));
+        sim.queue_order(1, Order::AttackMove { x: 10.0, y: 50.0 }, None).unwrap();
+        sim.queue_order(1, Order::Hold, Some(std::f32::consts::FRAC_PI_2)).unwrap();
+        assert_eq!(sim.queue_order(99, Order::Hold, None), Err(SimError::UnknownSquad(99)));
+        let snapshot = sim.snapshot();
+        assert_eq!(snapshot.squads[0].queue.len(), 3);
+        assert_eq!(snapshot.squads[0].queue[1].order, "AttackMove(10.0,50.0)");
+
+        sim.step(1.0 / 30.0);
+        assert_eq!(sim.snapshot().squads[0].order, "MoveTo(10.0,40.0)");
+        assert_eq!(sim.order_queue(1).len(), 2);
+        for _ in 0..300 {
+            sim.step(1.0 / 30.0);
+        }
+
+        let squad = &sim.snapshot().squads[0];
+        assert_eq!(squad.order, "Hold");
+        assert!(squad.queue.is_empty());
+        assert!((squad.x - 10.0).abs() < 1.0 && (squad.y - 50.0).abs() < 1.0);
+        assert_eq!(squad.facing, std::f32::consts::FRAC_PI_2);
+
+        // A plain order replaces the queue
+        sim.queue_order(1, Order::MoveTo { x: 0.0, y: 0.0 }, None).unwrap();
+        sim.order_hold(1).unwrap();
+        assert!(sim.order_queue(1).is_empty());
+    }
+
+    #[test]
+    fn test_company_order_is_split_across_platoons() {
+        let mut sim = SimWorld::new();
+        assert_eq!(sim.create_command_group(1, CommandLevel::Company, Faction::Blue, None), Ok(()));
+        assert_eq!(sim.create_command_group(10, CommandLevel::Platoon, Faction::Blue, Some(1)), Ok(()));
+        assert_eq!(sim.create_command_group(20, CommandLevel::Platoon, Faction::Blue, Some(1)), Ok(()));
+        assert_eq!(sim.create_command_group(30, CommandLevel::Platoon, Faction::Red, Some(1)), Err(SimError::FactionMismatch(1)));
+        assert_eq!(sim.create_command_group(30, CommandLevel::Platoon, Faction::Blue, Some(10)), Err(SimError::InvalidId(10)));
+        assert_eq!(sim.create_command_group(30, CommandLevel::Platoon, Faction::Blue, Some(99)), Err(SimError::UnknownGroup(99)));
+        assert_eq!(sim.create_command_group(10, CommandLevel::Platoon, Faction::Blue, None), Err(SimError::InvalidId(10)));
+        assert_eq!(sim.set_group_objective(99, 0.0, 0.0), Err(SimError::UnknownGroup(99)));
+        for (id, platoon) in [(1, 10), (2, 10), (3, 20), (4, 20)] {
+            sim.spawn_ai_squad(id, Faction::Blue, 0.0, id as f32 * 10.0);
+            sim.disable_ai(id);
+            assert_eq!(sim.assign_squad(id, platoon), Ok(()));
+        }
+        sim.spawn_ai_squad(5, Faction::Red, 400.0, 0.0);
+        assert_eq!(sim.assign_squad(5, 10), Err(SimError::FactionMismatch(10)));
+        assert_eq!(sim.assign_squad(5, 99), Err(SimError::UnknownGroup(99)));
+
+        assert_eq!(sim.group_squads(1), vec![1, 2, 3, 4]);
+        assert_eq!(sim.group_squads(20), vec![3, 4]);
+        let snapshot = sim.snapshot();
+        assert_eq!(snapshot.squads.iter().find(|s| s.id == 3).unwrap().parent, Some(20));
+        assert_eq!(snapshot.command_groups.len(), 3);
+        assert_eq!(snapshot.command_groups[1].parent, Some(1));
+
+        assert_eq!(sim.order_group(1, Order::MoveTo { x: 100.0, y: 25.0 }), Ok(()));
+        for _ in 0..1200 {
+            sim.step(1.0 / 30.0);
+        }
+        let snapshot = sim.snapshot();
+        let ys: Vec<f32> = (1..=4)
+            .map(|id| snapshot.squads.iter().find(|s| s.id == id).unwrap())
+            .inspect(|s| assert!((s.x - 100.0).abs() < 1.0, "squad {} at x={}", s.id, s.x))
+            .map(|s| s.y)
+            .collect();
+        // One line, with a gap between the platoons
+        let within = (ys[0] - ys[1]).abs();
+        let between = (ys[1] - ys[2]).abs();
+        assert!((within - FORMATION_SPACING).abs() < 1.5);
+        assert!(between > within + 0.5 * FORMATION_SPACING);
+
+        assert_eq!(sim.order_group(20, Order::Hold), Ok(()));
+        assert_eq!(sim.snapshot().squads.iter().find(|s| s.id == 4).unwrap().order, "Hold");
+        assert_eq!(sim.order_group(99, Order::Hold), Err(SimError::UnknownGroup(99)));
+    }
+
+    #[test]
+    fn test_routed_squad_falls_back_to_rally_point_and_resumes() {
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+        sim.spawn_ai_squad(2, Faction::Red, 90.0, 40.0);
+        sim.disable_ai(1);
+        sim.disable_ai(2);
+        assert_eq!(sim.add_rally_point(1, Faction::Blue, -60.0, 40.0), Ok(()));
+        assert_eq!(sim.add_rally_point(1, Faction::Blue, 0.0, 0.0), Err(SimError::InvalidId(1)));
+        // A rally point past the enemy is no refuge
+        sim.add_rally_point(2, Faction::Blue, 120.0, 40.0).unwrap();
+
+        sim.order_move(1, 30.0, 40.0).unwrap();
+        let squad = sim.find_squad(1).unwrap();
+        sim.world_mut().get_mut::<Morale>(squad).unwrap().value = 0.1;
+        sim.step(1.0 / 30.0);
+        sim.step(1.0 / 30.0);
+        let snapshot = sim.snapshot();
+        let routed = snapshot.squads.iter().find(|s| s.id == 1).unwrap();
+        assert_eq!(routed.order, "Retreat");
+        assert_eq!(routed.fallback, Some((-60.0, 40.0)));
+        assert_eq!(snapshot.rally_points.len(), 2);
+
+        for _ in 0..300 {
+            sim.step(1.0 / 30.0);
+        }
+        let x = sim.snapshot().squads.iter().find(|s| s.id == 1).unwrap().x;
+        assert!(x < -20.0, "broken squad runs for the rally point");
+
+        for _ in 0..600 {
+            sim.step(1.0 / 30.0);
+        }
+        let snapshot = sim.snapshot();
+        let squad = snapshot.squads.iter().find(|s| s.id == 1).unwrap();
+        assert!(squad.morale >= RALLIED_MORALE);
+        assert_eq!(squad.order, "MoveTo(30.0,40.0)");
+        assert!(squad.fallback.is_none());
+
+        assert_eq!(sim.remove_rally_point(1), Ok(()));
+        assert_eq!(sim.remove_rally_point(1), Err(SimError::InvalidId(1)));
+    }
+
+    #[test]
+    fn test_ai_squad_keeps_patrolling_its_route() {
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+        assert_eq!(sim.order_patrol(1, &[]), Err(SimError::NoWaypoints));
+        assert_eq!(sim.order_patrol(1, &[(0.0, 40.0), (20.0, 40.0)]), Ok(()));
+
+        let mut furthest: f32 = 0.0;
+        for _ in 0..300 {
+            sim.step(1.0 / 30.0);
+            let snapshot = sim.snapshot();
+            let squad = snapshot.squads.iter().find(|s| s.id == 1).unwrap();
+            assert_eq!(squad.order, "Patrol(2)");
+            furthest = furthest.max(squad.x);
+        }
+        assert!(furthest > 18.0, "reached the far waypoint, got to x = {}", furthest);
+        let x = sim.snapshot().squads.iter().find(|s| s.id == 1).unwrap().x;
+        assert!(x < furthest - 2.0, "turned back at the far waypoint");
+    }
+
+    #[test]
+    fn test_zero_hour_attack_goes_in_when_barrage_lifts() {
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, -60.0, 40.0);
+        sim.spawn_ai_squad(2, Faction::Blue, -60.0, 50.0);
+        sim.disable_ai(1);
+        sim.disable_ai(2);
+
+        assert_eq!(sim.fire_barrage(1, Faction::Blue, 60.0, 40.0, 20, 1.0), Ok(()));
+        assert_eq!(sim.fire_barrage(1, Faction::Blue, 0.0, 0.0, 5, 1.0), Err(SimError::InvalidId(1)));
+        assert_eq!(sim.schedule_order(1, OrderTrigger::BarrageLifted { barrage: 1 }, Order::AttackMove { x: 60.0, y: 40.0 }), Ok(()));
+        sim.schedule_order(2, OrderTrigger::AtTick(10), Order::MoveTo { x: -50.0, y: 50.0 }).unwrap();
+        assert_eq!(sim.schedule_order(99, OrderTrigger::AtTick(1), Order::Hold), Err(SimError::UnknownSquad(99)));
+        assert_eq!(sim.schedule_order(1, OrderTrigger::Arrived { squad: 99 }, Order::Hold), Err(SimError::UnknownSquad(99)));
+        assert_eq!(
+            sim.schedule_order(1, OrderTrigger::MoraleBelow { squad: 1, threshold: f32::NAN }, Order::Retreat),
+            Err(SimError::InvalidTrigger)
+        );
+        assert_eq!(sim.schedule_order(1, OrderTrigger::BarrageLifted { barrage: 7 }, Order::Hold), Err(SimError::InvalidId(7)));
+        assert_eq!(sim.snapshot().squads[0].scheduled[0].trigger, "BarrageLifted(1)");
+
+        for _ in 0..10 {
+            sim.step(1.0 / 30.0);
+        }
+        let snapshot = sim.snapshot();
+        let order = |id: u32| snapshot.squads.iter().find(|s| s.id == id).unwrap().order.clone();
+        assert_eq!(order(1), "Hold", "waits under the barrage");
+        assert_eq!(order(2), "MoveTo(-50.0,50.0)");
+        assert_eq!(sim.barrage_lifted(1), Some(false));
+
+        for _ in 0..25 {
+            sim.step(1.0 / 30.0);
+        }
+        assert_eq!(sim.barrage_lifted(1), Some(true));
+        assert_eq!(sim.world.query::<&Barrage>().iter(&sim.world).count(), 0, "lifted barrages are despawned");
+        assert_eq!(sim.fire_barrage(1, Faction::Blue, 0.0, 0.0, 5, 1.0), Err(SimError::InvalidId(1)));
+        assert!(sim.snapshot().squads.iter().any(|s| s.id == 1 && s.order == "AttackMove(60.0,40.0)"));
+        assert!(sim.scheduled_orders(1).is_empty());
+    }
+
+    #[test]
+    fn test_barrage_on_own_lines_causes_friendly_fire() {
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+        sim.spawn_ai_squad(2, Faction::Red, 5.0, 40.0);
+        sim.disable_ai(1);
+        sim.disable_ai(2);
+
+        let impacts = sim.call_barrage(Faction::Blue, 2.0, 40.0, 24);
+        assert_eq!(impacts.len(), 24);
+        sim.step(1.0 / 30.0);
+
+        let snapshot = sim.snapshot();
+        for squad in &snapshot.squads {
+            assert!(squad.health < squad.health_max, "Squad {} should be hit by the barrage", squad.id);
+        }
+
+        let report = sim.after_action_report().unwrap();
+        assert!(report.friendly_fire_damage(Faction::Blue) > 0.0);
+        assert!(report.friendly_fire.iter().all(|i| i.squad_id == 1));
+        assert_eq!(report.friendly_fire_damage(Faction::Red), 0.0);
+    }
+
+    #[test]
+    fn test_fire_missions_on_one_tick_scatter_differently() {
+        let mut sim = SimWorld::new();
+        let offsets = |impacts: Vec<(f32, f32)>, x: f32| -> Vec<(f32, f32)> {
+            impacts.into_iter().map(|(ix, iy)| (ix - x, iy - 40.0)).collect()
+        };
+        let first = offsets(sim.call_barrage(Faction::Blue, 0.0, 40.0, 6), 0.0);
+        let second = offsets(sim.call_barrage(Faction::Blue, 0.0, 40.0, 6), 0.0);
+        let smoke = offsets(sim.call_smoke_barrage(Faction::Blue, 50.0, 40.0, 6), 50.0);
+        assert_ne!(first, second);
+        assert_ne!(first, smoke);
+    }
+
+    #[test]
+    fn test_barrages_on_one_tick_scatter_by_id() {
+        // Two barrages landing their only shell on the same tick
+        let run = |missions_before: usize| -> Vec<(f32, f32)> {
+            let mut sim = SimWorld::new();
+            for _ in 0..missions_before {
+                sim.call_barrage(Faction::Red, 300.0, 300.0, 1);
+            }
+            sim.snapshot();
+            sim.fire_barrage(1, Faction::Blue, 0.0, 40.0, 1, 0.0).unwrap();
+            sim.fire_barrage(2, Faction::Blue, 100.0, 40.0, 1, 0.0).unwrap();
+            sim.step(1.0 / 30.0);
+            let mut offsets: Vec<(f32, f32)> = sim.snapshot().new_craters.iter()
+                .map(|c| (c.x - if c.x > 50.0 { 100.0 } else { 0.0 }, c.y - 40.0))
+                .collect();
+            offsets.sort_by(|a, b| a.0.total_cmp(&b.0));
+            offsets
+        };
+
+        let offsets = run(0);
+        assert_eq!(offsets.len(), 2);
+        assert_ne!(offsets[0], offsets[1]);
+        // Earlier fire missions don't change where the barrages land
+        assert_eq!(offsets, run(3));
+    }
+
+    #[test]
+    fn test_blast_events_applied_once_and_reported() {
+        let mut sim = SimWorld::new();
+        sim.spawn_ai_squad(1, Faction::Blue, 0.0, 40.0);
+        sim.disable_ai(1);
+        sim.spawn_crater(3.0, 40.0, 4.0, 1.5);
+
+        sim.step(1.0 / 30.0);
+        let snapshot = sim.snapshot();
+        let squad = &snapshot.squads[0];
+        let health_after_blast = squad.health;
+        assert!(health_after_blast < squad.health_max);
+        assert!(squad.suppression > 0.0);
+        assert_eq!(snapshot.terrain_damage.len(), 1);
+
+        // The event entity is gone and not re-applied
+        let mut events = sim.world_mut().query::<&TerrainDamageEvent>();
+        assert_eq!(events.iter(sim.world()).count(), 0);
+        for _ in 0..10 {
+            sim.step(1.0 / 30.0);
+        }
+        let snapshot = sim.snapshot();
+        assert_eq!(snapshot.squads[0].health, health_after_blast);
+        assert!(snapshot.terrain_damage.is_empty());
+    }
+
+    #[test]
+    fn test_crater_visible_to_systems_next_tick() {
+        let mut sim = SimWorld::new();
+        sim.spawn_crater(0.0, 40.0, 6.0, 3.0);
+
+        // The ECS resource and the API see the same deformed terrain
+        let from_resource = sim.world().resource::<TerrainResource>().get_cover_at(0.0, 40.0);
+        assert_eq!(from_resource, sim.get_cover_at(0.0, 40.0));
+        assert!(from_resource > 0.0, "Crater should provide cover");
+    }
+
     #[test]
     fn test_snapshot_json() {
         let mut sim = SimWorld::new_default_test_world();
@@ -5,6 +5,7 @@
 
 use bevy_ecs::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 // ============================================================================
 // SPATIAL COMPONENTS
@@ -140,6 +141,145 @@ impl Default for SquadStats {
     }
 }
 
+/// Role of a squad on the battlefield, used for target selection.
+///
+/// Squads without this component are treated as `Rifle`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum UnitClass {
+    /// Line infantry.
+    #[default]
+    Rifle,
+    /// Machine gun team - high volume of fire.
+    MachineGun,
+    /// Command element (officers, NCOs).
+    Officer,
+    /// Anti-tank team.
+    AntiTank,
+    /// Armored or soft-skinned vehicle.
+    Vehicle,
+}
+
+impl UnitClass {
+    /// How dangerous this unit is as a target (1.0 = ordinary rifle squad).
+    pub fn threat_weight(&self) -> f32 {
+        match self {
+            UnitClass::Rifle => 1.0,
+            UnitClass::MachineGun => 1.6,
+            UnitClass::Officer => 1.2,
+            UnitClass::AntiTank => 1.1,
+            UnitClass::Vehicle => 2.0,
+        }
+    }
+
+    /// How well this unit's weapons suit engaging the given target class.
+    pub fn weapon_suitability(&self, target: UnitClass) -> f32 {
+        match (self, target) {
+            (UnitClass::AntiTank, UnitClass::Vehicle) => 1.5,
+            (UnitClass::AntiTank, _) => 0.5,
+            (_, UnitClass::Vehicle) => 0.1,
+            (UnitClass::MachineGun, _) => 1.2,
+            _ => 1.0,
+        }
+    }
+
+    /// How hard this unit's fire wears down cover it hits (1.0 = rifles).
+    pub fn structure_damage(&self) -> f32 {
+        match self {
+            UnitClass::Rifle => 1.0,
+            UnitClass::MachineGun => 2.0,
+            UnitClass::Officer => 0.5,
+            UnitClass::AntiTank => 6.0,
+            UnitClass::Vehicle => 8.0,
+        }
+    }
+}
+
+/// Per-squad fire priority, biasing target selection.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FirePriority {
+    /// Balanced threat-weighted selection.
+    #[default]
+    Auto,
+    /// Always engage the closest visible enemy.
+    Closest,
+    /// Prefer vehicles when any are visible.
+    PreferVehicles,
+    /// Focus fire on command elements.
+    FocusOfficers,
+    /// Focus fire on machine gun teams.
+    FocusMachineGuns,
+}
+
+impl FirePriority {
+    /// Score multiplier for a target of the given class.
+    pub fn bias(&self, target: UnitClass) -> f32 {
+        match (self, target) {
+            (FirePriority::PreferVehicles, UnitClass::Vehicle)
+            | (FirePriority::FocusOfficers, UnitClass::Officer)
+            | (FirePriority::FocusMachineGuns, UnitClass::MachineGun) => 3.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Ticks a squad counts as under fire after the last small-arms fire it
+/// took (see `IncomingFire`).
+pub const UNDER_FIRE_TICKS: u64 = 60;
+
+/// Per-squad rules of engagement.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FireStance {
+    /// Engage any enemy in range.
+    #[default]
+    FireAtWill,
+    /// Hold fire until fired upon.
+    ReturnFire,
+    /// Never fire, e.g. to lie in ambush.
+    HoldFire,
+    /// Fire only at the squad with this ID.
+    EngageTarget(u32),
+}
+
+impl FireStance {
+    /// Whether a squad with this stance holds its fire, given whether it is
+    /// under small-arms fire (see `IncomingFire::is_recent`).
+    pub fn holds_fire(&self, under_fire: bool) -> bool {
+        match self {
+            FireStance::HoldFire => true,
+            FireStance::ReturnFire => !under_fire,
+            FireStance::FireAtWill | FireStance::EngageTarget(_) => false,
+        }
+    }
+}
+
+/// The last small-arms fire a squad took, recorded when combat attributes
+/// hits to it. Blasts and lingering suppression don't count.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct IncomingFire {
+    /// Tick of the last incoming fire, if any.
+    pub tick: Option<u64>,
+    /// The squad that fired it; `None` for fire from a sector pool.
+    pub attacker: Option<Entity>,
+}
+
+impl IncomingFire {
+    /// Record fire taken on `tick`.
+    pub fn record(&mut self, tick: u64, attacker: Option<Entity>) {
+        self.tick = Some(tick);
+        self.attacker = attacker;
+    }
+
+    /// Whether the squad took fire within `UNDER_FIRE_TICKS` of `now`.
+    pub fn is_recent(&self, now: u64) -> bool {
+        self.tick.is_some_and(|t| now.saturating_sub(t) <= UNDER_FIRE_TICKS)
+    }
+}
+
+/// Marker for squads whose `FireStance` was set explicitly (see
+/// `SimWorld::set_fire_stance`). The AI leaves their stance alone.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct ExplicitFireStance;
+
 // ============================================================================
 // MORALE & SUPPRESSION COMPONENTS
 // ============================================================================
@@ -212,6 +352,45 @@ impl Suppression {
     }
 }
 
+/// Physical exhaustion from marching and fighting (0.0 = fresh, 1.0 = spent).
+#[derive(Component, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Fatigue {
+    pub value: f32,
+}
+
+impl Fatigue {
+    pub fn add(&mut self, amount: f32) {
+        self.value = (self.value + amount).min(1.0);
+    }
+
+    pub fn recover(&mut self, amount: f32) {
+        self.value = (self.value - amount).max(0.0);
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.value >= 0.8
+    }
+}
+
+/// Direct-fire record of a squad over the battle.
+///
+/// Maintained by `combat_apply_system`; the basis for veterancy and the
+/// after-action report.
+#[derive(Component, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CombatStats {
+    /// Expected hits scored on enemy squads.
+    pub hits: f32,
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    /// Enemy squads this squad finished off.
+    pub kills: u32,
+}
+
+/// Marker for squads that have surrendered. They no longer fight, move or
+/// appear in the spatial grid.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Surrendered;
+
 // ============================================================================
 // AI / ORDER COMPONENTS
 // ============================================================================
@@ -227,6 +406,18 @@ pub enum Order {
     AttackMove { x: f32, y: f32 },
     /// Retreat away from combat.
     Retreat,
+    /// Hold position and put suppressive fire on a zone, hitting whatever
+    /// is in it whether or not it can be seen.
+    SuppressArea { x: f32, y: f32, radius: f32 },
+    /// Move to a building (by `DestructibleId`) at `(x, y)` and occupy it.
+    Garrison { building: u32, x: f32, y: f32 },
+    /// Leave the occupied building and hold outside it.
+    Ungarrison,
+    /// Hold a zone of `radius` around `(x, y)`, taking the best cover in it
+    /// against enemies that threaten it.
+    DefendArea { x: f32, y: f32, radius: f32 },
+    /// Walk a loop through the waypoints, stopping to fight contacts.
+    Patrol { waypoints: Waypoints },
 }
 
 impl Default for Order {
@@ -235,6 +426,126 @@ impl Default for Order {
     }
 }
 
+/// Distance from a destination at which a movement order has arrived.
+pub const ARRIVAL_RADIUS: f32 = 1.0;
+
+impl Order {
+    /// Whether the order has been carried out by a squad at `pos`.
+    ///
+    /// Hold completes at once; retreat and area fire never do. A squad
+    /// leaving a building completes once it is back to holding.
+    pub fn is_complete(&self, pos: &Position) -> bool {
+        match self {
+            Order::Hold => true,
+            Order::MoveTo { x, y } | Order::AttackMove { x, y } | Order::Garrison { x, y, .. } => {
+                (x - pos.x).powi(2) + (y - pos.y).powi(2) < ARRIVAL_RADIUS * ARRIVAL_RADIUS
+            }
+            Order::Retreat
+            | Order::SuppressArea { .. }
+            | Order::Ungarrison
+            | Order::DefendArea { .. }
+            | Order::Patrol { .. } => false,
+        }
+    }
+
+    /// Whether this is a standing order, carried out by `defense_system`
+    /// rather than steered by the AI.
+    pub fn is_standing(&self) -> bool {
+        matches!(self, Order::DefendArea { .. } | Order::Patrol { .. })
+    }
+}
+
+/// Most waypoints a patrol route can have.
+pub const MAX_PATROL_WAYPOINTS: usize = 8;
+
+/// Route of an `Order::Patrol`. Fixed-size so that `Order` stays `Copy`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Waypoints {
+    points: [(f32, f32); MAX_PATROL_WAYPOINTS],
+    len: u8,
+}
+
+impl Waypoints {
+    /// Route through `points`; any past `MAX_PATROL_WAYPOINTS` are dropped.
+    pub fn new(points: &[(f32, f32)]) -> Self {
+        let mut route = Self { points: [(0.0, 0.0); MAX_PATROL_WAYPOINTS], len: 0 };
+        for (slot, point) in route.points.iter_mut().zip(points) {
+            *slot = *point;
+            route.len += 1;
+        }
+        route
+    }
+
+    pub fn as_slice(&self) -> &[(f32, f32)] {
+        &self.points[..self.len as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// An order waiting in a squad's `OrderQueue`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct QueuedOrder {
+    pub order: Order,
+    /// Direction to face once the order completes (radians, 0 = +x).
+    pub facing: Option<f32>,
+}
+
+/// Orders to carry out after the current `Order`, shift-queue style.
+///
+/// When the current order completes, `order_system` turns the squad to the
+/// facing it was queued with, if any, and starts the next one.
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrderQueue {
+    /// Facing to take when the current order completes.
+    pub current_facing: Option<f32>,
+    pub pending: VecDeque<QueuedOrder>,
+}
+
+impl OrderQueue {
+    /// Append an order to the end of the queue.
+    pub fn push(&mut self, order: Order, facing: Option<f32>) {
+        self.pending.push_back(QueuedOrder { order, facing });
+    }
+
+    /// Drop all queued orders and the pending facing.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.current_facing = None;
+    }
+
+    /// Take the next order, making its facing the current one.
+    pub fn advance(&mut self) -> Option<Order> {
+        let next = self.pending.pop_front()?;
+        self.current_facing = next.facing;
+        Some(next.order)
+    }
+
+    /// Whether completing the current order has any effect.
+    pub fn has_work(&self) -> bool {
+        self.current_facing.is_some() || !self.pending.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Direction a squad faces (radians, 0 = +x). Follows the direction of
+/// travel, and can be set by queued orders.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Facing(pub f32);
+
 /// AI behavior state for autonomous decision-making.
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BehaviorState {
@@ -471,6 +782,74 @@ pub struct TerrainDamageEvent {
     pub depth: f32,
 }
 
+/// Kind of explosion behind a terrain damage event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BlastKind {
+    /// Indirect artillery or mortar fire.
+    #[default]
+    Artillery,
+    /// Hand grenades and demolition charges.
+    Grenade,
+    /// A collapsing building.
+    Collapse,
+}
+
+/// Attribution for a terrain damage event: who fired it and what it was.
+///
+/// Attached to `TerrainDamageEvent` entities. Events without a source (or with
+/// `faction: None`) are unattributed and never count as friendly fire.
+#[derive(Component, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BlastSource {
+    pub faction: Option<Faction>,
+    pub kind: BlastKind,
+}
+
+/// Fraction of a smoke cloud's lifetime over which it thins out.
+const SMOKE_FADE_FRACTION: f32 = 0.25;
+
+/// A drifting smoke cloud (smoke shell or smoke grenade).
+///
+/// Spawned with a `Position`; blocks line of sight and degrades accuracy
+/// through it until it disperses.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SmokeCloud {
+    pub radius: f32,
+    /// Total lifetime in seconds.
+    pub lifetime: f32,
+    /// Seconds since deployment.
+    pub age: f32,
+}
+
+impl SmokeCloud {
+    pub fn new(radius: f32, lifetime: f32) -> Self {
+        Self { radius, lifetime, age: 0.0 }
+    }
+
+    /// Opacity (1.0 = thick, 0.0 = gone). Thins out over the last part of
+    /// the lifetime.
+    pub fn density(&self) -> f32 {
+        let fade = self.lifetime * SMOKE_FADE_FRACTION;
+        if fade <= 0.0 {
+            return if self.age < self.lifetime { 1.0 } else { 0.0 };
+        }
+        ((self.lifetime - self.age) / fade).clamp(0.0, 1.0)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.age >= self.lifetime
+    }
+}
+
+/// Smoke grenades carried by a squad.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SmokeGrenades(pub u32);
+
+impl Default for SmokeGrenades {
+    fn default() -> Self {
+        Self(2)
+    }
+}
+
 /// State of a destructible object (tree, building, etc.).
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DestructibleState {
@@ -619,6 +998,22 @@ pub struct Tree;
 #[derive(Component, Debug, Clone, Copy, Default)]
 pub struct Building;
 
+/// Number of squads a building can hold.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GarrisonCapacity(pub u32);
+
+impl Default for GarrisonCapacity {
+    fn default() -> Self {
+        Self(2)
+    }
+}
+
+/// A squad occupying a building. See `systems::garrison`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Garrisoned {
+    pub building: Entity,
+}
+
 // ============================================================================
 // BUNDLE HELPERS
 // ============================================================================
@@ -685,6 +1080,7 @@ pub struct BuildingBundle {
     pub cover: CoverProvider,
     pub dtype: DestructibleType,
     pub marker: Building,
+    pub garrison: GarrisonCapacity,
 }
 
 impl BuildingBundle {
@@ -697,6 +1093,7 @@ impl BuildingBundle {
             cover: CoverProvider::building(),
             dtype: DestructibleType::Building,
             marker: Building,
+            garrison: GarrisonCapacity::default(),
         }
     }
 }
@@ -0,0 +1,71 @@
+//! Errors returned by the simulation API.
+//!
+//! Orders are checked when they are given, so the client can tell the
+//! player why one was rejected instead of it silently doing nothing.
+
+use std::fmt;
+
+/// Why the simulation rejected a command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimError {
+    /// An ID from the client can't be used: it can't be a valid ID (e.g.
+    /// negative), is already taken, or doesn't name anything of the kind
+    /// expected.
+    InvalidId(i64),
+    /// No squad has this ID.
+    UnknownSquad(u32),
+    /// The squad has been wiped out.
+    DeadSquad(u32),
+    /// The squad can't take orders: it has surrendered or is routing.
+    NotControllable(u32),
+    /// The target point is outside the terrain bounds.
+    OutOfBounds { x: f32, y: f32 },
+    /// Squads can't stand at the target point (e.g. water).
+    Impassable { x: f32, y: f32 },
+    /// No building has this ID.
+    UnknownBuilding(u32),
+    /// No command group has this ID.
+    UnknownGroup(u32),
+    /// The squad can't attack this target (e.g. it is on the same side).
+    InvalidTarget(u32),
+    /// A patrol was given no waypoints.
+    NoWaypoints,
+    /// A group order was given no squads.
+    NoSquads,
+    /// An order trigger couldn't be built from the arguments given.
+    InvalidTrigger,
+    /// The squad and the command group are on different sides.
+    FactionMismatch(u32),
+    /// The squad has no smoke grenades left.
+    NoSmokeGrenades(u32),
+    /// A number from the client is out of range (e.g. a negative or NaN
+    /// radius).
+    InvalidValue(f32),
+}
+
+/// Result of giving an order.
+pub type OrderResult = Result<(), SimError>;
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::InvalidId(id) => write!(f, "invalid ID {}", id),
+            SimError::UnknownSquad(id) => write!(f, "no squad with ID {}", id),
+            SimError::DeadSquad(id) => write!(f, "squad {} has been wiped out", id),
+            SimError::NotControllable(id) => write!(f, "squad {} is not taking orders", id),
+            SimError::OutOfBounds { x, y } => write!(f, "({:.1}, {:.1}) is off the map", x, y),
+            SimError::Impassable { x, y } => write!(f, "({:.1}, {:.1}) is impassable", x, y),
+            SimError::UnknownBuilding(id) => write!(f, "no building with ID {}", id),
+            SimError::UnknownGroup(id) => write!(f, "no command group with ID {}", id),
+            SimError::InvalidTarget(id) => write!(f, "squad {} can't be attacked", id),
+            SimError::NoWaypoints => write!(f, "patrol has no waypoints"),
+            SimError::NoSquads => write!(f, "no squads given"),
+            SimError::InvalidTrigger => write!(f, "invalid order trigger"),
+            SimError::FactionMismatch(id) => write!(f, "command group {} belongs to the other side", id),
+            SimError::NoSmokeGrenades(id) => write!(f, "squad {} has no smoke grenades left", id),
+            SimError::InvalidValue(value) => write!(f, "{} is out of range", value),
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
@@ -87,6 +87,11 @@
 //! The buffer is deterministic: given the same `Snapshot`, the output is identical.
 //! Squads are serialized in their existing order (no sorting applied).
 
+use crate::components::{Faction, FirePriority, FireStance, SectorId, UnitClass};
+use crate::error::{OrderResult, SimError};
+use crate::systems::command::CommandLevel;
+use crate::systems::formation::Formation;
+use crate::systems::trigger::OrderTrigger;
 use crate::world::Snapshot;
 
 // ============================================================================
@@ -116,6 +121,115 @@ pub const ORDER_MOVE_TO: f32 = 1.0;
 pub const ORDER_ATTACK_MOVE: f32 = 2.0;
 /// Order type: Retreat
 pub const ORDER_RETREAT: f32 = 3.0;
+/// Order type: Suppressive fire on an area
+pub const ORDER_SUPPRESS_AREA: f32 = 4.0;
+/// Order type: Defend an area
+pub const ORDER_DEFEND_AREA: f32 = 5.0;
+/// Order type: Patrol a route
+pub const ORDER_PATROL: f32 = 6.0;
+/// Order type: Move into a building and garrison it
+pub const ORDER_GARRISON: f32 = 7.0;
+/// Order type: Leave the garrisoned building
+pub const ORDER_UNGARRISON: f32 = 8.0;
+
+// Formation constants for FFI
+/// Formation: line abreast
+pub const FORMATION_LINE: i32 = 0;
+/// Formation: column
+pub const FORMATION_COLUMN: i32 = 1;
+/// Formation: wedge
+pub const FORMATION_WEDGE: i32 = 2;
+/// Formation: skirmish (extended order)
+pub const FORMATION_SKIRMISH: i32 = 3;
+
+// Command level constants for FFI
+/// Command level: platoon
+pub const COMMAND_LEVEL_PLATOON: i32 = 0;
+/// Command level: company
+pub const COMMAND_LEVEL_COMPANY: i32 = 1;
+
+// Fire stance constants for FFI (engaging a target goes through
+// `order_attack_target`)
+/// Fire stance: fire at will
+pub const FIRE_STANCE_AT_WILL: i32 = 0;
+/// Fire stance: return fire only
+pub const FIRE_STANCE_RETURN_FIRE: i32 = 1;
+/// Fire stance: hold fire
+pub const FIRE_STANCE_HOLD_FIRE: i32 = 2;
+/// Fire stance: hand the stance back to the AI (`SimWorld::clear_fire_stance`)
+pub const FIRE_STANCE_AI: i32 = 3;
+
+// Fire priority constants for FFI
+/// Fire priority: balanced threat-weighted selection
+pub const FIRE_PRIORITY_AUTO: i32 = 0;
+/// Fire priority: closest visible enemy
+pub const FIRE_PRIORITY_CLOSEST: i32 = 1;
+/// Fire priority: prefer vehicles
+pub const FIRE_PRIORITY_PREFER_VEHICLES: i32 = 2;
+/// Fire priority: focus officers
+pub const FIRE_PRIORITY_FOCUS_OFFICERS: i32 = 3;
+/// Fire priority: focus machine guns
+pub const FIRE_PRIORITY_FOCUS_MACHINE_GUNS: i32 = 4;
+
+// Unit class constants for FFI
+/// Unit class: line infantry
+pub const UNIT_CLASS_RIFLE: i32 = 0;
+/// Unit class: machine gun team
+pub const UNIT_CLASS_MACHINE_GUN: i32 = 1;
+/// Unit class: command element
+pub const UNIT_CLASS_OFFICER: i32 = 2;
+/// Unit class: anti-tank team
+pub const UNIT_CLASS_ANTI_TANK: i32 = 3;
+/// Unit class: vehicle
+pub const UNIT_CLASS_VEHICLE: i32 = 4;
+
+// Order trigger constants for FFI, with the arguments each takes
+/// Trigger: at a tick (`tick`)
+pub const TRIGGER_AT_TICK: i32 = 0;
+/// Trigger: a squad has arrived (`squad_id`)
+pub const TRIGGER_ARRIVED: i32 = 1;
+/// Trigger: a barrage has lifted (`barrage_id`)
+pub const TRIGGER_BARRAGE_LIFTED: i32 = 2;
+/// Trigger: a faction holds a sector (`sector_x, sector_y, faction_id`)
+pub const TRIGGER_SECTOR_CAPTURED: i32 = 3;
+/// Trigger: a squad's morale is below a threshold (`squad_id, threshold`)
+pub const TRIGGER_MORALE_BELOW: i32 = 4;
+
+// Order result codes for FFI (see `SimError`)
+/// Order result: accepted
+pub const ORDER_RESULT_OK: i32 = 0;
+/// Order result: ID can't be used (negative, taken, or names nothing)
+pub const ORDER_RESULT_INVALID_ID: i32 = 1;
+/// Order result: no such squad
+pub const ORDER_RESULT_UNKNOWN_SQUAD: i32 = 2;
+/// Order result: squad wiped out
+pub const ORDER_RESULT_DEAD_SQUAD: i32 = 3;
+/// Order result: squad surrendered or routing
+pub const ORDER_RESULT_NOT_CONTROLLABLE: i32 = 4;
+/// Order result: target off the map
+pub const ORDER_RESULT_OUT_OF_BOUNDS: i32 = 5;
+/// Order result: target impassable
+pub const ORDER_RESULT_IMPASSABLE: i32 = 6;
+/// Order result: no such building
+pub const ORDER_RESULT_UNKNOWN_BUILDING: i32 = 7;
+/// Order result: no such command group
+pub const ORDER_RESULT_UNKNOWN_GROUP: i32 = 8;
+/// Order result: target squad can't be attacked
+pub const ORDER_RESULT_INVALID_TARGET: i32 = 9;
+/// Order result: patrol without waypoints
+pub const ORDER_RESULT_NO_WAYPOINTS: i32 = 10;
+/// Order result: no squads given
+pub const ORDER_RESULT_NO_SQUADS: i32 = 11;
+/// Order result: trigger ID or arguments invalid
+pub const ORDER_RESULT_INVALID_TRIGGER: i32 = 12;
+/// Order result: the bridge has no simulation world yet
+pub const ORDER_RESULT_NO_WORLD: i32 = 13;
+/// Order result: squad and command group on different sides
+pub const ORDER_RESULT_FACTION_MISMATCH: i32 = 14;
+/// Order result: no smoke grenades left
+pub const ORDER_RESULT_NO_SMOKE_GRENADES: i32 = 15;
+/// Order result: a number is out of range (e.g. negative radius)
+pub const ORDER_RESULT_INVALID_VALUE: i32 = 16;
 
 // Faction ID constants for FFI
 /// Faction ID: Blue team
@@ -142,6 +256,144 @@ pub fn faction_to_id(faction: &str) -> f32 {
     }
 }
 
+/// Convert a formation ID from FFI to a `Formation`.
+///
+/// Unknown IDs fail with `InvalidId`.
+#[inline]
+pub fn formation_from_id(id: i32) -> Result<Formation, SimError> {
+    match id {
+        FORMATION_LINE => Ok(Formation::Line),
+        FORMATION_COLUMN => Ok(Formation::Column),
+        FORMATION_WEDGE => Ok(Formation::Wedge),
+        FORMATION_SKIRMISH => Ok(Formation::Skirmish),
+        _ => Err(SimError::InvalidId(id as i64)),
+    }
+}
+
+/// Convert a command level ID from FFI to a `CommandLevel`.
+///
+/// Unknown IDs fail with `InvalidId`.
+#[inline]
+pub fn command_level_from_id(id: i32) -> Result<CommandLevel, SimError> {
+    match id {
+        COMMAND_LEVEL_PLATOON => Ok(CommandLevel::Platoon),
+        COMMAND_LEVEL_COMPANY => Ok(CommandLevel::Company),
+        _ => Err(SimError::InvalidId(id as i64)),
+    }
+}
+
+/// Convert a fire stance ID from FFI to a `FireStance`.
+///
+/// Unknown IDs fail with `InvalidId`. `FIRE_STANCE_AI` isn't a stance;
+/// callers handle it before converting.
+#[inline]
+pub fn fire_stance_from_id(id: i32) -> Result<FireStance, SimError> {
+    match id {
+        FIRE_STANCE_AT_WILL => Ok(FireStance::FireAtWill),
+        FIRE_STANCE_RETURN_FIRE => Ok(FireStance::ReturnFire),
+        FIRE_STANCE_HOLD_FIRE => Ok(FireStance::HoldFire),
+        _ => Err(SimError::InvalidId(id as i64)),
+    }
+}
+
+/// Convert a fire priority ID from FFI to a `FirePriority`.
+///
+/// Unknown IDs fail with `InvalidId`.
+#[inline]
+pub fn fire_priority_from_id(id: i32) -> Result<FirePriority, SimError> {
+    match id {
+        FIRE_PRIORITY_AUTO => Ok(FirePriority::Auto),
+        FIRE_PRIORITY_CLOSEST => Ok(FirePriority::Closest),
+        FIRE_PRIORITY_PREFER_VEHICLES => Ok(FirePriority::PreferVehicles),
+        FIRE_PRIORITY_FOCUS_OFFICERS => Ok(FirePriority::FocusOfficers),
+        FIRE_PRIORITY_FOCUS_MACHINE_GUNS => Ok(FirePriority::FocusMachineGuns),
+        _ => Err(SimError::InvalidId(id as i64)),
+    }
+}
+
+/// Convert a unit class ID from FFI to a `UnitClass`.
+///
+/// Unknown IDs fail with `InvalidId`.
+#[inline]
+pub fn unit_class_from_id(id: i32) -> Result<UnitClass, SimError> {
+    match id {
+        UNIT_CLASS_RIFLE => Ok(UnitClass::Rifle),
+        UNIT_CLASS_MACHINE_GUN => Ok(UnitClass::MachineGun),
+        UNIT_CLASS_OFFICER => Ok(UnitClass::Officer),
+        UNIT_CLASS_ANTI_TANK => Ok(UnitClass::AntiTank),
+        UNIT_CLASS_VEHICLE => Ok(UnitClass::Vehicle),
+        _ => Err(SimError::InvalidId(id as i64)),
+    }
+}
+
+/// Convert an ID from FFI, rejecting values that can't be IDs.
+#[inline]
+pub fn id_from_ffi(id: i32) -> Result<u32, SimError> {
+    u32::try_from(id).map_err(|_| SimError::InvalidId(id as i64))
+}
+
+/// Convert an order result to its code for FFI (`ORDER_RESULT_*`).
+pub fn order_result_to_id(result: &OrderResult) -> i32 {
+    match result {
+        Ok(()) => ORDER_RESULT_OK,
+        Err(SimError::InvalidId(_)) => ORDER_RESULT_INVALID_ID,
+        Err(SimError::UnknownSquad(_)) => ORDER_RESULT_UNKNOWN_SQUAD,
+        Err(SimError::DeadSquad(_)) => ORDER_RESULT_DEAD_SQUAD,
+        Err(SimError::NotControllable(_)) => ORDER_RESULT_NOT_CONTROLLABLE,
+        Err(SimError::OutOfBounds { .. }) => ORDER_RESULT_OUT_OF_BOUNDS,
+        Err(SimError::Impassable { .. }) => ORDER_RESULT_IMPASSABLE,
+        Err(SimError::UnknownBuilding(_)) => ORDER_RESULT_UNKNOWN_BUILDING,
+        Err(SimError::UnknownGroup(_)) => ORDER_RESULT_UNKNOWN_GROUP,
+        Err(SimError::InvalidTarget(_)) => ORDER_RESULT_INVALID_TARGET,
+        Err(SimError::NoWaypoints) => ORDER_RESULT_NO_WAYPOINTS,
+        Err(SimError::NoSquads) => ORDER_RESULT_NO_SQUADS,
+        Err(SimError::InvalidTrigger) => ORDER_RESULT_INVALID_TRIGGER,
+        Err(SimError::FactionMismatch(_)) => ORDER_RESULT_FACTION_MISMATCH,
+        Err(SimError::NoSmokeGrenades(_)) => ORDER_RESULT_NO_SMOKE_GRENADES,
+        Err(SimError::InvalidValue(_)) => ORDER_RESULT_INVALID_VALUE,
+    }
+}
+
+/// Convert a trigger ID and its arguments from FFI to an `OrderTrigger`.
+///
+/// Fails with `InvalidTrigger` for unknown IDs, too few arguments, or
+/// arguments that aren't what the trigger takes (a fractional or
+/// non-finite number where it takes a whole one, an unknown faction), and
+/// with `InvalidId` for squad or barrage IDs out of range.
+pub fn order_trigger_from_id(id: i32, args: &[f32]) -> Result<OrderTrigger, SimError> {
+    let arg = |i: usize| args.get(i).copied().ok_or(SimError::InvalidTrigger);
+    let whole = |i: usize| arg(i).and_then(|v| if v.is_finite() && v.fract() == 0.0 { Ok(v) } else { Err(SimError::InvalidTrigger) });
+    let id_arg = |i: usize| whole(i).and_then(|v| u32::try_from(v as i64).map_err(|_| SimError::InvalidId(v as i64)));
+    let sector_arg = |i: usize| whole(i).and_then(|v| i32::try_from(v as i64).map_err(|_| SimError::InvalidTrigger));
+    match id {
+        TRIGGER_AT_TICK => {
+            let tick = whole(0)?;
+            if tick < 0.0 {
+                return Err(SimError::InvalidTrigger);
+            }
+            Ok(OrderTrigger::AtTick(tick as u64))
+        }
+        TRIGGER_ARRIVED => Ok(OrderTrigger::Arrived { squad: id_arg(0)? }),
+        TRIGGER_BARRAGE_LIFTED => Ok(OrderTrigger::BarrageLifted { barrage: id_arg(0)? }),
+        TRIGGER_SECTOR_CAPTURED => {
+            let faction = match arg(2)? {
+                FACTION_BLUE => Faction::Blue,
+                FACTION_RED => Faction::Red,
+                _ => return Err(SimError::InvalidTrigger),
+            };
+            Ok(OrderTrigger::SectorCaptured { sector: SectorId(sector_arg(0)?, sector_arg(1)?), faction })
+        }
+        TRIGGER_MORALE_BELOW => {
+            let threshold = arg(1)?;
+            if !threshold.is_finite() {
+                return Err(SimError::InvalidTrigger);
+            }
+            Ok(OrderTrigger::MoraleBelow { squad: id_arg(0)?, threshold })
+        }
+        _ => Err(SimError::InvalidTrigger),
+    }
+}
+
 /// Convert an order string to its numeric ID for FFI.
 /// 
 /// # Mapping
@@ -149,6 +401,11 @@ pub fn faction_to_id(faction: &str) -> f32 {
 /// - "MoveTo(...)" → 1.0
 /// - "AttackMove(...)" → 2.0
 /// - "Retreat" → 3.0
+/// - "SuppressArea(...)" → 4.0
+/// - "DefendArea(...)" → 5.0
+/// - "Patrol(...)" → 6.0
+/// - "Garrison(...)" → 7.0
+/// - "Ungarrison" → 8.0
 /// - Unknown → 0.0 (defaults to Hold)
 #[inline]
 pub fn order_to_id(order: &str) -> f32 {
@@ -160,6 +417,16 @@ pub fn order_to_id(order: &str) -> f32 {
         ORDER_ATTACK_MOVE
     } else if order == "Retreat" {
         ORDER_RETREAT
+    } else if order.starts_with("SuppressArea") {
+        ORDER_SUPPRESS_AREA
+    } else if order.starts_with("DefendArea") {
+        ORDER_DEFEND_AREA
+    } else if order.starts_with("Patrol") {
+        ORDER_PATROL
+    } else if order.starts_with("Garrison") {
+        ORDER_GARRISON
+    } else if order == "Ungarrison" {
+        ORDER_UNGARRISON
     } else {
         ORDER_HOLD // Default to Hold for unknown
     }
@@ -525,7 +792,6 @@ impl BattleSummary {
 mod tests {
     use super::*;
     use crate::api::SimWorld;
-    use crate::components::Faction;
     use crate::systems::SimConfig;
 
     #[test]
@@ -681,12 +947,93 @@ mod tests {
         assert_eq!(faction_to_id("Unknown"), FACTION_BLUE); // Default
     }
 
+    #[test]
+    fn test_formation_from_id() {
+        assert_eq!(formation_from_id(FORMATION_LINE), Ok(Formation::Line));
+        assert_eq!(formation_from_id(FORMATION_COLUMN), Ok(Formation::Column));
+        assert_eq!(formation_from_id(FORMATION_SKIRMISH), Ok(Formation::Skirmish));
+        assert_eq!(formation_from_id(-1), Err(SimError::InvalidId(-1)));
+    }
+
+    #[test]
+    fn test_command_level_from_id() {
+        assert_eq!(command_level_from_id(COMMAND_LEVEL_PLATOON), Ok(CommandLevel::Platoon));
+        assert_eq!(command_level_from_id(COMMAND_LEVEL_COMPANY), Ok(CommandLevel::Company));
+        assert_eq!(command_level_from_id(7), Err(SimError::InvalidId(7)));
+    }
+
+    #[test]
+    fn test_fire_stance_from_id() {
+        assert_eq!(fire_stance_from_id(FIRE_STANCE_RETURN_FIRE), Ok(FireStance::ReturnFire));
+        assert_eq!(fire_stance_from_id(FIRE_STANCE_HOLD_FIRE), Ok(FireStance::HoldFire));
+        assert_eq!(fire_stance_from_id(FIRE_STANCE_AT_WILL), Ok(FireStance::FireAtWill));
+        assert_eq!(fire_stance_from_id(FIRE_STANCE_AI), Err(SimError::InvalidId(3)));
+    }
+
+    #[test]
+    fn test_fire_priority_and_unit_class_from_id() {
+        assert_eq!(fire_priority_from_id(FIRE_PRIORITY_FOCUS_MACHINE_GUNS), Ok(FirePriority::FocusMachineGuns));
+        assert_eq!(fire_priority_from_id(FIRE_PRIORITY_AUTO), Ok(FirePriority::Auto));
+        assert_eq!(fire_priority_from_id(-1), Err(SimError::InvalidId(-1)));
+        assert_eq!(unit_class_from_id(UNIT_CLASS_VEHICLE), Ok(UnitClass::Vehicle));
+        assert_eq!(unit_class_from_id(UNIT_CLASS_RIFLE), Ok(UnitClass::Rifle));
+        assert_eq!(unit_class_from_id(9), Err(SimError::InvalidId(9)));
+    }
+
+    #[test]
+    fn test_order_results_to_ffi() {
+        assert_eq!(id_from_ffi(7), Ok(7));
+        assert_eq!(id_from_ffi(-1), Err(SimError::InvalidId(-1)));
+        assert_eq!(order_result_to_id(&Ok(())), ORDER_RESULT_OK);
+        assert_eq!(order_result_to_id(&id_from_ffi(-1).map(|_| ())), ORDER_RESULT_INVALID_ID);
+
+        let mut sim = SimWorld::new();
+        assert_eq!(order_result_to_id(&sim.order_hold(3)), ORDER_RESULT_UNKNOWN_SQUAD);
+        sim.spawn_ai_squad(3, Faction::Blue, 0.0, 0.0);
+        assert_eq!(order_result_to_id(&sim.order_move(3, 1000.0, 0.0)), ORDER_RESULT_OUT_OF_BOUNDS);
+        assert_eq!(order_result_to_id(&sim.clear_order_queue(4)), ORDER_RESULT_UNKNOWN_SQUAD);
+        assert_eq!(order_result_to_id(&sim.assign_squad(3, 1)), ORDER_RESULT_UNKNOWN_GROUP);
+        assert_eq!(order_result_to_id(&sim.add_rally_point(1, Faction::Blue, 0.0, 0.0)), ORDER_RESULT_OK);
+        assert_eq!(order_result_to_id(&sim.add_rally_point(1, Faction::Blue, 0.0, 0.0)), ORDER_RESULT_INVALID_ID);
+        assert_eq!(order_result_to_id(&sim.set_group_objective(1, 0.0, 0.0)), ORDER_RESULT_UNKNOWN_GROUP);
+        for _ in 0..crate::components::SmokeGrenades::default().0 {
+            assert_eq!(order_result_to_id(&sim.throw_smoke(3, 5.0, 0.0)), ORDER_RESULT_OK);
+        }
+        assert_eq!(order_result_to_id(&sim.throw_smoke(3, 5.0, 0.0)), ORDER_RESULT_NO_SMOKE_GRENADES);
+    }
+
+    #[test]
+    fn test_order_trigger_from_id() {
+        assert_eq!(order_trigger_from_id(TRIGGER_AT_TICK, &[300.0]), Ok(OrderTrigger::AtTick(300)));
+        assert_eq!(
+            order_trigger_from_id(TRIGGER_SECTOR_CAPTURED, &[1.0, -2.0, FACTION_RED]),
+            Ok(OrderTrigger::SectorCaptured { sector: SectorId(1, -2), faction: Faction::Red })
+        );
+        assert_eq!(order_trigger_from_id(TRIGGER_MORALE_BELOW, &[3.0]), Err(SimError::InvalidTrigger)); // Missing threshold
+        assert_eq!(order_trigger_from_id(9, &[1.0]), Err(SimError::InvalidTrigger));
+
+        // Arguments that casting would quietly turn into something else
+        assert_eq!(order_trigger_from_id(TRIGGER_ARRIVED, &[-1.0]), Err(SimError::InvalidId(-1)));
+        assert_eq!(order_trigger_from_id(TRIGGER_ARRIVED, &[f32::NAN]), Err(SimError::InvalidTrigger));
+        assert_eq!(order_trigger_from_id(TRIGGER_BARRAGE_LIFTED, &[1.5]), Err(SimError::InvalidTrigger));
+        assert_eq!(order_trigger_from_id(TRIGGER_AT_TICK, &[-1.0]), Err(SimError::InvalidTrigger));
+        assert_eq!(order_trigger_from_id(TRIGGER_AT_TICK, &[f32::INFINITY]), Err(SimError::InvalidTrigger));
+        assert_eq!(order_trigger_from_id(TRIGGER_MORALE_BELOW, &[3.0, f32::NAN]), Err(SimError::InvalidTrigger));
+        assert_eq!(order_trigger_from_id(TRIGGER_SECTOR_CAPTURED, &[0.5, 0.0, FACTION_RED]), Err(SimError::InvalidTrigger));
+        assert_eq!(order_trigger_from_id(TRIGGER_SECTOR_CAPTURED, &[0.0, 0.0, 2.0]), Err(SimError::InvalidTrigger));
+    }
+
     #[test]
     fn test_order_to_id() {
         assert_eq!(order_to_id("Hold"), ORDER_HOLD);
         assert_eq!(order_to_id("MoveTo(10.0,20.0)"), ORDER_MOVE_TO);
         assert_eq!(order_to_id("AttackMove(5.0,5.0)"), ORDER_ATTACK_MOVE);
         assert_eq!(order_to_id("Retreat"), ORDER_RETREAT);
+        assert_eq!(order_to_id("SuppressArea(5.0,5.0,10.0)"), ORDER_SUPPRESS_AREA);
+        assert_eq!(order_to_id("DefendArea(5.0,5.0,20.0)"), ORDER_DEFEND_AREA);
+        assert_eq!(order_to_id("Patrol(3)"), ORDER_PATROL);
+        assert_eq!(order_to_id("Garrison(4)"), ORDER_GARRISON);
+        assert_eq!(order_to_id("Ungarrison"), ORDER_UNGARRISON);
         assert_eq!(order_to_id("Unknown"), ORDER_HOLD); // Default
     }
 
@@ -809,8 +1156,8 @@ mod tests {
         sim.spawn_ai_squad(100, Faction::Red, 30.0, 0.0);
         
         // Issue attack orders
-        sim.order_attack_move(1, 30.0, 0.0);
-        sim.order_attack_move(100, 0.0, 0.0);
+        sim.order_attack_move(1, 30.0, 0.0).unwrap();
+        sim.order_attack_move(100, 0.0, 0.0).unwrap();
         
         // Run simulation for a while
         for _ in 0..100 {
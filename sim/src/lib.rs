@@ -16,7 +16,9 @@
 
 pub mod api;
 pub mod components;
+pub mod error;
 pub mod godot_bridge;
+pub mod los;
 pub mod profiler;
 pub mod spatial;
 pub mod systems;
@@ -24,7 +26,9 @@ pub mod terrain;
 pub mod world;
 
 pub use components::*;
+pub use error::{OrderResult, SimError};
 pub use godot_bridge::snapshot_to_flatbuffer;
+pub use los::{LineOfSight, LosBlocker};
 pub use profiler::{Profiler, StressProfiler, SectionStats};
 pub use spatial::{SpatialGrid, SpatialEntry};
 pub use systems::*;
@@ -0,0 +1,347 @@
+//! Line-of-sight service over the terrain grid.
+//!
+//! Combines terrain ray-marching (`TerrainGrid::has_line_of_sight`) with
+//! dynamic blockers such as intact buildings. Results are cached per tick,
+//! keyed by a pair of coarse cache cells, so squads that stand close together
+//! share the same ray. The cached ray runs between the two cell centres, so
+//! a result never depends on which squad asked first.
+//!
+//! Smoke clouds obscure sight lines that pass through them; thick smoke
+//! blocks sight entirely.
+//!
+//! LOS is treated as symmetric: if A can see B, B can see A.
+
+use crate::components::*;
+use crate::terrain::TerrainGrid;
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Size of a cache cell in world units. Points within the same cache cell
+/// share LOS results for the current tick, traced from the cell centre.
+const LOS_CACHE_CELL_SIZE: f32 = 2.0;
+
+/// Fraction of a building's cover radius that blocks sight (its footprint).
+pub const BUILDING_FOOTPRINT_FRACTION: f32 = 0.5;
+
+/// Obscuration (0.0 - 1.0) at or above which smoke blocks sight.
+pub const SMOKE_BLOCK_THRESHOLD: f32 = 0.5;
+
+/// Cache key: an ordered pair of cache cells.
+type LosCacheKey = ((i32, i32), (i32, i32));
+
+/// A circular obstacle that blocks line of sight (e.g. an intact building).
+#[derive(Debug, Clone, Copy)]
+pub struct LosBlocker {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+}
+
+impl LosBlocker {
+    /// Check whether the segment between two points passes through this blocker.
+    /// Segments that start or end inside the blocker are not blocked by it.
+    fn blocks_segment(&self, from_x: f32, from_y: f32, to_x: f32, to_y: f32) -> bool {
+        let radius_sq = self.radius * self.radius;
+        let inside = |x: f32, y: f32| (x - self.x).powi(2) + (y - self.y).powi(2) <= radius_sq;
+        if inside(from_x, from_y) || inside(to_x, to_y) {
+            return false;
+        }
+
+        let dx = to_x - from_x;
+        let dy = to_y - from_y;
+        let len_sq = dx * dx + dy * dy;
+        if len_sq < 0.0001 {
+            return false;
+        }
+        let t = (((self.x - from_x) * dx + (self.y - from_y) * dy) / len_sq).clamp(0.0, 1.0);
+        inside(from_x + dx * t, from_y + dy * t)
+    }
+}
+
+/// A smoke cloud sampled for the current tick.
+#[derive(Debug, Clone, Copy)]
+pub struct SmokeScreen {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub density: f32,
+}
+
+impl SmokeScreen {
+    /// How much this cloud obscures the segment between two points
+    /// (0.0 = clear). Thickest through the middle, thin at the edge.
+    /// Segments starting or ending inside the cloud pass through it.
+    fn obscuration(&self, from_x: f32, from_y: f32, to_x: f32, to_y: f32) -> f32 {
+        let dx = to_x - from_x;
+        let dy = to_y - from_y;
+        let len_sq = dx * dx + dy * dy;
+        let t = if len_sq < 0.0001 {
+            0.0
+        } else {
+            (((self.x - from_x) * dx + (self.y - from_y) * dy) / len_sq).clamp(0.0, 1.0)
+        };
+        let dist_sq = (from_x + dx * t - self.x).powi(2) + (from_y + dy * t - self.y).powi(2);
+        let radius_sq = self.radius * self.radius;
+        if dist_sq >= radius_sq {
+            return 0.0;
+        }
+        self.density * (1.0 - dist_sq / radius_sq)
+    }
+}
+
+/// Resource providing cached line-of-sight queries.
+///
+/// Rebuilt at the start of every tick by `los_update_system`.
+#[derive(Resource, Default)]
+pub struct LineOfSight {
+    /// Dynamic blockers (intact buildings).
+    blockers: Vec<LosBlocker>,
+    /// Smoke clouds.
+    smoke: Vec<SmokeScreen>,
+    /// Per-tick cache of LOS results keyed by cache-cell pairs.
+    cache: Mutex<HashMap<LosCacheKey, bool>>,
+}
+
+impl LineOfSight {
+    /// Create an empty LOS service.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clear cached results, dynamic blockers and smoke.
+    pub fn clear(&mut self) {
+        self.blockers.clear();
+        self.smoke.clear();
+        if let Ok(cache) = self.cache.get_mut() {
+            cache.clear();
+        }
+    }
+
+    /// Add a dynamic blocker.
+    pub fn add_blocker(&mut self, x: f32, y: f32, radius: f32) {
+        self.blockers.push(LosBlocker { x, y, radius });
+    }
+
+    /// Get all dynamic blockers (for debugging/visualization).
+    pub fn blockers(&self) -> &[LosBlocker] {
+        &self.blockers
+    }
+
+    /// Add a smoke cloud.
+    pub fn add_smoke(&mut self, x: f32, y: f32, radius: f32, density: f32) {
+        self.smoke.push(SmokeScreen { x, y, radius, density });
+    }
+
+    /// Get all smoke clouds.
+    pub fn smoke(&self) -> &[SmokeScreen] {
+        &self.smoke
+    }
+
+    /// How much smoke obscures the segment between two points
+    /// (0.0 = clear, 1.0 = opaque). Overlapping clouds add up.
+    pub fn obscuration(&self, from_x: f32, from_y: f32, to_x: f32, to_y: f32) -> f32 {
+        self.smoke.iter()
+            .map(|s| s.obscuration(from_x, from_y, to_x, to_y))
+            .sum::<f32>()
+            .min(1.0)
+    }
+
+    /// Number of cached LOS results this tick.
+    pub fn cached_count(&self) -> usize {
+        self.cache.lock().map(|c| c.len()).unwrap_or(0)
+    }
+
+    #[inline]
+    fn cache_cell(x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / LOS_CACHE_CELL_SIZE).floor() as i32,
+            (y / LOS_CACHE_CELL_SIZE).floor() as i32,
+        )
+    }
+
+    #[inline]
+    fn cell_centre(cell: (i32, i32)) -> (f32, f32) {
+        (
+            (cell.0 as f32 + 0.5) * LOS_CACHE_CELL_SIZE,
+            (cell.1 as f32 + 0.5) * LOS_CACHE_CELL_SIZE,
+        )
+    }
+
+    /// Check line of sight between two points, using the per-tick cache.
+    ///
+    /// Both points are snapped to their cache-cell centres before tracing, so
+    /// every query for the same pair of cells gets the same answer.
+    /// `terrain` may be `None` when no terrain is loaded, in which case only
+    /// dynamic blockers are considered.
+    pub fn has_los(
+        &self,
+        terrain: Option<&TerrainGrid>,
+        from_x: f32,
+        from_y: f32,
+        to_x: f32,
+        to_y: f32,
+    ) -> bool {
+        let a = Self::cache_cell(from_x, from_y);
+        let b = Self::cache_cell(to_x, to_y);
+        let key = if a < b { (a, b) } else { (b, a) };
+
+        let cached = self.cache.lock().ok().and_then(|c| c.get(&key).copied());
+        if let Some(visible) = cached {
+            return visible;
+        }
+
+        let (ax, ay) = Self::cell_centre(key.0);
+        let (bx, by) = Self::cell_centre(key.1);
+        let visible = self.trace(terrain, ax, ay, bx, by);
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key, visible);
+        }
+        visible
+    }
+
+    /// Trace a ray without consulting the cache.
+    pub fn trace(
+        &self,
+        terrain: Option<&TerrainGrid>,
+        from_x: f32,
+        from_y: f32,
+        to_x: f32,
+        to_y: f32,
+    ) -> bool {
+        if self.obscuration(from_x, from_y, to_x, to_y) >= SMOKE_BLOCK_THRESHOLD {
+            return false;
+        }
+        self.trace_solid(terrain, from_x, from_y, to_x, to_y)
+    }
+
+    /// Trace a ray against terrain and buildings only, ignoring smoke and
+    /// the cache. Used for fire aimed at a point rather than a spotted enemy.
+    pub fn trace_solid(
+        &self,
+        terrain: Option<&TerrainGrid>,
+        from_x: f32,
+        from_y: f32,
+        to_x: f32,
+        to_y: f32,
+    ) -> bool {
+        if self.blockers.iter().any(|b| b.blocks_segment(from_x, from_y, to_x, to_y)) {
+            return false;
+        }
+        terrain
+            .map(|t| t.has_line_of_sight(from_x, from_y, to_x, to_y))
+            .unwrap_or(true)
+    }
+}
+
+/// System that resets the LOS cache and rebuilds dynamic blockers and smoke
+/// each tick.
+///
+/// ## Data Access
+/// - Reads: Position, CoverProvider, DestructibleState (buildings), SmokeCloud
+/// - Writes: LineOfSight (resource)
+pub fn los_update_system(
+    mut los: ResMut<LineOfSight>,
+    buildings: Query<(&Position, &CoverProvider, &DestructibleState), With<Building>>,
+    smoke: Query<(&Position, &SmokeCloud)>,
+) {
+    los.clear();
+    for (pos, cover, state) in buildings.iter() {
+        if *state == DestructibleState::Intact {
+            los.add_blocker(pos.x, pos.y, cover.radius * BUILDING_FOOTPRINT_FRACTION);
+        }
+    }
+    for (pos, cloud) in smoke.iter() {
+        los.add_smoke(pos.x, pos.y, cloud.radius, cloud.density());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_building_blocks_los() {
+        let mut los = LineOfSight::new();
+        assert!(los.has_los(None, -20.0, 0.0, 20.0, 0.0));
+
+        los.clear();
+        los.add_blocker(0.0, 0.0, 3.0);
+        assert!(!los.has_los(None, -20.0, 0.0, 20.0, 0.0));
+        assert!(los.has_los(None, -20.0, 10.0, 20.0, 10.0));
+        // Standing inside the footprint does not block your own sight
+        assert!(los.has_los(None, 1.0, 0.0, 20.0, 0.0));
+    }
+
+    #[test]
+    fn test_cached_los_independent_of_query_order() {
+        // The blocker's edge splits cache cell (0, 0): an exact ray from its
+        // left side is blocked, one from its right side is not.
+        let mut los = LineOfSight::new();
+        los.add_blocker(10.0, 0.0, 1.5);
+        assert!(!los.trace(None, 0.2, 1.0, 20.0, 1.0));
+        assert!(los.trace(None, 1.8, 1.9, 20.0, 1.9));
+
+        let left_first = {
+            let first = los.has_los(None, 0.2, 1.0, 20.0, 1.0);
+            (first, los.has_los(None, 1.8, 1.9, 20.0, 1.9))
+        };
+        los.clear();
+        los.add_blocker(10.0, 0.0, 1.5);
+        let right_first = {
+            let first = los.has_los(None, 1.8, 1.9, 20.0, 1.9);
+            (los.has_los(None, 0.2, 1.0, 20.0, 1.0), first)
+        };
+        assert_eq!(left_first.0, left_first.1);
+        assert_eq!(left_first, right_first);
+    }
+
+    #[test]
+    fn test_same_cell_los_is_traced() {
+        let mut los = LineOfSight::new();
+        assert!(los.has_los(None, 0.2, 0.2, 1.8, 1.8));
+        // Two squads deep inside the same thick cloud cannot see each other
+        los.clear();
+        los.add_smoke(1.0, 1.0, 10.0, 1.0);
+        assert!(!los.has_los(None, 0.2, 0.2, 1.8, 1.8));
+    }
+
+    #[test]
+    fn test_smoke_blocks_los_and_thins_at_edge() {
+        let mut los = LineOfSight::new();
+        los.add_smoke(0.0, 0.0, 10.0, 1.0);
+        assert!(!los.has_los(None, -20.0, 0.0, 20.0, 0.0));
+        // From inside the cloud you can't see out
+        assert!(!los.trace(None, 0.0, 0.0, 30.0, 30.0));
+
+        // Grazing the edge: visible but partly obscured
+        let edge = los.obscuration(-20.0, 8.0, 20.0, 8.0);
+        assert!(edge > 0.0 && edge < SMOKE_BLOCK_THRESHOLD);
+        assert!(los.has_los(None, -20.0, 8.0, 20.0, 8.0));
+        assert_eq!(los.obscuration(-20.0, 15.0, 20.0, 15.0), 0.0);
+
+        // Dispersing smoke no longer blocks
+        los.clear();
+        los.add_smoke(0.0, 0.0, 10.0, 0.3);
+        assert!(los.has_los(None, -20.0, 0.0, 20.0, 0.0));
+    }
+
+    #[test]
+    fn test_los_update_ignores_destroyed_buildings() {
+        let mut world = World::new();
+        world.insert_resource(LineOfSight::new());
+        world.spawn(BuildingBundle::new(1, 0.0, 0.0));
+        let mut ruin = BuildingBundle::new(2, 50.0, 0.0);
+        ruin.state = DestructibleState::Destroyed;
+        world.spawn(ruin);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(los_update_system);
+        schedule.run(&mut world);
+
+        let los = world.resource::<LineOfSight>();
+        assert_eq!(los.blockers().len(), 1);
+        assert!(!los.has_los(None, -20.0, 0.0, 20.0, 0.0));
+        assert!(los.has_los(None, 30.0, 0.0, 70.0, 0.0));
+    }
+}
@@ -175,7 +175,10 @@ impl SpatialGrid {
 /// System that rebuilds the spatial grid each frame.
 pub fn spatial_grid_update_system(
     mut grid: ResMut<SpatialGrid>,
-    query: Query<(Entity, &crate::components::Position, &crate::components::Faction, &crate::components::Health)>,
+    query: Query<
+        (Entity, &crate::components::Position, &crate::components::Faction, &crate::components::Health),
+        Without<crate::components::Surrendered>,
+    >,
 ) {
     grid.clear();
     
@@ -24,23 +24,101 @@
 //! with each other as long as they run after the spatial grid is updated.
 
 use crate::components::*;
-use crate::spatial::SpatialGrid;
+use crate::los::LineOfSight;
+use crate::spatial::{SpatialEntry, SpatialGrid};
+use crate::systems::defense::Station;
+use crate::systems::flowfield::FlowFields;
+use crate::systems::garrison::GARRISON_SPOTTING_RANGE;
 use crate::systems::movement::DeltaTime;
 use crate::systems::performance::SimTick;
+use crate::systems::retreat::Fallback;
+use crate::terrain::{spotting_range_factor, TerrainGrid, TerrainResource};
 use bevy_ecs::prelude::*;
 
+/// Maximum number of LOS checks per squad when looking for threats.
+/// Candidates that could be spotted at all are checked closest first; once
+/// the budget is spent, farther ones go unseen.
+pub const MAX_THREAT_LOS_CHECKS: usize = 16;
+
+/// Distances at which candidate positions are sampled around a squad when
+/// looking for better ground.
+const POSITION_SEARCH_RADII: [f32; 2] = [12.0, 24.0];
+
+/// Number of directions sampled at each search radius.
+const POSITION_SEARCH_DIRECTIONS: usize = 8;
+
+/// Ticks between position searches for a squad (staggered across squads).
+const POSITION_SEARCH_INTERVAL: u64 = 15;
+
+/// How much more valuable a position must be before a squad moves to it.
+const POSITION_IMPROVEMENT_THRESHOLD: f32 = 0.15;
+
+/// Height advantage (world units) at which the value of high ground saturates.
+const ELEVATION_VALUE_SATURATION: f32 = 6.0;
+
+/// Aggression below which AI squads lie in ambush, holding fire.
+const AMBUSH_AGGRESSION: f32 = 0.35;
+
+/// Fraction of fire range an ambushing squad lets the enemy close to
+/// before opening fire.
+const AMBUSH_RANGE_FRACTION: f32 = 0.5;
+
 // ============================================================================
 // THREAT AWARENESS SYSTEM
 // ============================================================================
 
+/// Whether a squad at `eye` could spot an enemy before line of sight is
+/// checked: garrisoned enemies only show up within `GARRISON_SPOTTING_RANGE`.
+pub fn spottable(eye: (f32, f32), enemy: &SpatialEntry, garrisoned: bool) -> bool {
+    !garrisoned || (enemy.x - eye.0).hypot(enemy.y - eye.1) <= GARRISON_SPOTTING_RANGE
+}
+
+/// Whether a squad at `eye` can see a point. Everything is in sight
+/// without a `LineOfSight` resource.
+pub fn in_sight(los: Option<&LineOfSight>, terrain: Option<&TerrainGrid>, eye: (f32, f32), target: (f32, f32)) -> bool {
+    los.is_none_or(|l| l.has_los(terrain, eye.0, eye.1, target.0, target.1))
+}
+
+/// Enemies a squad at `eye` can see among `candidates`, which are sorted
+/// closest first. Spends at most `MAX_THREAT_LOS_CHECKS` LOS checks, only
+/// on enemies it could spot at all; enemies past the budget go unseen.
+pub fn visible_enemies<'a>(
+    candidates: &'a [SpatialEntry],
+    eye: (f32, f32),
+    garrisoned: impl Fn(Entity) -> bool + 'a,
+    los: Option<&'a LineOfSight>,
+    terrain: Option<&'a TerrainGrid>,
+) -> impl Iterator<Item = &'a SpatialEntry> + 'a {
+    candidates.iter()
+        .filter(move |e| spottable(eye, e, garrisoned(e.entity)))
+        .take(MAX_THREAT_LOS_CHECKS)
+        .filter(move |e| in_sight(los, terrain, eye, (e.x, e.y)))
+}
+
+/// Nearest enemy a squad at `eye` can see among `candidates` (see
+/// `visible_enemies`).
+pub fn nearest_visible_enemy<'a>(
+    candidates: &'a [SpatialEntry],
+    eye: (f32, f32),
+    garrisoned: impl Fn(Entity) -> bool + 'a,
+    los: Option<&'a LineOfSight>,
+    terrain: Option<&'a TerrainGrid>,
+) -> Option<&'a SpatialEntry> {
+    visible_enemies(candidates, eye, garrisoned, los, terrain).next()
+}
+
 /// System that updates threat awareness for AI-controlled squads.
-/// Uses spatial grid for efficient enemy detection.
+/// Uses spatial grid for efficient enemy detection; only enemies in line of
+/// sight are considered, and only as many as `visible_enemies` checks.
 /// 
 /// ## Complexity: O(n × k) where n = AI units, k = avg enemies per query
 /// 
 /// ## Data Access
-/// - Reads: DeltaTime, SpatialGrid, SimTick, Position, Faction, SquadStats, SimLod
+/// - Reads: DeltaTime, SpatialGrid, SimTick, TerrainResource, LineOfSight, Position, Faction, SquadStats, SimLod, Garrisoned
 /// - Writes: ThreatAwareness (ONLY)
+///
+/// Squads on high ground spot farther. Garrisoned enemies are only spotted
+/// up close.
 /// 
 /// ## Parallelization
 /// This system can run in parallel with `nearby_friendlies_system` because
@@ -49,19 +127,23 @@ pub fn threat_awareness_system(
     dt: Res<DeltaTime>,
     grid: Res<SpatialGrid>,
     tick: Option<Res<SimTick>>,
+    terrain: Option<Res<TerrainResource>>,
+    los: Option<Res<LineOfSight>>,
     mut ai_query: Query<(
-        Entity,
         &Position,
         &Faction,
         &SquadStats,
         &mut ThreatAwareness,
         Option<&SimLod>,
     ), With<AIControlled>>,
+    garrisoned: Query<(), With<Garrisoned>>,
 ) {
     let delta = dt.0;
     let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
+    let terrain_guard = terrain.as_ref().and_then(|t| t.read());
+    let terrain_grid = terrain_guard.as_deref();
 
-    for (entity, pos, faction, stats, mut threat, lod) in ai_query.iter_mut() {
+    for (pos, faction, stats, mut threat, lod) in ai_query.iter_mut() {
         // Respect LOD scheduling
         if let Some(lod) = lod {
             if !lod.should_update(current_tick) {
@@ -80,34 +162,25 @@ pub fn threat_awareness_system(
         };
 
         // Use spatial grid for efficient enemy lookup
-        let search_radius = stats.fire_range * 2.0;
+        let height = terrain_grid.map(|t| t.get_height_at(pos.x, pos.y)).unwrap_or(0.0);
+        let search_radius = stats.fire_range * 2.0 * spotting_range_factor(height);
         let enemies = grid.query_enemies(pos.x, pos.y, search_radius, my_faction);
 
-        let mut closest_dist = f32::MAX;
-        let mut enemies_in_range = 0u32;
-
-        for enemy in &enemies {
-            if enemy.entity == entity {
-                continue;
-            }
-
-            let dx = enemy.x - pos.x;
-            let dy = enemy.y - pos.y;
-            let dist = (dx * dx + dy * dy).sqrt();
-
-            // Track nearest enemy
-            if dist < closest_dist {
-                closest_dist = dist;
-                threat.nearest_enemy = Some((enemy.x, enemy.y));
-                threat.nearest_enemy_dist = dist;
-            }
+        // Only enemies we can actually see are threats we know about
+        let eye = (pos.x, pos.y);
+        let dist = |e: &SpatialEntry| (e.x - eye.0).hypot(e.y - eye.1);
+        let mut visible = visible_enemies(&enemies, eye, |e| garrisoned.contains(e), los.as_deref(), terrain_grid)
+            .peekable();
 
-            // Count enemies in engagement range
-            if dist <= stats.fire_range * 1.5 {
-                enemies_in_range += 1;
-            }
+        let mut closest_dist = f32::MAX;
+        if let Some(&nearest) = visible.peek() {
+            closest_dist = dist(nearest);
+            threat.nearest_enemy = Some((nearest.x, nearest.y));
+            threat.nearest_enemy_dist = closest_dist;
         }
 
+        // Count enemies in engagement range; closest first
+        let enemies_in_range = visible.take_while(|e| dist(e) <= stats.fire_range * 1.5).count() as u32;
         threat.enemies_in_range = enemies_in_range;
 
         // Calculate threat level
@@ -198,8 +271,11 @@ pub fn nearby_friendlies_system(
 
 /// System that applies flocking behaviors to AI velocity.
 /// Uses spatial grid for efficient separation calculation.
+/// Goal seeking follows the flow field toward the goal if there is one.
 pub fn flocking_system(
     grid: Res<SpatialGrid>,
+    flow_fields: Option<Res<FlowFields>>,
+    terrain: Option<Res<TerrainResource>>,
     mut ai_query: Query<(
         &Position,
         &Faction,
@@ -211,9 +287,13 @@ pub fn flocking_system(
         &ThreatAwareness,
         &Suppression,
         &Morale,
+        Option<&Fallback>,
+        Option<&Station>,
     ), With<AIControlled>>,
 ) {
-    for (pos, _faction, mut vel, order, stats, weights, nearby, threat, suppression, morale) in ai_query.iter_mut() {
+    let terrain_guard = terrain.as_ref().and_then(|t| t.read());
+    let flow = flow_fields.as_deref().zip(terrain_guard.as_deref());
+    for (pos, _faction, mut vel, order, stats, weights, nearby, threat, suppression, morale, fallback, station) in ai_query.iter_mut() {
         // Skip if pinned or broken
         if suppression.is_pinned() || morale.is_broken() {
             vel.vx = 0.0;
@@ -226,11 +306,14 @@ pub fn flocking_system(
 
         // 1. Goal seeking (from current order)
         let (goal_x, goal_y) = match order {
-            Order::MoveTo { x, y } | Order::AttackMove { x, y } => (*x, *y),
-            Order::Hold => (pos.x, pos.y),
+            Order::MoveTo { x, y } | Order::AttackMove { x, y } | Order::Garrison { x, y, .. } => (*x, *y),
+            Order::Hold | Order::SuppressArea { .. } | Order::Ungarrison => (pos.x, pos.y),
+            Order::DefendArea { .. } | Order::Patrol { .. } => station.map_or((pos.x, pos.y), |s| (s.x, s.y)),
             Order::Retreat => {
-                // Move away from nearest enemy
-                if let Some((ex, ey)) = threat.nearest_enemy {
+                // Head for the fallback point, else away from nearest enemy
+                if let Some(fallback) = fallback {
+                    (fallback.x, fallback.y)
+                } else if let Some((ex, ey)) = threat.nearest_enemy {
                     let dx = pos.x - ex;
                     let dy = pos.y - ey;
                     let dist = (dx * dx + dy * dy).sqrt().max(0.1);
@@ -241,13 +324,16 @@ pub fn flocking_system(
             }
         };
 
-        let goal_dx = goal_x - pos.x;
-        let goal_dy = goal_y - pos.y;
-        let goal_dist = (goal_dx * goal_dx + goal_dy * goal_dy).sqrt();
+        let goal_dist = (goal_x - pos.x).hypot(goal_y - pos.y);
 
         if goal_dist > 1.0 {
-            steering_x += (goal_dx / goal_dist) * weights.goal_seeking;
-            steering_y += (goal_dy / goal_dist) * weights.goal_seeking;
+            let (seek_x, seek_y) = flow
+                .and_then(|(fields, terrain)| fields.steer(terrain, pos, (goal_x, goal_y)))
+                .unwrap_or((goal_x, goal_y));
+            let (seek_dx, seek_dy) = (seek_x - pos.x, seek_y - pos.y);
+            let seek_dist = seek_dx.hypot(seek_dy).max(0.1);
+            steering_x += (seek_dx / seek_dist) * weights.goal_seeking;
+            steering_y += (seek_dy / seek_dist) * weights.goal_seeking;
         }
 
         // 2. Cohesion (move toward center of nearby friendlies)
@@ -397,10 +483,83 @@ fn determine_behavior_state(
 // AI ORDER GENERATION SYSTEM
 // ============================================================================
 
+/// Tactical value of holding a position against an enemy at `enemy`: the
+/// cover there plus the height advantage over the enemy.
+pub fn position_value(terrain: &TerrainGrid, x: f32, y: f32, enemy: (f32, f32)) -> f32 {
+    let advantage = terrain.get_height_at(x, y) - terrain.get_height_at(enemy.0, enemy.1);
+    terrain.get_cover_at(x, y) + (advantage / ELEVATION_VALUE_SATURATION).clamp(-0.5, 1.0)
+}
+
+/// Whether a squad could fight `enemy` from `point`: the point is on the
+/// map, squads can stand there and the enemy can be seen from it. Without
+/// terrain or a LOS service those checks pass.
+pub(crate) fn can_fight_from(
+    terrain: Option<&TerrainGrid>,
+    los: Option<&LineOfSight>,
+    point: (f32, f32),
+    enemy: (f32, f32),
+) -> bool {
+    let ground = terrain.is_none_or(|t| {
+        t.contains(point.0, point.1) && t.get_terrain_at(point.0, point.1).terrain_type.is_passable()
+    });
+    ground && los.is_none_or(|los| los.has_los(terrain, point.0, point.1, enemy.0, enemy.1))
+}
+
+/// Best nearby position to fight from, if clearly better than the current
+/// one. Candidates must keep the enemy within fire range and pass
+/// `can_fight_from`.
+fn better_position(
+    terrain: &TerrainGrid,
+    los: Option<&LineOfSight>,
+    pos: &Position,
+    enemy: (f32, f32),
+    fire_range: f32,
+) -> Option<(f32, f32)> {
+    let current = position_value(terrain, pos.x, pos.y, enemy);
+    let mut best: Option<(f32, (f32, f32))> = None;
+    for radius in POSITION_SEARCH_RADII {
+        for i in 0..POSITION_SEARCH_DIRECTIONS {
+            let angle = i as f32 / POSITION_SEARCH_DIRECTIONS as f32 * std::f32::consts::TAU;
+            let (x, y) = (pos.x + radius * angle.cos(), pos.y + radius * angle.sin());
+            if ((enemy.0 - x).powi(2) + (enemy.1 - y).powi(2)).sqrt() > fire_range
+                || !can_fight_from(Some(terrain), los, (x, y), enemy)
+            {
+                continue;
+            }
+            let value = position_value(terrain, x, y, enemy);
+            if best.is_none_or(|(best_value, _)| value > best_value) {
+                best = Some((value, (x, y)));
+            }
+        }
+    }
+    best.filter(|(value, _)| *value > current + POSITION_IMPROVEMENT_THRESHOLD)
+        .map(|(_, point)| point)
+}
+
+/// Whether a squad has no movement order left to carry out.
+fn is_settled(order: &Order, pos: &Position) -> bool {
+    // Don't give up a fire mission to reposition; standing orders pick
+    // their own ground
+    if order.is_standing() || matches!(order, Order::SuppressArea { .. }) {
+        return false;
+    }
+    order.is_complete(pos)
+}
+
 /// System that generates orders for AI squads based on behavior state.
+///
+/// Squads taking cover, or holding to engage, periodically look for better
+/// ground nearby - cover, and hills and ridges above the enemy - and move
+/// there. Retreating squads are left to `retreat_system`, and squads on
+/// standing orders to `defense_system`.
 pub fn ai_order_system(
+    tick: Option<Res<SimTick>>,
+    terrain: Option<Res<TerrainResource>>,
+    los: Option<Res<LineOfSight>>,
     mut ai_query: Query<(
+        Entity,
         &Position,
+        &SquadStats,
         &BehaviorState,
         &ThreatAwareness,
         &TacticalPreferences,
@@ -408,22 +567,30 @@ pub fn ai_order_system(
         &mut Order,
     ), With<AIControlled>>,
 ) {
-    for (pos, state, threat, prefs, nearby, mut order) in ai_query.iter_mut() {
-        // Only generate orders for certain states
-        match state {
-            BehaviorState::Retreating => {
-                // Move away from threat
-                if let Some((ex, ey)) = threat.nearest_enemy {
-                    let dx = pos.x - ex;
-                    let dy = pos.y - ey;
-                    let dist = (dx * dx + dy * dy).sqrt().max(0.1);
-                    let retreat_dist = 40.0;
-                    *order = Order::MoveTo {
-                        x: pos.x + (dx / dist) * retreat_dist,
-                        y: pos.y + (dy / dist) * retreat_dist,
-                    };
+    let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
+    let terrain_guard = terrain.as_ref().and_then(|t| t.read());
+    let terrain_grid = terrain_guard.as_deref();
+
+    for (entity, pos, stats, state, threat, prefs, nearby, mut order) in ai_query.iter_mut() {
+        if order.is_standing() {
+            continue;
+        }
+
+        // Look for better ground when holding a position under threat
+        let holding = matches!(state, BehaviorState::TakingCover)
+            || (matches!(state, BehaviorState::Engaging) && prefs.aggression <= 0.6);
+        let search_due = (current_tick + entity.index() as u64).is_multiple_of(POSITION_SEARCH_INTERVAL);
+        if holding && search_due && is_settled(&order, pos) {
+            if let (Some(terrain), Some(enemy)) = (terrain_grid, threat.nearest_enemy) {
+                if let Some((x, y)) = better_position(terrain, los.as_deref(), pos, enemy, stats.fire_range) {
+                    *order = Order::MoveTo { x, y };
+                    continue;
                 }
             }
+        }
+
+        // Only generate orders for certain states
+        match state {
             BehaviorState::Flanking => {
                 // Move perpendicular to enemy
                 if let Some((ex, ey)) = threat.nearest_enemy {
@@ -462,6 +629,53 @@ pub fn ai_order_system(
     }
 }
 
+// ============================================================================
+// AI FIRE STANCE SYSTEM
+// ============================================================================
+
+/// Components read and written by `ai_fire_stance_system`.
+type FireStanceData<'a> = (
+    &'a TacticalPreferences,
+    &'a BehaviorState,
+    &'a ThreatAwareness,
+    &'a SquadStats,
+    Option<&'a IncomingFire>,
+    &'a mut FireStance,
+);
+
+/// System that sets AI squads' rules of engagement.
+///
+/// Defensive squads (`AMBUSH_AGGRESSION`) hold fire until the enemy is
+/// within `AMBUSH_RANGE_FRACTION` of their range or they come under
+/// small-arms fire (`IncomingFire`), and keep firing while enemies stay in
+/// range. Retreating squads only
+/// return fire. Squads given a target to engage keep it, as do squads
+/// whose stance was set explicitly (`ExplicitFireStance`).
+pub fn ai_fire_stance_system(
+    tick: Option<Res<SimTick>>,
+    mut ai_query: Query<FireStanceData, (With<AIControlled>, Without<ExplicitFireStance>)>,
+) {
+    let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
+    for (prefs, state, threat, stats, incoming, mut stance) in ai_query.iter_mut() {
+        if matches!(*stance, FireStance::EngageTarget(_)) {
+            continue;
+        }
+        let new_stance = if matches!(state, BehaviorState::Retreating) {
+            FireStance::ReturnFire
+        } else if prefs.aggression < AMBUSH_AGGRESSION {
+            let sprung = incoming.is_some_and(|i| i.is_recent(current_tick))
+                || threat.nearest_enemy_dist <= stats.fire_range * AMBUSH_RANGE_FRACTION
+                || (*stance == FireStance::FireAtWill && threat.enemies_in_range > 0);
+            if sprung { FireStance::FireAtWill } else { FireStance::HoldFire }
+        } else {
+            FireStance::FireAtWill
+        };
+        if *stance != new_stance {
+            *stance = new_stance;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -503,6 +717,185 @@ mod tests {
         assert!(threat.enemies_in_range > 0);
     }
 
+    #[test]
+    fn test_threat_awareness_ignores_hidden_enemies() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.insert_resource(LineOfSight::new());
+        world.spawn(BuildingBundle::new(1, 15.0, 0.0));
+
+        world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            ThreatAwareness::default(),
+            AIControlled,
+        ));
+
+        // Enemy directly behind the building
+        world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(30.0, 0.0),
+            Health::new(100.0),
+        ));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            spatial_grid_update_system,
+            crate::los::los_update_system,
+            threat_awareness_system,
+        ).chain());
+        schedule.run(&mut world);
+
+        let mut query = world.query::<&ThreatAwareness>();
+        let threat = query.single(&world);
+        assert!(threat.nearest_enemy.is_none());
+        assert_eq!(threat.enemies_in_range, 0);
+    }
+
+    #[test]
+    fn test_threat_awareness_sees_past_concealed_enemies_and_counts_up_to_budget() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            ThreatAwareness::default(),
+            AIControlled,
+        ));
+
+        // More garrisoned enemies than the LOS budget, all too far to spot,
+        // in front of one in the open
+        let building = world.spawn_empty().id();
+        for i in 0..MAX_THREAT_LOS_CHECKS as u32 + 4 {
+            world.spawn((
+                SquadId(10 + i),
+                Faction::Red,
+                Position::new(40.0, i as f32 * 0.5),
+                Health::new(100.0),
+                Garrisoned { building },
+            ));
+        }
+        world.spawn((SquadId(2), Faction::Red, Position::new(50.0, 0.0), Health::new(100.0)));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, threat_awareness_system).chain());
+        schedule.run(&mut world);
+        let threat = world.query::<&ThreatAwareness>().single(&world);
+        assert_eq!(threat.nearest_enemy, Some((50.0, 0.0)));
+        assert_eq!(threat.enemies_in_range, 1);
+
+        // Out of their building they count, as far as the LOS budget goes
+        let mut garrisoned = world.query_filtered::<Entity, With<Garrisoned>>();
+        for entity in garrisoned.iter(&world).collect::<Vec<_>>() {
+            world.entity_mut(entity).remove::<Garrisoned>();
+        }
+        schedule.run(&mut world);
+        let threat = world.query::<&ThreatAwareness>().single(&world);
+        assert_eq!(threat.enemies_in_range, MAX_THREAT_LOS_CHECKS as u32);
+    }
+
+    #[test]
+    fn test_engaged_squad_moves_to_high_ground() {
+        let mut grid = TerrainGrid::new(100, 100, 2.0);
+        grid.add_hill(0.0, 20.0, 15.0, 6.0);
+
+        let mut world = World::new();
+        world.insert_resource(TerrainResource::new(grid));
+        world.insert_resource(SimTick(0));
+        let threat = ThreatAwareness {
+            nearest_enemy: Some((0.0, 60.0)),
+            enemies_in_range: 1,
+            ..Default::default()
+        };
+        let squad = world.spawn((
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            BehaviorState::Engaging,
+            threat,
+            TacticalPreferences::default(),
+            NearbyFriendlies::default(),
+            Order::Hold,
+            AIControlled,
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(ai_order_system);
+        for t in 0..POSITION_SEARCH_INTERVAL {
+            world.insert_resource(SimTick(t));
+            schedule.run(&mut world);
+        }
+
+        let Order::MoveTo { x, y } = *world.get::<Order>(squad).unwrap() else {
+            panic!("squad should move to better ground");
+        };
+        let terrain = world.resource::<TerrainResource>();
+        assert!(terrain.get_height_at(x, y) > 3.0, "moved to ({}, {})", x, y);
+    }
+
+    /// Order of a squad engaging an enemy 60 units north, with a hill on
+    /// the way, after a round of searches for better ground.
+    fn order_after_position_search(grid: TerrainGrid, los: LineOfSight) -> Order {
+        let mut world = World::new();
+        world.insert_resource(TerrainResource::new(grid));
+        world.insert_resource(los);
+        let threat = ThreatAwareness {
+            nearest_enemy: Some((0.0, 60.0)),
+            enemies_in_range: 1,
+            ..Default::default()
+        };
+        let squad = world.spawn((
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            BehaviorState::Engaging,
+            threat,
+            TacticalPreferences::default(),
+            NearbyFriendlies::default(),
+            Order::Hold,
+            AIControlled,
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(ai_order_system);
+        for t in 0..POSITION_SEARCH_INTERVAL {
+            world.insert_resource(SimTick(t));
+            schedule.run(&mut world);
+        }
+        *world.get::<Order>(squad).unwrap()
+    }
+
+    #[test]
+    fn test_squad_only_moves_to_ground_it_can_stand_on_and_see_from() {
+        use crate::terrain::TerrainType;
+
+        let mut grid = TerrainGrid::new(100, 100, 2.0);
+        grid.add_hill(0.0, 20.0, 15.0, 6.0);
+        assert!(matches!(order_after_position_search(grid.clone(), LineOfSight::new()), Order::MoveTo { .. }));
+
+        let mut screened = LineOfSight::new();
+        screened.add_blocker(0.0, 45.0, 4.0);
+        assert!(
+            matches!(order_after_position_search(grid.clone(), screened), Order::Hold),
+            "the enemy can't be seen from the hill"
+        );
+
+        for cell in grid.cells.iter_mut().filter(|c| c.height > 0.5) {
+            cell.terrain_type = TerrainType::Water;
+        }
+        assert!(
+            matches!(order_after_position_search(grid, LineOfSight::new()), Order::Hold),
+            "the hill is under water"
+        );
+    }
+
     #[test]
     fn test_behavior_state_transitions() {
         // Test retreating when morale broken
@@ -0,0 +1,333 @@
+//! Close assault system - melee resolution when opposing squads make contact.
+//!
+//! Squads within `ASSAULT_RANGE` of an enemy stop trading rifle fire and fight
+//! hand to hand. Each contact is resolved every tick on the relative assault
+//! strength of the two squads (size, morale, fatigue, suppression and the
+//! defender's cover). Both sides take heavy casualties and a morale shock
+//! scaled by how badly they are outmatched. A side whose morale breaks routs;
+//! if it was also hopelessly outmatched, it surrenders instead.
+//!
+//! Every squad fights at most one close assault per tick, against its closest
+//! enemy in contact. Squads in an assault do not fire at range that tick.
+
+use crate::components::*;
+use crate::spatial::SpatialGrid;
+use crate::systems::cover::InCover;
+use crate::systems::movement::DeltaTime;
+use bevy_ecs::prelude::*;
+use std::collections::HashSet;
+
+/// Distance at which opposing squads are in contact (world units).
+pub const ASSAULT_RANGE: f32 = 5.0;
+
+/// Health lost per second by a squad facing overwhelming odds.
+const MELEE_DAMAGE_RATE: f32 = 40.0;
+
+/// Morale lost per second by a squad facing overwhelming odds.
+const MELEE_MORALE_SHOCK: f32 = 0.6;
+
+/// Fatigue gained per second of hand-to-hand fighting.
+const FATIGUE_ASSAULT_RATE: f32 = 0.1;
+
+/// Fatigue gained per second when moving at full speed.
+const FATIGUE_MOVE_RATE: f32 = 0.01;
+
+/// Fatigue recovered per second at rest.
+const FATIGUE_RECOVERY_RATE: f32 = 0.02;
+
+/// Strength bonus per unit of cover value for a squad fighting from cover.
+const COVER_ASSAULT_BONUS: f32 = 0.8;
+
+/// A broken squad whose share of the assault strength is below this surrenders
+/// instead of routing.
+const SURRENDER_RATIO: f32 = 0.25;
+
+/// Close assaults in progress this tick.
+#[derive(Resource, Debug, Default)]
+pub struct CloseAssaults {
+    /// Squads fighting hand to hand this tick.
+    engaged: HashSet<Entity>,
+    /// Contact pairs this tick.
+    pub pairs: Vec<(Entity, Entity)>,
+}
+
+impl CloseAssaults {
+    pub fn clear(&mut self) {
+        self.engaged.clear();
+        self.pairs.clear();
+    }
+
+    /// Whether a squad is fighting a close assault this tick.
+    pub fn is_engaged(&self, entity: Entity) -> bool {
+        self.engaged.contains(&entity)
+    }
+}
+
+/// Close-assault strength of a squad.
+///
+/// `cover` is the cover value at the squad's position (0.0 - 1.0); squads
+/// fighting from a trench or strongpoint hold off larger attackers.
+pub fn assault_strength(
+    stats: &SquadStats,
+    health: &Health,
+    morale: &Morale,
+    suppression: f32,
+    fatigue: f32,
+    cover: f32,
+) -> f32 {
+    let soldiers = stats.size as f32 * health.fraction();
+    soldiers
+        * (0.5 + morale.value)
+        * (1.0 - 0.5 * fatigue.clamp(0.0, 1.0))
+        * (1.0 - 0.5 * suppression.clamp(0.0, 1.0))
+        * (1.0 + COVER_ASSAULT_BONUS * cover.clamp(0.0, 1.0))
+}
+
+/// Components read and written by `assault_system`.
+type AssaultData<'a> = (
+    Entity,
+    &'a Faction,
+    &'a Position,
+    &'a SquadStats,
+    &'a mut Health,
+    &'a mut Morale,
+    &'a mut Order,
+    &'a mut Velocity,
+    Option<&'a Suppression>,
+    Option<&'a mut Fatigue>,
+    Option<&'a InCover>,
+);
+
+/// System that detects contacts and resolves close assaults.
+///
+/// ## Data Access
+/// - Reads: DeltaTime, SpatialGrid, Faction, Position, SquadStats, Suppression, InCover
+/// - Writes: Health, Morale, Order, Velocity, Fatigue, CloseAssaults, Surrendered (via commands)
+pub fn assault_system(
+    dt: Res<DeltaTime>,
+    grid: Res<SpatialGrid>,
+    mut assaults: ResMut<CloseAssaults>,
+    mut commands: Commands,
+    mut query: Query<AssaultData, Without<Surrendered>>,
+) {
+    let delta = dt.0;
+    assaults.clear();
+
+    // Contact detection: each squad's closest enemy within assault range
+    let mut contacts: Vec<(f32, Entity, Entity)> = Vec::new();
+    for (entity, faction, pos, _, health, ..) in query.iter() {
+        if !health.is_alive() {
+            continue;
+        }
+        let faction_id = match faction { Faction::Blue => 0, Faction::Red => 1 };
+        if let Some(enemy) = grid.query_enemies(pos.x, pos.y, ASSAULT_RANGE, faction_id).first() {
+            let dist = ((enemy.x - pos.x).powi(2) + (enemy.y - pos.y).powi(2)).sqrt();
+            let pair = if entity < enemy.entity { (entity, enemy.entity) } else { (enemy.entity, entity) };
+            contacts.push((dist, pair.0, pair.1));
+        }
+    }
+    contacts.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    // Each squad fights one assault per tick, closest contacts first
+    for (_, a, b) in contacts {
+        if assaults.engaged.contains(&a) || assaults.engaged.contains(&b) {
+            continue;
+        }
+        let Ok([side_a, side_b]) = query.get_many_mut([a, b]) else { continue };
+        let (_, _, _, stats_a, mut health_a, mut morale_a, mut order_a, mut vel_a, sup_a, mut fatigue_a, cover_a) = side_a;
+        let (_, _, _, stats_b, mut health_b, mut morale_b, mut order_b, mut vel_b, sup_b, mut fatigue_b, cover_b) = side_b;
+        if !health_a.is_alive() || !health_b.is_alive() {
+            continue;
+        }
+        assaults.engaged.insert(a);
+        assaults.engaged.insert(b);
+        assaults.pairs.push((a, b));
+
+        let strength_a = assault_strength(
+            stats_a,
+            &health_a,
+            &morale_a,
+            sup_a.map(|s| s.value).unwrap_or(0.0),
+            fatigue_a.as_ref().map(|f| f.value).unwrap_or(0.0),
+            cover_a.map(|c| c.value).unwrap_or(0.0),
+        );
+        let strength_b = assault_strength(
+            stats_b,
+            &health_b,
+            &morale_b,
+            sup_b.map(|s| s.value).unwrap_or(0.0),
+            fatigue_b.as_ref().map(|f| f.value).unwrap_or(0.0),
+            cover_b.map(|c| c.value).unwrap_or(0.0),
+        );
+        let total = strength_a + strength_b;
+        if total <= 0.0 {
+            continue;
+        }
+        let share_a = strength_a / total;
+
+        let side_a = AssaultSide {
+            health: &mut health_a,
+            morale: &mut morale_a,
+            order: &mut order_a,
+            velocity: &mut vel_a,
+            fatigue: fatigue_a.as_deref_mut(),
+        };
+        if side_a.resolve(share_a, delta) {
+            commands.entity(a).insert(Surrendered).remove::<AIBundle>();
+        }
+        let side_b = AssaultSide {
+            health: &mut health_b,
+            morale: &mut morale_b,
+            order: &mut order_b,
+            velocity: &mut vel_b,
+            fatigue: fatigue_b.as_deref_mut(),
+        };
+        if side_b.resolve(1.0 - share_a, delta) {
+            commands.entity(b).insert(Surrendered).remove::<AIBundle>();
+        }
+    }
+}
+
+/// Mutable state of one side of a close assault.
+struct AssaultSide<'a> {
+    health: &'a mut Health,
+    morale: &'a mut Morale,
+    order: &'a mut Order,
+    velocity: &'a mut Velocity,
+    fatigue: Option<&'a mut Fatigue>,
+}
+
+impl AssaultSide<'_> {
+    /// Apply one tick of close assault to a side holding `share` of the total
+    /// assault strength. Returns true if the side surrenders.
+    fn resolve(self, share: f32, delta: f32) -> bool {
+        let odds_against = 1.0 - share;
+
+        self.health.damage(MELEE_DAMAGE_RATE * odds_against * delta);
+        self.morale.decrease(MELEE_MORALE_SHOCK * odds_against * delta);
+        if let Some(fatigue) = self.fatigue {
+            fatigue.add(FATIGUE_ASSAULT_RATE * delta);
+        }
+
+        if !self.health.is_alive() || !self.morale.is_broken() {
+            return false;
+        }
+
+        if share < SURRENDER_RATIO {
+            // Overrun: lay down arms
+            *self.order = Order::Hold;
+            self.velocity.vx = 0.0;
+            self.velocity.vy = 0.0;
+            true
+        } else {
+            *self.order = Order::Retreat;
+            false
+        }
+    }
+}
+
+/// System that accumulates fatigue while moving and recovers it at rest.
+///
+/// ## Data Access
+/// - Reads: DeltaTime, Velocity, SquadStats
+/// - Writes: Fatigue
+pub fn fatigue_system(
+    dt: Res<DeltaTime>,
+    mut query: Query<(&Velocity, &SquadStats, &mut Fatigue)>,
+) {
+    let delta = dt.0;
+    for (vel, stats, mut fatigue) in query.iter_mut() {
+        let speed_frac = (vel.vx * vel.vx + vel.vy * vel.vy).sqrt() / stats.speed.max(0.001);
+        if speed_frac > 0.1 {
+            fatigue.add(FATIGUE_MOVE_RATE * speed_frac.min(1.0) * delta);
+        } else {
+            fatigue.recover(FATIGUE_RECOVERY_RATE * delta);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial::spatial_grid_update_system;
+
+    fn spawn_squad(world: &mut World, faction: Faction, x: f32, size: u32, morale: f32) -> Entity {
+        world.spawn((
+            faction,
+            Position::new(x, 0.0),
+            Velocity::default(),
+            SquadStats { size, ..Default::default() },
+            Health::new(100.0),
+            Morale::new(morale),
+            Suppression::default(),
+            Fatigue::default(),
+            Order::Hold,
+        )).id()
+    }
+
+    fn run_assault(world: &mut World, ticks: usize) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, assault_system).chain());
+        for _ in 0..ticks {
+            schedule.run(world);
+        }
+    }
+
+    #[test]
+    fn test_cover_and_morale_decide_assault_strength() {
+        let stats = SquadStats::default();
+        let health = Health::new(100.0);
+        let steady = Morale::new(1.0);
+        let shaken = Morale::new(0.3);
+
+        let in_open = assault_strength(&stats, &health, &steady, 0.0, 0.0, 0.0);
+        assert!(assault_strength(&stats, &health, &steady, 0.0, 0.0, 0.8) > in_open);
+        assert!(assault_strength(&stats, &health, &shaken, 0.0, 0.0, 0.0) < in_open);
+        assert!(assault_strength(&stats, &health, &steady, 0.0, 1.0, 0.0) < in_open);
+    }
+
+    #[test]
+    fn test_outmatched_squad_surrenders() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.insert_resource(CloseAssaults::default());
+
+        let attackers = spawn_squad(&mut world, Faction::Blue, 0.0, 24, 1.0);
+        let defenders = spawn_squad(&mut world, Faction::Red, 3.0, 4, 0.4);
+        // Far away squads are not in contact
+        let bystander = spawn_squad(&mut world, Faction::Red, 40.0, 12, 1.0);
+
+        run_assault(&mut world, 1);
+        assert!(world.resource::<CloseAssaults>().is_engaged(attackers));
+        assert!(!world.resource::<CloseAssaults>().is_engaged(bystander));
+
+        run_assault(&mut world, 30);
+        assert!(world.get::<Surrendered>(defenders).is_some());
+        assert!(world.get::<Surrendered>(attackers).is_none());
+        assert!(world.get::<Health>(defenders).unwrap().current < world.get::<Health>(attackers).unwrap().current);
+        assert!(world.get::<Fatigue>(attackers).unwrap().value > 0.0);
+    }
+
+    #[test]
+    fn test_even_assault_breaks_loser_into_rout() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.insert_resource(CloseAssaults::default());
+
+        let blue = spawn_squad(&mut world, Faction::Blue, 0.0, 12, 1.0);
+        let red = spawn_squad(&mut world, Faction::Red, 3.0, 10, 0.6);
+
+        // Run until the weaker side breaks
+        for _ in 0..40 {
+            run_assault(&mut world, 1);
+            if world.get::<Morale>(red).unwrap().is_broken() {
+                break;
+            }
+        }
+        assert!(matches!(world.get::<Order>(red).unwrap(), Order::Retreat));
+        assert!(world.get::<Surrendered>(red).is_none());
+        assert!(matches!(world.get::<Order>(blue).unwrap(), Order::Hold));
+    }
+}
@@ -2,6 +2,7 @@
 //! 
 //! ## Performance Optimizations
 //! - Uses spatial grid for O(k) enemy detection instead of O(n)
+//! - Only targets enemies in line of sight (cached per tick by `LineOfSight`)
 //! - Respects LOD: Low-LOD units update less frequently
 //! - Skips idle units that aren't firing
 //! - Updates activity flags for damage tracking
@@ -35,22 +36,69 @@
 //! for internal parallel iteration, processing attackers across multiple threads.
 
 use crate::components::*;
+use crate::los::LineOfSight;
 use crate::spatial::SpatialGrid;
+use crate::systems::assault::CloseAssaults;
+use crate::systems::cover::{CoverEvaluation, CoverEvaluator, CoverProviderIndex, CoverZones, InCover};
+use crate::systems::garrison::{GARRISON_ELEVATION, GARRISON_SPOTTING_RANGE};
 use crate::systems::movement::DeltaTime;
 use crate::systems::performance::SimTick;
-use crate::terrain::TerrainResource;
+use crate::systems::smoke::SMOKE_ACCURACY_PENALTY;
+use crate::systems::targeting::{
+    score_target, Engagements, ShooterProfile, TargetCandidate, TargetInfo, MAX_TARGET_CANDIDATES,
+};
+use crate::terrain::{elevation_accuracy_factor, elevation_range_factor, TerrainGrid, TerrainResource};
 use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
 use std::collections::HashMap;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 /// Combat configuration constants.
-const BASE_HIT_CHANCE: f32 = 0.15;
-const SUPPRESSION_PER_HIT: f32 = 0.2;
-const DAMAGE_PER_HIT: f32 = 8.0;
+pub(crate) const BASE_HIT_CHANCE: f32 = 0.15;
+pub(crate) const SUPPRESSION_PER_HIT: f32 = 0.2;
+pub(crate) const DAMAGE_PER_HIT: f32 = 8.0;
 const RANGE_FALLOFF_START: f32 = 0.5; // Start accuracy falloff at 50% of max range
-const MAX_COVER_REDUCTION: f32 = 0.7; // Cover can reduce damage by up to 70%
+
+/// Damage to a cover provider per hit it stops from a rifle (see
+/// `UnitClass::structure_damage`). A rifle squad firing into an intact
+/// building brings it down in several minutes; heavy weapons much faster.
+const STRUCTURE_DAMAGE_PER_HIT: f32 = 1.0;
+
+/// Area fire: damage per hit relative to aimed fire.
+const AREA_FIRE_LETHALITY: f32 = 0.25;
+/// Area fire: suppression per hit relative to aimed fire.
+const AREA_FIRE_SUPPRESSION: f32 = 1.5;
+/// Zone radius up to which area fire is fully concentrated.
+const AREA_FIRE_REFERENCE_RADIUS: f32 = 10.0;
+
+/// Per-squad components read for target selection.
+type TargetingData<'a> = (
+    Option<&'a Velocity>,
+    Option<&'a UnitClass>,
+    Option<&'a FirePriority>,
+    Option<&'a Order>,
+    Option<&'a Garrisoned>,
+    Option<&'a FireStance>,
+);
+
+/// One attacker's fire at one target during a tick, with attribution.
+#[derive(Debug, Clone, Copy)]
+pub struct FireRecord {
+    pub shooter: Entity,
+    pub target: Entity,
+    /// The shooter's unit class, standing in for its weapon.
+    pub weapon: UnitClass,
+    /// Expected hits this tick.
+    pub hits: f32,
+    pub damage: f32,
+    pub suppression: f32,
+    /// The target's cover along the line of fire (0.0-1.0).
+    pub cover: f32,
+    /// Whether this fire finished the target off (set by the apply phase).
+    pub killed: bool,
+}
 
 /// Collected combat results to apply after iteration.
 /// 
@@ -62,6 +110,12 @@ pub struct CombatResults {
     pub suppression: HashMap<Entity, f32>,
     /// Entities that fired this tick (for activity tracking)
     pub fired: Vec<Entity>,
+    /// Target chosen by each attacker this tick (attacker -> target).
+    pub targets: HashMap<Entity, Entity>,
+    /// Attributed fire, one record per attacker that fired.
+    pub fire: Vec<FireRecord>,
+    /// Damage to cover providers from fire they stopped (provider -> damage).
+    pub structure_damage: HashMap<Entity, f32>,
 }
 
 impl CombatResults {
@@ -74,6 +128,58 @@ impl CombatResults {
             *self.suppression.entry(entity).or_insert(0.0) += sup;
         }
         self.fired.extend(other.fired);
+        self.targets.extend(other.targets);
+        self.fire.extend(other.fire);
+        for (entity, dmg) in other.structure_damage {
+            *self.structure_damage.entry(entity).or_insert(0.0) += dmg;
+        }
+    }
+
+    /// Record `hits` by an attacker on a target. Damage and suppression are
+    /// scaled by the given factors and reduced by the target's cover; the
+    /// share of fire the cover stops goes into the provider sheltering the
+    /// target, if any.
+    fn add_hits(
+        &mut self,
+        attacker: &AttackerData,
+        target: Entity,
+        hits: f32,
+        cover: CoverEvaluation,
+        lethality: f32,
+        suppressiveness: f32,
+    ) {
+        let damage = hits * DAMAGE_PER_HIT * lethality * cover.damage_multiplier();
+        let suppression = hits * SUPPRESSION_PER_HIT * suppressiveness * cover.suppression_multiplier();
+        *self.damage.entry(target).or_insert(0.0) += damage;
+        *self.suppression.entry(target).or_insert(0.0) += suppression;
+        if let Some(shelter) = cover.shelter {
+            let stopped = hits * cover.absorbed_fraction() * lethality;
+            *self.structure_damage.entry(shelter).or_insert(0.0) +=
+                stopped * STRUCTURE_DAMAGE_PER_HIT * attacker.class.structure_damage();
+        }
+        self.fire.push(FireRecord {
+            shooter: attacker.entity,
+            target,
+            weapon: attacker.class,
+            hits,
+            damage,
+            suppression,
+            cover: cover.value,
+            killed: false,
+        });
+    }
+
+    /// Credit each target killed this tick to the attacker that dealt it
+    /// the most damage, marking that fire record.
+    pub(crate) fn credit_kills(&mut self, killed: &[Entity]) {
+        for &target in killed {
+            let best = self.fire.iter_mut()
+                .filter(|record| record.target == target)
+                .max_by(|a, b| a.damage.total_cmp(&b.damage));
+            if let Some(record) = best {
+                record.killed = true;
+            }
+        }
     }
 }
 
@@ -82,142 +188,46 @@ impl CombatResults {
 #[derive(Resource, Default)]
 pub struct PendingCombatResults(pub CombatResults);
 
-/// System that processes combat between opposing squads.
-/// 
-/// ## Data Access
-/// - Reads: DeltaTime, SpatialGrid, SimTick, TerrainResource, Position, Faction, SquadStats, Morale, SimLod
-/// - Writes: Health, Suppression, ActivityFlags
-/// 
-/// ## Performance
-/// - Uses spatial grid for efficient enemy detection
-/// - Respects LOD scheduling (low-LOD units fire less often)
-/// - Updates activity flags for damage tracking
-pub fn combat_system(
-    dt: Res<DeltaTime>,
-    grid: Res<SpatialGrid>,
-    tick: Option<Res<SimTick>>,
-    terrain: Option<Res<TerrainResource>>,
-    mut query: Query<(
-        Entity,
-        &SquadId,
-        &Faction,
-        &Position,
-        &SquadStats,
-        &mut Health,
-        &mut Suppression,
-        &Morale,
-        Option<&SimLod>,
-    )>,
-    mut activity_query: Query<&mut ActivityFlags>,
-) {
-    let delta = dt.0;
-    let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
-    let mut results = CombatResults::default();
-
-    // Collect attacker data first to avoid borrow issues
-    let attackers: Vec<_> = query.iter()
-        .filter(|(_, _, _, _, _, health, suppression, morale, lod)| {
-            // Must be alive and able to fire
-            if !health.is_alive() || suppression.value >= 1.0 || morale.value < 0.2 {
-                return false;
-            }
-            // Respect LOD scheduling
-            if let Some(lod) = lod {
-                if !lod.should_update(current_tick) {
-                    return false;
-                }
-            }
-            true
-        })
-        .map(|(entity, _, faction, pos, stats, _, suppression, morale, lod)| {
-            let my_faction = match faction {
-                Faction::Blue => 0u8,
-                Faction::Red => 1u8,
-            };
-            // LOD affects fire rate - low LOD fires less but with accumulated damage
-            let lod_multiplier = lod.map(|l| l.tick_interval() as f32).unwrap_or(1.0);
-            (entity, my_faction, pos.x, pos.y, stats.fire_range, stats.accuracy, stats.size, suppression.value, morale.value, lod_multiplier)
-        })
-        .collect();
-
-    // Process each attacker
-    for (entity, my_faction, x, y, fire_range, accuracy, size, suppression_val, morale_val, lod_mult) in &attackers {
-        // Use spatial grid to find enemies in range
-        let enemies = grid.query_enemies(*x, *y, *fire_range, *my_faction);
-
-        // Find closest enemy
-        let mut best_target: Option<(Entity, f32, f32)> = None; // (entity, dist, cover)
-        for enemy in &enemies {
-            let dx = enemy.x - x;
-            let dy = enemy.y - y;
-            let dist = (dx * dx + dy * dy).sqrt();
-
-            if dist <= *fire_range {
-                // Get cover at target position
-                let target_cover = terrain.as_ref()
-                    .map(|t| t.get_cover_at(enemy.x, enemy.y))
-                    .unwrap_or(0.0);
-
-                if best_target.is_none() || dist < best_target.unwrap().1 {
-                    best_target = Some((enemy.entity, dist, target_cover));
-                }
-            }
-        }
-
-        // Fire at target if found
-        if let Some((target_entity, dist, target_cover)) = best_target {
-            // Mark this entity as firing
-            results.fired.push(*entity);
-
-            // Calculate hit chance with modifiers
-            let range_factor = if dist > fire_range * RANGE_FALLOFF_START {
-                1.0 - ((dist - fire_range * RANGE_FALLOFF_START) / (fire_range * (1.0 - RANGE_FALLOFF_START)))
-            } else {
-                1.0
-            };
-
-            // Suppression reduces accuracy
-            let suppression_penalty = 1.0 - (suppression_val * 0.5).min(0.8);
-
-            // Morale affects accuracy
-            let morale_factor = 0.5 + morale_val * 0.5;
-
-            let effective_accuracy =
-                accuracy * range_factor * suppression_penalty * morale_factor;
-
-            // Each soldier in squad fires
-            // LOD multiplier compensates for skipped ticks
-            let shots = *size as f32 * delta * 2.0 * lod_mult;
-            let hits = shots * effective_accuracy * BASE_HIT_CHANCE;
-
-            // Apply cover damage reduction to target
-            let cover_reduction = 1.0 - (target_cover * MAX_COVER_REDUCTION);
-            let final_damage = hits * DAMAGE_PER_HIT * cover_reduction;
-
-            // Apply damage and suppression (suppression is less affected by cover)
-            *results.damage.entry(target_entity).or_insert(0.0) += final_damage;
-            *results.suppression.entry(target_entity).or_insert(0.0) += hits * SUPPRESSION_PER_HIT * (1.0 - target_cover * 0.3);
-        }
-    }
+/// World resources read by the combat computation.
+///
+/// Bundled so that combat systems stay within a manageable parameter count.
+#[derive(SystemParam)]
+pub struct CombatEnvironment<'w> {
+    dt: Res<'w, DeltaTime>,
+    grid: Res<'w, SpatialGrid>,
+    terrain: Option<Res<'w, TerrainResource>>,
+    los: Option<Res<'w, LineOfSight>>,
+    cover_zones: Option<Res<'w, CoverZones>>,
+    cover_providers: Option<Res<'w, CoverProviderIndex>>,
+    assaults: Option<Res<'w, CloseAssaults>>,
+}
 
-    // Apply accumulated results
-    for (entity, _, _, _, _, mut health, mut suppression, _, _) in query.iter_mut() {
-        if let Some(&dmg) = results.damage.get(&entity) {
-            health.damage(dmg);
-            // Mark as recently damaged
-            if let Ok(mut flags) = activity_query.get_mut(entity) {
-                flags.mark_damaged(current_tick);
-            }
-        }
-        if let Some(&sup) = results.suppression.get(&entity) {
-            suppression.add(sup);
-        }
+impl CombatEnvironment<'_> {
+    /// Whether a squad is fighting hand to hand this tick (and so not firing).
+    fn in_close_assault(&self, entity: Entity) -> bool {
+        self.assaults.as_ref().is_some_and(|a| a.is_engaged(entity))
     }
 
-    // Update firing flags
-    for entity in results.fired {
-        if let Ok(mut flags) = activity_query.get_mut(entity) {
-            flags.is_firing = true;
+    /// Build the shared combat context. `terrain` is the read guard acquired
+    /// once by the calling system.
+    fn context<'a>(
+        &'a self,
+        terrain: Option<&'a TerrainGrid>,
+        targets: &'a HashMap<Entity, TargetInfo>,
+        engagements: &'a Engagements,
+    ) -> CombatContext<'a> {
+        CombatContext {
+            grid: &self.grid,
+            terrain,
+            los: self.los.as_deref(),
+            cover: CoverEvaluator {
+                terrain,
+                zones: self.cover_zones.as_deref(),
+                providers: self.cover_providers.as_deref(),
+            },
+            targets,
+            engagements,
+            delta: self.dt.0,
         }
     }
 }
@@ -240,6 +250,223 @@ struct AttackerData {
     suppression: f32,
     morale: f32,
     lod_multiplier: f32,
+    /// Low LOD: fires individually only at enemies outside the sector pools.
+    pooled: bool,
+    /// Accuracy penalty from firing out of cover (0.0 = none).
+    cover_penalty: f32,
+    class: UnitClass,
+    priority: FirePriority,
+    /// Zone (x, y, radius) under a `SuppressArea` order.
+    area_fire: Option<(f32, f32, f32)>,
+    /// Height above the ground the squad fires from.
+    elevation: f32,
+    stance: FireStance,
+    /// Whether the squad is under small-arms fire (see `IncomingFire`).
+    under_fire: bool,
+    /// The target of `FireStance::EngageTarget`, if it exists, or the squad
+    /// a `FireStance::ReturnFire` squad is answering.
+    focus: Option<Entity>,
+}
+
+impl AttackerData {
+    fn new(
+        entity: Entity,
+        faction: &Faction,
+        pos: &Position,
+        stats: &SquadStats,
+        suppression: &Suppression,
+        morale: &Morale,
+        lod: Option<&SimLod>,
+    ) -> Self {
+        Self {
+            entity,
+            faction: match faction { Faction::Blue => 0, Faction::Red => 1 },
+            x: pos.x,
+            y: pos.y,
+            fire_range: stats.fire_range,
+            accuracy: stats.accuracy,
+            size: stats.size,
+            suppression: suppression.value,
+            morale: morale.value,
+            // LOD affects fire rate - low LOD fires less but with accumulated damage
+            lod_multiplier: lod.map(|l| l.tick_interval() as f32).unwrap_or(1.0),
+            pooled: lod == Some(&SimLod::Low),
+            cover_penalty: 0.0,
+            class: UnitClass::default(),
+            priority: FirePriority::default(),
+            area_fire: None,
+            elevation: 0.0,
+            stance: FireStance::default(),
+            under_fire: false,
+            focus: None,
+        }
+    }
+
+    /// Expected hits this tick on a target at `dist`, given the effective
+    /// fire range and an extra accuracy modifier for this shot.
+    fn expected_hits(&self, dist: f32, fire_range: f32, modifier: f32, delta: f32) -> f32 {
+        let range_factor = if dist > fire_range * RANGE_FALLOFF_START {
+            1.0 - ((dist - fire_range * RANGE_FALLOFF_START) 
+                   / (fire_range * (1.0 - RANGE_FALLOFF_START)))
+        } else {
+            1.0
+        };
+
+        let suppression_penalty = 1.0 - (self.suppression * 0.5).min(0.8);
+        let morale_factor = 0.5 + self.morale * 0.5;
+        let cover_penalty = 1.0 - self.cover_penalty;
+        let effective_accuracy =
+            self.accuracy * range_factor * suppression_penalty * morale_factor * cover_penalty * modifier;
+
+        let shots = self.size as f32 * delta * 2.0 * self.lod_multiplier;
+        shots * effective_accuracy * BASE_HIT_CHANCE
+    }
+
+    /// Apply the accuracy penalty for the cover the attacker is firing from.
+    fn with_cover(mut self, in_cover: Option<&InCover>) -> Self {
+        self.cover_penalty = in_cover.map(|c| c.cover_type.accuracy_penalty()).unwrap_or(0.0);
+        self
+    }
+
+    /// Apply the attacker's unit class, fire priority, fire stance, firing
+    /// height and any area-fire zone from a `SuppressArea` order.
+    fn with_targeting(mut self, (_, class, priority, order, garrisoned, stance): TargetingData) -> Self {
+        self.class = class.copied().unwrap_or_default();
+        self.priority = priority.copied().unwrap_or_default();
+        self.stance = stance.copied().unwrap_or_default();
+        self.elevation = if garrisoned.is_some() { GARRISON_ELEVATION } else { 0.0 };
+        if let Some(&Order::SuppressArea { x, y, radius }) = order {
+            self.area_fire = Some((x, y, radius));
+        }
+        self
+    }
+
+    /// Resolve the squad ID of an `EngageTarget` stance to its entity.
+    fn with_focus(mut self, squads: &HashMap<u32, Entity>) -> Self {
+        if let FireStance::EngageTarget(id) = self.stance {
+            self.focus = squads.get(&id).copied();
+        }
+        self
+    }
+
+    /// Apply the fire the squad has taken: a return-fire squad under fire
+    /// answers its last attacker.
+    fn with_incoming(mut self, incoming: Option<&IncomingFire>, current_tick: u64) -> Self {
+        self.under_fire = incoming.is_some_and(|i| i.is_recent(current_tick));
+        if self.under_fire && self.stance == FireStance::ReturnFire {
+            self.focus = incoming.and_then(|i| i.attacker);
+        }
+        self
+    }
+
+    fn profile(&self) -> ShooterProfile {
+        ShooterProfile {
+            x: self.x,
+            y: self.y,
+            fire_range: self.fire_range,
+            class: self.class,
+            priority: self.priority,
+        }
+    }
+}
+
+/// Target-selection data for a squad.
+fn target_info(suppression: &Suppression, lod: Option<&SimLod>, (velocity, class, _, _, garrisoned, _): TargetingData) -> TargetInfo {
+    TargetInfo {
+        class: class.copied().unwrap_or_default(),
+        suppression: suppression.value,
+        vx: velocity.map(|v| v.vx).unwrap_or(0.0),
+        vy: velocity.map(|v| v.vy).unwrap_or(0.0),
+        elevation: if garrisoned.is_some() { GARRISON_ELEVATION } else { 0.0 },
+        concealed: garrisoned.is_some(),
+        pooled: lod == Some(&SimLod::Low),
+    }
+}
+
+/// Record on each squad fired upon who fired at it this tick.
+fn record_incoming_fire(fire: &[FireRecord], current_tick: u64, incoming: &mut Query<&mut IncomingFire>) {
+    for record in fire {
+        if let Ok(mut target) = incoming.get_mut(record.target) {
+            target.record(current_tick, Some(record.shooter));
+        }
+    }
+}
+
+/// Apply damage from stopped fire to the cover providers that stopped it.
+fn damage_structures(damage: &HashMap<Entity, f32>, structures: &mut Query<&mut DestructibleHealth>) {
+    for (&entity, &amount) in damage {
+        if let Ok(mut health) = structures.get_mut(entity) {
+            if !health.is_destroyed() {
+                health.damage(amount);
+            }
+        }
+    }
+}
+
+/// Whether a squad is able to fire individually this tick.
+///
+/// Low-LOD squads fight each other through the sector firepower pools (see
+/// `sector_combat`), but still fire individually at higher-LOD enemies on
+/// their update ticks, so fights across an LOD boundary go both ways.
+fn can_fire(
+    health: &Health,
+    suppression: &Suppression,
+    morale: &Morale,
+    lod: Option<&SimLod>,
+    current_tick: u64,
+) -> bool {
+    // Must be alive and able to fire
+    if !health.is_alive() || suppression.value >= 1.0 || morale.value < 0.2 {
+        return false;
+    }
+    // Respect LOD scheduling
+    match lod {
+        Some(l) => l.should_update(current_tick),
+        None => true,
+    }
+}
+
+/// Shared, read-only inputs for the per-attacker combat computation.
+///
+/// Borrowed once per system run so the compute phase can be run in parallel.
+struct CombatContext<'a> {
+    grid: &'a SpatialGrid,
+    terrain: Option<&'a TerrainGrid>,
+    los: Option<&'a LineOfSight>,
+    cover: CoverEvaluator<'a>,
+    targets: &'a HashMap<Entity, TargetInfo>,
+    engagements: &'a Engagements,
+    delta: f32,
+}
+
+impl CombatContext<'_> {
+    /// Whether a point is visible from another. Without a LOS service,
+    /// everything is visible.
+    fn has_los(&self, from_x: f32, from_y: f32, to_x: f32, to_y: f32) -> bool {
+        self.los
+            .map(|los| los.has_los(self.terrain, from_x, from_y, to_x, to_y))
+            .unwrap_or(true)
+    }
+
+    /// Whether terrain and buildings leave a clear line of fire to a point.
+    /// Smoke doesn't block it; it only costs accuracy.
+    fn has_line_of_fire(&self, from_x: f32, from_y: f32, to_x: f32, to_y: f32) -> bool {
+        self.los
+            .map(|los| los.trace_solid(self.terrain, from_x, from_y, to_x, to_y))
+            .unwrap_or(true)
+    }
+
+    /// How much smoke obscures the line of fire (0.0 = clear).
+    fn obscuration(&self, from_x: f32, from_y: f32, to_x: f32, to_y: f32) -> f32 {
+        self.los
+            .map(|los| los.obscuration(from_x, from_y, to_x, to_y))
+            .unwrap_or(0.0)
+    }
+
+    /// Ground height at a point (flat without terrain).
+    fn height_at(&self, x: f32, y: f32) -> f32 {
+        self.terrain.map(|t| t.get_height_at(x, y)).unwrap_or(0.0)
+    }
 }
 
 /// Combat gather system - computes damage intents without applying them.
@@ -247,17 +474,16 @@ struct AttackerData {
 /// ## Complexity: O(n × k) where n = attackers, k = avg enemies per query
 /// 
 /// ## Data Access (READ-ONLY on entities)
-/// - Reads: DeltaTime, SpatialGrid, SimTick, TerrainResource
-/// - Reads: Position, Faction, SquadStats, Health, Suppression, Morale, SimLod
+/// - Reads: DeltaTime, SpatialGrid, SimTick, TerrainResource, LineOfSight, CoverZones, CoverProviderIndex
+/// - Reads: Position, Faction, SquadStats, Health, Suppression, Morale, SimLod, InCover
+/// - Reads: Velocity, UnitClass, FirePriority, Order, Garrisoned, FireStance, SquadId, IncomingFire
 /// - Writes: PendingCombatResults (resource only)
 /// 
 /// This system can run in parallel with other read-only systems because
 /// it only writes to a dedicated resource.
 pub fn combat_gather_system(
-    dt: Res<DeltaTime>,
-    grid: Res<SpatialGrid>,
+    env: CombatEnvironment,
     tick: Option<Res<SimTick>>,
-    terrain: Option<Res<TerrainResource>>,
     mut pending: ResMut<PendingCombatResults>,
     query: Query<(
         Entity,
@@ -268,43 +494,42 @@ pub fn combat_gather_system(
         &Suppression,
         &Morale,
         Option<&SimLod>,
-    )>,
+        Option<&InCover>,
+        TargetingData,
+    ), Without<Surrendered>>,
+    squad_ids: Query<(Entity, &SquadId)>,
+    incoming: Query<&IncomingFire>,
 ) {
-    let delta = dt.0;
     let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
-    
+    let terrain_guard = env.terrain.as_ref().and_then(|t| t.read());
+
+    // Last tick's target assignments spread fire across targets this tick
+    let engagements = Engagements::from_assignments(std::mem::take(&mut pending.0.targets));
+
     // Clear previous results
     pending.0 = CombatResults::default();
 
-    // GATHER PHASE: Collect attacker data (read-only iteration)
+    // GATHER PHASE: Collect attacker and target data (read-only iteration)
     // Complexity: O(n) where n = total entities
+    let targets: HashMap<Entity, TargetInfo> = query.iter()
+        .map(|(entity, _, _, _, _, suppression, _, lod, _, targeting)| {
+            (entity, target_info(suppression, lod, targeting))
+        })
+        .collect();
+    let squads: HashMap<u32, Entity> = squad_ids.iter().map(|(entity, id)| (id.0, entity)).collect();
     let attackers: Vec<AttackerData> = query.iter()
-        .filter(|(_, _, _, _, health, suppression, morale, lod)| {
-            if !health.is_alive() || suppression.value >= 1.0 || morale.value < 0.2 {
-                return false;
-            }
-            if let Some(lod) = lod {
-                if !lod.should_update(current_tick) {
-                    return false;
-                }
-            }
-            true
+        .filter(|(entity, _, _, _, health, suppression, morale, lod, _, _)| {
+            can_fire(health, suppression, morale, *lod, current_tick) && !env.in_close_assault(*entity)
         })
-        .map(|(entity, faction, pos, stats, _, suppression, morale, lod)| {
-            AttackerData {
-                entity,
-                faction: match faction { Faction::Blue => 0, Faction::Red => 1 },
-                x: pos.x,
-                y: pos.y,
-                fire_range: stats.fire_range,
-                accuracy: stats.accuracy,
-                size: stats.size,
-                suppression: suppression.value,
-                morale: morale.value,
-                lod_multiplier: lod.map(|l| l.tick_interval() as f32).unwrap_or(1.0),
-            }
+        .map(|(entity, faction, pos, stats, _, suppression, morale, lod, in_cover, targeting)| {
+            AttackerData::new(entity, faction, pos, stats, suppression, morale, lod)
+                .with_cover(in_cover)
+                .with_targeting(targeting)
+                .with_focus(&squads)
+                .with_incoming(incoming.get(entity).ok(), current_tick)
         })
         .collect();
+    let ctx = env.context(terrain_guard.as_deref(), &targets, &engagements);
 
     // COMPUTE PHASE: Calculate combat interactions
     // Complexity: O(n × k) where n = attackers, k = avg enemies per spatial query
@@ -316,9 +541,7 @@ pub fn combat_gather_system(
         // Each thread computes its own CombatResults, then we merge them
         let partial_results: Vec<CombatResults> = attackers
             .par_iter()
-            .map(|attacker| {
-                compute_attacker_combat(attacker, &grid, terrain.as_ref().map(|t| t.as_ref()), delta)
-            })
+            .map(|attacker| compute_attacker_combat(attacker, &ctx))
             .collect();
         
         // Merge all partial results
@@ -331,7 +554,7 @@ pub fn combat_gather_system(
     {
         // SEQUENTIAL MODE: Process attackers one by one
         for attacker in &attackers {
-            let result = compute_attacker_combat(attacker, &grid, terrain.as_ref().map(|t| t.as_ref()), delta);
+            let result = compute_attacker_combat(attacker, &ctx);
             pending.0.merge(result);
         }
     }
@@ -339,62 +562,147 @@ pub fn combat_gather_system(
 
 /// Compute combat for a single attacker. Returns partial CombatResults.
 /// This function is pure and can be called in parallel.
-fn compute_attacker_combat(
-    attacker: &AttackerData,
-    grid: &SpatialGrid,
-    terrain: Option<&TerrainResource>,
-    delta: f32,
-) -> CombatResults {
+///
+/// Fires at the best-scoring visible enemy in range (see `targeting`). The
+/// target's cover is judged along the line of fire, so flanking fire
+/// bypasses oriented cover. Higher ground extends range and improves
+/// accuracy; firing uphill or through thin smoke costs accuracy.
+///
+/// Squads whose `FireStance` holds fire don't fire at all, area fire
+/// included.
+fn compute_attacker_combat(attacker: &AttackerData, ctx: &CombatContext) -> CombatResults {
     let mut result = CombatResults::default();
-    
-    // Spatial query: O(k) where k = enemies in range
-    let enemies = grid.query_enemies(attacker.x, attacker.y, attacker.fire_range, attacker.faction);
+    if attacker.stance.holds_fire(attacker.under_fire) {
+        return result;
+    }
 
-    // Find best target: O(k)
-    let mut best_target: Option<(Entity, f32, f32)> = None;
-    for enemy in &enemies {
-        let dx = enemy.x - attacker.x;
-        let dy = enemy.y - attacker.y;
-        let dist = (dx * dx + dy * dy).sqrt();
+    if let Some(area) = attacker.area_fire {
+        compute_area_fire(attacker, area, ctx, &mut result);
+        return result;
+    }
 
-        if dist <= attacker.fire_range {
-            let target_cover = terrain
-                .map(|t| t.get_cover_at(enemy.x, enemy.y))
-                .unwrap_or(0.0);
+    // Calculate damage if target found
+    if let Some(target) = select_target(attacker, ctx) {
+        result.fired.push(attacker.entity);
+        result.targets.insert(attacker.entity, target.entity);
 
-            if best_target.is_none() || dist < best_target.unwrap().1 {
-                best_target = Some((enemy.entity, dist, target_cover));
-            }
+        let fire_range = attacker.fire_range * elevation_range_factor(target.height_advantage);
+        let elevation = elevation_accuracy_factor(target.height_advantage);
+        let smoke = 1.0 - SMOKE_ACCURACY_PENALTY * ctx.obscuration(attacker.x, attacker.y, target.x, target.y);
+        let hits = attacker.expected_hits(target.dist, fire_range, elevation * smoke, ctx.delta);
+
+        result.add_hits(attacker, target.entity, hits, target.cover, 1.0, 1.0);
+    }
+    
+    result
+}
+
+/// Area fire for a `SuppressArea` order: the fire volume sweeps the zone.
+///
+/// Every enemy in the zone is suppressed whether or not the shooter can
+/// see it, so the order works against enemies known only vaguely. The zone
+/// itself must be in range and not hidden by terrain or buildings; smoke
+/// costs accuracy as it does for aimed fire. Lethality is low. The hits are
+/// spread over the enemies present, so a crowded zone takes no more fire in
+/// total than a lone squad; larger zones dilute them.
+fn compute_area_fire(attacker: &AttackerData, (x, y, radius): (f32, f32, f32), ctx: &CombatContext, result: &mut CombatResults) {
+    let dist = ((x - attacker.x).powi(2) + (y - attacker.y).powi(2)).sqrt();
+    if dist > attacker.fire_range || !ctx.has_line_of_fire(attacker.x, attacker.y, x, y) {
+        return;
+    }
+    // Low-LOD shooters leave low-LOD enemies to the sector pools
+    let mut enemies = ctx.grid.query_enemies(x, y, radius, attacker.faction);
+    if attacker.pooled {
+        enemies.retain(|enemy| !ctx.targets.get(&enemy.entity).is_some_and(|t| t.pooled));
+        if enemies.is_empty() {
+            return;
         }
     }
+    result.fired.push(attacker.entity);
 
-    // Calculate damage if target found
-    if let Some((target_entity, dist, target_cover)) = best_target {
-        result.fired.push(attacker.entity);
+    let smoke = 1.0 - SMOKE_ACCURACY_PENALTY * ctx.obscuration(attacker.x, attacker.y, x, y);
+    let hits = attacker.expected_hits(dist, attacker.fire_range, smoke, ctx.delta);
+    let concentration = (AREA_FIRE_REFERENCE_RADIUS / radius.max(0.001)).min(1.0);
+    let share = 1.0 / enemies.len().max(1) as f32;
 
-        let range_factor = if dist > attacker.fire_range * RANGE_FALLOFF_START {
-            1.0 - ((dist - attacker.fire_range * RANGE_FALLOFF_START) 
-                   / (attacker.fire_range * (1.0 - RANGE_FALLOFF_START)))
-        } else {
-            1.0
-        };
+    for enemy in &enemies {
+        let cover = ctx.cover.evaluate_from(attacker.x, attacker.y, enemy.x, enemy.y);
+        result.add_hits(
+            attacker,
+            enemy.entity,
+            hits * concentration * share,
+            cover,
+            AREA_FIRE_LETHALITY,
+            AREA_FIRE_SUPPRESSION,
+        );
+    }
+}
 
-        let suppression_penalty = 1.0 - (attacker.suppression * 0.5).min(0.8);
-        let morale_factor = 0.5 + attacker.morale * 0.5;
-        let effective_accuracy = attacker.accuracy * range_factor * suppression_penalty * morale_factor;
+/// Choose a target among the visible enemies in range.
+///
+/// Enemies are sorted closest first; at most `MAX_TARGET_CANDIDATES` visible
+/// ones are scored. `FirePriority::Closest` takes the first visible enemy.
+/// Range is extended against targets below the shooter. Garrisoned enemies
+/// are only spotted within `GARRISON_SPOTTING_RANGE`. A squad on
+/// `FireStance::EngageTarget` only takes its target; one on
+/// `FireStance::ReturnFire` answers its attacker when it can see it.
+/// Otherwise low-LOD shooters skip low-LOD enemies, which the sector pools
+/// already cover.
+fn select_target(attacker: &AttackerData, ctx: &CombatContext) -> Option<TargetCandidate> {
+    let shooter_height = ctx.height_at(attacker.x, attacker.y) + attacker.elevation;
+    let search_range = attacker.fire_range * elevation_range_factor(shooter_height);
 
-        let shots = attacker.size as f32 * delta * 2.0 * attacker.lod_multiplier;
-        let hits = shots * effective_accuracy * BASE_HIT_CHANCE;
+    // Spatial query: O(k) where k = enemies in range, sorted closest first
+    let enemies = ctx.grid.query_enemies(attacker.x, attacker.y, search_range, attacker.faction);
 
-        let cover_reduction = 1.0 - (target_cover * MAX_COVER_REDUCTION);
-        let final_damage = hits * DAMAGE_PER_HIT * cover_reduction;
+    let mut candidates = enemies.iter()
+        .filter_map(|enemy| {
+            let dist = ((enemy.x - attacker.x).powi(2) + (enemy.y - attacker.y).powi(2)).sqrt();
+            let info = ctx.targets.get(&enemy.entity).copied().unwrap_or_default();
+            let engaging = matches!(attacker.stance, FireStance::EngageTarget(_));
+            if (info.concealed && dist > GARRISON_SPOTTING_RANGE) || (attacker.pooled && info.pooled && !engaging) {
+                return None;
+            }
+            let height_advantage = shooter_height - ctx.height_at(enemy.x, enemy.y) - info.elevation;
+            let in_range = dist <= attacker.fire_range * elevation_range_factor(height_advantage);
+            (in_range && ctx.has_los(attacker.x, attacker.y, enemy.x, enemy.y)).then(|| TargetCandidate {
+                entity: enemy.entity,
+                x: enemy.x,
+                y: enemy.y,
+                dist,
+                cover: ctx.cover.evaluate_from(attacker.x, attacker.y, enemy.x, enemy.y),
+                height_advantage,
+                info,
+            })
+        });
 
-        *result.damage.entry(target_entity).or_insert(0.0) += final_damage;
-        *result.suppression.entry(target_entity).or_insert(0.0) += 
-            hits * SUPPRESSION_PER_HIT * (1.0 - target_cover * 0.3);
+    match (attacker.stance, attacker.focus) {
+        (FireStance::EngageTarget(_), focus) => {
+            return focus.and_then(|focus| candidates.find(|c| c.entity == focus));
+        }
+        (FireStance::ReturnFire, Some(focus)) => {
+            if let Some(candidate) = candidates.clone().find(|c| c.entity == focus) {
+                return Some(candidate);
+            }
+        }
+        _ => {}
     }
-    
-    result
+    if attacker.priority == FirePriority::Closest {
+        return candidates.next();
+    }
+
+    let shooter = attacker.profile();
+    candidates
+        .take(MAX_TARGET_CANDIDATES)
+        .map(|candidate| {
+            let engaged = ctx.engagements.others_engaging(candidate.entity, attacker.entity);
+            (score_target(&shooter, &candidate, engaged), candidate)
+        })
+        .fold(None, |best: Option<(f32, TargetCandidate)>, (score, candidate)| match best {
+            Some((best_score, _)) if best_score >= score => best,
+            _ => Some((score, candidate)),
+        })
+        .map(|(_, candidate)| candidate)
 }
 
 /// Combat apply system - applies pending damage and suppression.
@@ -402,23 +710,34 @@ fn compute_attacker_combat(
 /// ## Complexity: O(n + m) where n = entities, m = damage events
 /// 
 /// ## Data Access
-/// - Reads: SimTick, PendingCombatResults
-/// - Writes: Health, Suppression, ActivityFlags
+/// - Reads: SimTick
+/// - Writes: Health, Suppression, CombatStats, IncomingFire, ActivityFlags, DestructibleHealth
+/// - Writes: PendingCombatResults (kill attribution)
 /// 
 /// This system must run after combat_gather_system and should be sequential.
 pub fn combat_apply_system(
     tick: Option<Res<SimTick>>,
-    pending: Res<PendingCombatResults>,
-    mut query: Query<(Entity, &mut Health, &mut Suppression)>,
+    mut pending: ResMut<PendingCombatResults>,
+    mut query: Query<(Entity, &mut Health, &mut Suppression, Option<&mut CombatStats>)>,
     mut activity_query: Query<&mut ActivityFlags>,
+    mut structures: Query<&mut DestructibleHealth>,
+    mut incoming: Query<&mut IncomingFire>,
 ) {
     let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
-    let results = &pending.0;
+    let results = &mut pending.0;
+    let mut killed = Vec::new();
 
     // Apply damage and suppression: O(n) iteration, O(1) lookup per entity
-    for (entity, mut health, mut suppression) in query.iter_mut() {
+    for (entity, mut health, mut suppression, stats) in query.iter_mut() {
         if let Some(&dmg) = results.damage.get(&entity) {
+            let was_alive = health.is_alive();
             health.damage(dmg);
+            if was_alive && !health.is_alive() {
+                killed.push(entity);
+            }
+            if let Some(mut stats) = stats {
+                stats.damage_taken += dmg;
+            }
             if let Ok(mut flags) = activity_query.get_mut(entity) {
                 flags.mark_damaged(current_tick);
             }
@@ -428,6 +747,19 @@ pub fn combat_apply_system(
         }
     }
 
+    // Attribute hits, damage and kills to the shooters
+    results.credit_kills(&killed);
+    for record in &results.fire {
+        if let Ok((_, _, _, Some(mut stats))) = query.get_mut(record.shooter) {
+            stats.hits += record.hits;
+            stats.damage_dealt += record.damage;
+            stats.kills += u32::from(record.killed);
+        }
+    }
+
+    record_incoming_fire(&results.fire, current_tick, &mut incoming);
+    damage_structures(&results.structure_damage, &mut structures);
+
     // Update firing flags: O(m) where m = entities that fired
     for entity in &results.fired {
         if let Ok(mut flags) = activity_query.get_mut(*entity) {
@@ -446,6 +778,7 @@ mod tests {
         let mut world = World::new();
         world.insert_resource(DeltaTime(0.1));
         world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
 
         // Spawn two opposing squads in range
         world.spawn((
@@ -469,7 +802,7 @@ mod tests {
         ));
 
         let mut schedule = Schedule::default();
-        schedule.add_systems((spatial_grid_update_system, combat_system).chain());
+        schedule.add_systems((spatial_grid_update_system, combat_gather_system, combat_apply_system).chain());
         
         // Run multiple ticks to accumulate damage
         for _ in 0..10 {
@@ -483,4 +816,503 @@ mod tests {
             assert!(sup.value > 0.0, "Suppression should increase from combat");
         }
     }
+
+    #[test]
+    fn test_combat_requires_line_of_sight() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+        world.insert_resource(LineOfSight::new());
+
+        // Building between the two squads blocks sight
+        world.spawn(BuildingBundle::new(1, 15.0, 0.0));
+
+        world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+        ));
+
+        world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(30.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+        ));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            spatial_grid_update_system,
+            crate::los::los_update_system,
+            combat_gather_system,
+            combat_apply_system,
+        ).chain());
+
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+
+        let mut query = world.query::<(&SquadId, &Health)>();
+        for (_, health) in query.iter(&world) {
+            assert_eq!(health.current, 100.0, "Hidden squads should not exchange fire");
+        }
+    }
+
+    #[test]
+    fn test_area_fire_suppresses_unseen_enemy_at_low_lethality() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+        world.insert_resource(LineOfSight::new());
+        // The building hides Red but not the zone centre
+        world.spawn(BuildingBundle::new(1, 15.0, 3.0));
+
+        let blue = world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+            Order::SuppressArea { x: 28.0, y: -1.0, radius: 8.0 },
+        )).id();
+        let red = world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(30.0, 6.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            spatial_grid_update_system,
+            crate::los::los_update_system,
+            combat_gather_system,
+            combat_apply_system,
+        ).chain());
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+
+        // Blue fires blind past the building; Red can't see to answer
+        assert!(world.get::<Suppression>(red).unwrap().value > 0.0);
+        assert_eq!(world.get::<Health>(blue).unwrap().current, 100.0);
+        let area_loss = 100.0 - world.get::<Health>(red).unwrap().current;
+        let aimed_loss = 100.0 - red_health_after_duel(None);
+        assert!(area_loss > 0.0 && area_loss < aimed_loss * 0.5,
+            "area fire lost {}, aimed fire lost {}", area_loss, aimed_loss);
+    }
+
+    #[test]
+    fn test_area_fire_needs_line_of_fire_to_zone() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+        world.insert_resource(LineOfSight::new());
+        world.spawn(BuildingBundle::new(1, 15.0, 0.0));
+
+        world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+            Order::SuppressArea { x: 30.0, y: 0.0, radius: 8.0 },
+        ));
+        let red = world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(30.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            spatial_grid_update_system,
+            crate::los::los_update_system,
+            combat_gather_system,
+            combat_apply_system,
+        ).chain());
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+
+        // The building stands between Blue and the whole zone
+        assert_eq!(world.get::<Suppression>(red).unwrap().value, 0.0);
+        assert_eq!(world.get::<Health>(red).unwrap().current, 100.0);
+    }
+
+    /// Total suppression dealt and hits credited after a second of area
+    /// fire into a zone holding `enemies` squads.
+    fn area_fire_totals(enemies: usize) -> (f32, f32) {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+        let blue = world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+            CombatStats::default(),
+            Order::SuppressArea { x: 30.0, y: 0.0, radius: 8.0 },
+        )).id();
+        for i in 0..enemies {
+            world.spawn((
+                SquadId(10 + i as u32),
+                Faction::Red,
+                Position::new(30.0, i as f32 * 2.0 - 3.0),
+                SquadStats::default(),
+                Health::new(100.0),
+                Suppression::default(),
+                Morale::default(),
+                FireStance::HoldFire,
+            ));
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, combat_gather_system, combat_apply_system).chain());
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+
+        let mut red = world.query_filtered::<&Suppression, Without<CombatStats>>();
+        let suppression = red.iter(&world).map(|s| s.value).sum();
+        (suppression, world.get::<CombatStats>(blue).unwrap().hits)
+    }
+
+    #[test]
+    fn test_area_fire_totals_do_not_grow_with_enemies_in_zone() {
+        let (one_suppression, one_hits) = area_fire_totals(1);
+        let (four_suppression, four_hits) = area_fire_totals(4);
+        assert!(one_suppression > 0.0 && one_hits > 0.0);
+        assert!((four_suppression - one_suppression).abs() < 1e-4,
+            "one enemy took {} suppression, four took {}", one_suppression, four_suppression);
+        assert!((four_hits - one_hits).abs() < 1e-4, "one enemy: {} hits logged, four: {}", one_hits, four_hits);
+    }
+
+    /// Run a duel between two squads and return the Red squad's remaining health.
+    fn red_health_after_duel(providers: Option<CoverProviderIndex>) -> f32 {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+        if let Some(providers) = providers {
+            world.insert_resource(providers);
+        }
+
+        world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+        ));
+        let red = world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(30.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, combat_gather_system, combat_apply_system).chain());
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+        world.get::<Health>(red).unwrap().current
+    }
+
+    #[test]
+    fn test_high_ground_outranges_squad_below() {
+        let mut grid = TerrainGrid::new(100, 50, 2.0);
+        grid.add_hill(0.0, 0.0, 20.0, 6.0);
+
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+        world.insert_resource(TerrainResource::new(grid));
+
+        let spawn = |world: &mut World, id: u32, faction: Faction, x: f32| {
+            world.spawn((
+                SquadId(id),
+                faction,
+                Position::new(x, 0.0),
+                SquadStats::default(),
+                Health::new(100.0),
+                Suppression::default(),
+                Morale::default(),
+            )).id()
+        };
+        // Beyond the default 60 unit range, but not from the hilltop
+        let on_hill = spawn(&mut world, 1, Faction::Blue, 0.0);
+        let below = spawn(&mut world, 2, Faction::Red, 70.0);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, combat_gather_system, combat_apply_system).chain());
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+
+        assert!(world.get::<Health>(below).unwrap().current < 100.0);
+        assert_eq!(world.get::<Health>(on_hill).unwrap().current, 100.0);
+    }
+
+    #[test]
+    fn test_cover_provider_reduces_damage() {
+        let open = red_health_after_duel(None);
+
+        let mut providers = CoverProviderIndex::default();
+        providers.insert(crate::systems::cover::CoverProviderSample {
+            entity: Entity::from_raw(99),
+            x: 32.0,
+            y: 0.0,
+            radius: 5.0,
+            cover: 0.7,
+        });
+        let covered = red_health_after_duel(Some(providers));
+
+        assert!(covered > open, "Squad behind a building should take less damage ({} vs {})", covered, open);
+    }
+
+    #[test]
+    fn test_sustained_fire_reduces_sheltering_building_to_rubble() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+        world.insert_resource(CoverProviderIndex::default());
+        let building = world.spawn(BuildingBundle::new(1, 30.0, 0.0)).id();
+
+        world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+            UnitClass::AntiTank,
+        ));
+        // Sheltering in the building, too shaken to fire back
+        world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(30.0, 0.0),
+            SquadStats::default(),
+            Health::new(1.0e6),
+            Suppression::default(),
+            Morale::new(0.0),
+        ));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            spatial_grid_update_system,
+            crate::systems::cover::cover_provider_index_system,
+            combat_gather_system,
+            combat_apply_system,
+            crate::systems::destruction::destruction_state_system,
+        ).chain());
+
+        schedule.run(&mut world);
+        let health = |world: &World| world.get::<DestructibleHealth>(building).unwrap().current;
+        assert!(health(&world) < 150.0, "fire stopped by the building damages it");
+
+        for _ in 0..1500 {
+            schedule.run(&mut world);
+        }
+        assert_eq!(*world.get::<DestructibleState>(building).unwrap(), DestructibleState::Destroyed);
+        let cover = world.resource::<CoverProviderIndex>().get_cover_at(30.0, 0.0);
+        assert!(cover < CoverProvider::building().intact_cover);
+    }
+
+    #[test]
+    fn test_target_selection_ignores_dug_in_squad_for_exposed_threat() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+
+        // Closer Red squad sheltering next to a building
+        let mut providers = CoverProviderIndex::default();
+        providers.insert(crate::systems::cover::CoverProviderSample {
+            entity: Entity::from_raw(99),
+            x: 20.0,
+            y: 0.0,
+            radius: 5.0,
+            cover: 0.9,
+        });
+        world.insert_resource(providers);
+
+        let blue = world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+        )).id();
+        let dug_in = world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(20.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale { value: 0.1 }, // too shaken to fire back
+        )).id();
+        let machine_gun = world.spawn((
+            SquadId(3),
+            Faction::Red,
+            Position::new(35.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale { value: 0.1 },
+            UnitClass::MachineGun,
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, combat_gather_system, combat_apply_system).chain());
+        schedule.run(&mut world);
+
+        assert_eq!(world.get::<Health>(dug_in).unwrap().current, 100.0);
+        assert!(world.get::<Health>(machine_gun).unwrap().current < 100.0);
+
+        // With closest-target fire the dug-in squad soaks the volume instead
+        world.entity_mut(blue).insert(FirePriority::Closest);
+        schedule.run(&mut world);
+        assert!(world.get::<Health>(dug_in).unwrap().current < 100.0);
+    }
+
+    #[test]
+    fn test_fire_stances_hold_return_and_focus_fire() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+
+        let spawn = |world: &mut World, id: u32, faction: Faction, x: f32, stance: FireStance| {
+            world.spawn((
+                SquadId(id),
+                faction,
+                Position::new(x, 0.0),
+                SquadStats::default(),
+                Health::new(100.0),
+                Suppression::default(),
+                Morale::default(),
+                IncomingFire::default(),
+                stance,
+            )).id()
+        };
+        let blue = spawn(&mut world, 1, Faction::Blue, 0.0, FireStance::HoldFire);
+        let near = spawn(&mut world, 2, Faction::Red, 20.0, FireStance::ReturnFire);
+        let far = spawn(&mut world, 3, Faction::Red, 40.0, FireStance::HoldFire);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, combat_gather_system, combat_apply_system).chain());
+        let fired = |world: &World| world.resource::<PendingCombatResults>().0.targets.clone();
+
+        // Nobody opens fire; return fire waits to be shot at
+        schedule.run(&mut world);
+        assert!(fired(&world).is_empty());
+
+        // Focused on the farther squad, Blue ignores the closer one
+        world.entity_mut(blue).insert(FireStance::EngageTarget(3));
+        schedule.run(&mut world);
+        assert_eq!(fired(&world).get(&blue), Some(&far));
+        assert!(!fired(&world).contains_key(&near));
+
+        // Only the squad Blue shot at knows it is under fire
+        assert_eq!(world.get::<IncomingFire>(far).unwrap().attacker, Some(blue));
+        assert_eq!(world.get::<IncomingFire>(near).unwrap().tick, None);
+
+        // Once fired upon, the return-fire squad shoots back at its attacker
+        // even with a weaker-looking target closer to hand
+        let decoy = spawn(&mut world, 4, Faction::Blue, 15.0, FireStance::HoldFire);
+        world.entity_mut(blue).insert(FireStance::EngageTarget(2));
+        schedule.run(&mut world);
+        world.entity_mut(blue).insert(FireStance::HoldFire);
+        schedule.run(&mut world);
+        assert_eq!(fired(&world).get(&near), Some(&blue));
+        assert!(!fired(&world).contains_key(&decoy));
+        assert!(!fired(&world).contains_key(&far));
+    }
+
+    #[test]
+    fn test_return_fire_squad_stays_silent_under_artillery() {
+        use crate::systems::explosion::blast_damage_system;
+
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.init_resource::<PendingCombatResults>();
+        world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+            FireStance::HoldFire,
+        ));
+        let red = world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(30.0, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+            IncomingFire::default(),
+            FireStance::ReturnFire,
+        )).id();
+        // A shell lands near Red's position
+        world.spawn(TerrainDamageEvent { x: 38.0, y: 0.0, radius: 4.0, depth: 1.5 });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            blast_damage_system,
+            spatial_grid_update_system,
+            combat_gather_system,
+            combat_apply_system,
+        ).chain());
+        for _ in 0..5 {
+            schedule.run(&mut world);
+        }
+
+        assert!(world.get::<Suppression>(red).unwrap().value > 0.0);
+        assert!(!world.resource::<PendingCombatResults>().0.fired.contains(&red));
+        assert_eq!(world.get::<IncomingFire>(red).unwrap().tick, None);
+    }
 }
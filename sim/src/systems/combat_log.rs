@@ -0,0 +1,202 @@
+//! Combat event log - who shot whom, with what effect.
+//!
+//! `CombatResults` aggregates damage per target for the apply phase; the
+//! attributed `FireRecord`s it also carries are turned into log entries
+//! here. The log is optional: it is only filled while a `CombatLog`
+//! resource exists (see `SimWorld::enable_combat_log`), and clients drain it
+//! for tracers, muzzle flashes, after-action reports and balance debugging.
+
+use crate::components::*;
+use crate::systems::combat::PendingCombatResults;
+use crate::systems::performance::SimTick;
+use bevy_ecs::prelude::*;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Default maximum number of undrained entries kept.
+pub const DEFAULT_COMBAT_LOG_CAPACITY: usize = 10_000;
+
+/// One squad firing at another during one tick.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CombatLogEntry {
+    pub tick: u64,
+    pub shooter: u32,
+    pub target: u32,
+    pub shooter_x: f32,
+    pub shooter_y: f32,
+    pub target_x: f32,
+    pub target_y: f32,
+    pub weapon: UnitClass,
+    pub hits: f32,
+    pub damage: f32,
+    pub suppression: f32,
+    /// The target's cover along the line of fire (0.0-1.0).
+    pub cover: f32,
+    /// Whether this fire finished the target off.
+    pub killed: bool,
+}
+
+/// Buffer of combat log entries awaiting a drain.
+///
+/// When more than `capacity` entries pile up, the oldest are dropped.
+#[derive(Resource, Debug, Clone)]
+pub struct CombatLog {
+    entries: VecDeque<CombatLogEntry>,
+    capacity: usize,
+}
+
+impl Default for CombatLog {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_COMBAT_LOG_CAPACITY)
+    }
+}
+
+impl CombatLog {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { entries: VecDeque::new(), capacity }
+    }
+
+    /// Append an entry, dropping the oldest if over capacity.
+    pub fn push(&mut self, entry: CombatLogEntry) {
+        self.entries.push_back(entry);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Entries not yet drained, oldest first.
+    pub fn entries(&self) -> &VecDeque<CombatLogEntry> {
+        &self.entries
+    }
+
+    /// Take all entries, oldest first.
+    pub fn drain(&mut self) -> Vec<CombatLogEntry> {
+        std::mem::take(&mut self.entries).into()
+    }
+}
+
+/// Record this tick's attributed fire into the combat log.
+///
+/// ## Data Access
+/// - Reads: SimTick, PendingCombatResults, SquadId, Position
+/// - Writes: CombatLog
+///
+/// Runs after `combat_apply_system` so kills are attributed.
+pub fn combat_log_system(
+    tick: Option<Res<SimTick>>,
+    pending: Res<PendingCombatResults>,
+    log: Option<ResMut<CombatLog>>,
+    squads: Query<(&SquadId, &Position)>,
+) {
+    let Some(mut log) = log else {
+        return;
+    };
+    let current_tick = tick.map(|t| t.0).unwrap_or(0);
+
+    for record in &pending.0.fire {
+        let (Ok((shooter, from)), Ok((target, to))) = (squads.get(record.shooter), squads.get(record.target)) else {
+            continue;
+        };
+        log.push(CombatLogEntry {
+            tick: current_tick,
+            shooter: shooter.0,
+            target: target.0,
+            shooter_x: from.x,
+            shooter_y: from.y,
+            target_x: to.x,
+            target_y: to.y,
+            weapon: record.weapon,
+            hits: record.hits,
+            damage: record.damage,
+            suppression: record.suppression,
+            cover: record.cover,
+            killed: record.killed,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial::{spatial_grid_update_system, SpatialGrid};
+    use crate::systems::combat::{combat_apply_system, combat_gather_system};
+    use crate::systems::movement::DeltaTime;
+
+    fn spawn_squad(world: &mut World, id: u32, faction: Faction, x: f32, health: f32) -> Entity {
+        world.spawn((
+            SquadId(id),
+            faction,
+            Position::new(x, 0.0),
+            SquadStats::default(),
+            Health::new(health),
+            Suppression::default(),
+            Morale::default(),
+            CombatStats::default(),
+        )).id()
+    }
+
+    #[test]
+    fn test_fire_is_attributed_and_kill_credited_once() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.insert_resource(PendingCombatResults::default());
+        world.insert_resource(CombatLog::default());
+
+        let rifles = spawn_squad(&mut world, 1, Faction::Blue, 0.0, 100.0);
+        let gunners = spawn_squad(&mut world, 2, Faction::Blue, 0.0, 100.0);
+        world.entity_mut(gunners).insert(UnitClass::MachineGun);
+        let victim = spawn_squad(&mut world, 3, Faction::Red, 20.0, 0.01);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            spatial_grid_update_system,
+            combat_gather_system,
+            combat_apply_system,
+            combat_log_system,
+        ).chain());
+        schedule.run(&mut world);
+
+        let entries = world.resource_mut::<CombatLog>().drain();
+        let on_victim: Vec<_> = entries.iter().filter(|e| e.target == 3).collect();
+        assert_eq!(on_victim.len(), 2);
+        assert!(on_victim.iter().any(|e| e.shooter == 2 && e.weapon == UnitClass::MachineGun));
+        assert!(on_victim.iter().all(|e| e.hits > 0.0 && e.damage > 0.0 && e.target_x == 20.0));
+        assert_eq!(on_victim.iter().filter(|e| e.killed).count(), 1);
+
+        let stats = |e: Entity| *world.get::<CombatStats>(e).unwrap();
+        assert_eq!(stats(rifles).kills + stats(gunners).kills, 1);
+        let dealt: f32 = on_victim.iter().map(|e| e.damage).sum();
+        assert!((stats(victim).damage_taken - dealt).abs() < 1e-4);
+        assert!(stats(rifles).damage_dealt > 0.0);
+    }
+
+    fn entry(tick: u64) -> CombatLogEntry {
+        CombatLogEntry {
+            tick,
+            shooter: 1,
+            target: 2,
+            shooter_x: 0.0,
+            shooter_y: 0.0,
+            target_x: 10.0,
+            target_y: 0.0,
+            weapon: UnitClass::Rifle,
+            hits: 0.1,
+            damage: 0.8,
+            suppression: 0.02,
+            cover: 0.0,
+            killed: false,
+        }
+    }
+
+    #[test]
+    fn test_log_drops_oldest_over_capacity() {
+        let mut log = CombatLog::with_capacity(3);
+        for tick in 0..5 {
+            log.push(entry(tick));
+        }
+        let ticks: Vec<u64> = log.drain().iter().map(|e| e.tick).collect();
+        assert_eq!(ticks, vec![2, 3, 4]);
+        assert!(log.entries().is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+//! Command hierarchy: squads report to platoons, platoons to companies.
+//!
+//! Each platoon or company is its own entity with a `CommandGroup`. Squads
+//! and platoons point at their parent group with `CommandParent`. The
+//! hierarchy is used three ways:
+//!
+//! - Orders given to a group are split among its squads (`SimWorld::order_group`).
+//! - AI squads advance on their group's `CommandObjective` in line abreast,
+//!   and squads of a platoon move up to contacts spotted by their neighbours.
+//! - Losses anywhere in a group shake the morale of every squad in it.
+
+use crate::components::*;
+use crate::systems::formation::{Formation, FormationMember};
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Most levels above a squad (platoon, company).
+const MAX_COMMAND_DEPTH: usize = 2;
+
+/// Morale lost per unit of platoon strength lost.
+const PLATOON_LOSS_SHOCK: f32 = 0.5;
+
+/// Morale lost per unit of company strength lost.
+const COMPANY_LOSS_SHOCK: f32 = 0.25;
+
+/// Distance within which platoon members move up to a contact spotted by a
+/// neighbour.
+pub const SUPPORT_RANGE: f32 = 120.0;
+
+/// Echelon of a command group. Squads are the level below platoons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum CommandLevel {
+    Platoon,
+    Company,
+}
+
+impl CommandLevel {
+    /// Morale shock to members per unit of strength this group loses.
+    fn loss_shock(&self) -> f32 {
+        match self {
+            CommandLevel::Platoon => PLATOON_LOSS_SHOCK,
+            CommandLevel::Company => COMPANY_LOSS_SHOCK,
+        }
+    }
+}
+
+/// A platoon or company. Lives on its own entity.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CommandGroup {
+    pub id: u32,
+    pub level: CommandLevel,
+    pub faction: Faction,
+    /// Health of the group's squads as a fraction of their max, as of the
+    /// last tick. Dead and surrendered squads count as nothing.
+    pub strength: f32,
+    /// Number of squads counted in `strength`.
+    pub squads: u32,
+}
+
+impl CommandGroup {
+    pub fn new(id: u32, level: CommandLevel, faction: Faction) -> Self {
+        Self { id, level, faction, strength: 1.0, squads: 0 }
+    }
+}
+
+/// ID of the command group a squad or platoon belongs to.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandParent(pub u32);
+
+/// Point a group's AI squads advance on, with the facing of their line.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CommandObjective {
+    pub x: f32,
+    pub y: f32,
+    /// Direction of the advance (radians, 0 = +x).
+    pub facing: f32,
+}
+
+/// `first` and the groups above it, nearest first. `parents` maps each
+/// group ID to its parent's.
+pub fn lineage(first: u32, parents: &HashMap<u32, Option<u32>>) -> impl Iterator<Item = u32> + '_ {
+    std::iter::successors(Some(first), |id| parents.get(id).copied().flatten()).take(MAX_COMMAND_DEPTH)
+}
+
+/// Components read and written by `command_morale_system` for squads.
+type MoraleSquadData<'a> = (&'a CommandParent, &'a Health, &'a mut Morale, Has<Surrendered>);
+
+/// System that tracks group strength and spreads the shock of losses.
+///
+/// ## Data Access
+/// - Reads: CommandParent, Health, Surrendered
+/// - Writes: CommandGroup, Morale
+///
+/// A group whose roster changed since the last tick only takes a new
+/// baseline, so assigning a battered squad isn't felt as a loss.
+pub fn command_morale_system(
+    mut groups: Query<(&mut CommandGroup, Option<&CommandParent>)>,
+    mut squads: Query<MoraleSquadData, With<SquadId>>,
+) {
+    let parents: HashMap<u32, Option<u32>> = groups.iter().map(|(g, p)| (g.id, p.map(|p| p.0))).collect();
+
+    // (current, max, count) per group, over all squads below it
+    let mut totals: BTreeMap<u32, (f32, f32, u32)> = BTreeMap::new();
+    for (parent, health, _, surrendered) in squads.iter() {
+        let current = if surrendered { 0.0 } else { health.current.max(0.0) };
+        for id in lineage(parent.0, &parents) {
+            let total = totals.entry(id).or_default();
+            total.0 += current;
+            total.1 += health.max;
+            total.2 += 1;
+        }
+    }
+
+    let mut shocks: HashMap<u32, f32> = HashMap::new();
+    for (mut group, _) in groups.iter_mut() {
+        let (current, max, count) = totals.get(&group.id).copied().unwrap_or_default();
+        let strength = if max > 0.0 { current / max } else { 1.0 };
+        let loss = group.strength - strength;
+        if count == group.squads && loss > 0.0 {
+            shocks.insert(group.id, loss * group.level.loss_shock());
+        }
+        group.strength = strength;
+        group.squads = count;
+    }
+    if shocks.is_empty() {
+        return;
+    }
+
+    for (parent, health, mut morale, surrendered) in squads.iter_mut() {
+        if surrendered || !health.is_alive() {
+            continue;
+        }
+        let shock: f32 = lineage(parent.0, &parents).filter_map(|id| shocks.get(&id)).sum();
+        if shock > 0.0 {
+            morale.decrease(shock);
+        }
+    }
+}
+
+/// Components read by `command_ai_system` for command groups.
+type GroupData<'a> = (&'a CommandGroup, Option<&'a CommandParent>, Option<&'a CommandObjective>);
+
+/// Components read and written by `command_ai_system` for squads.
+type CommandSquadData<'a> = (
+    &'a SquadId,
+    &'a Position,
+    &'a Health,
+    &'a CommandParent,
+    &'a BehaviorState,
+    &'a ThreatAwareness,
+    &'a mut Order,
+    Has<FormationMember>,
+);
+
+/// System that coordinates AI squads under their command groups.
+///
+/// ## Data Access
+/// - Reads: CommandGroup, CommandParent, CommandObjective, SquadId, Position, Health, BehaviorState, ThreatAwareness, FormationMember
+/// - Writes: Order
+///
+/// Only idle or advancing squads out of contact are steered; squads in a
+/// fight are left to `ai_order_system`, squads in a formation move to
+/// `formation_system` and squads on standing orders to `defense_system`.
+/// A squad moves up to the nearest contact any platoon-mate has within
+/// `SUPPORT_RANGE`, otherwise it takes its slot in a line across the
+/// nearest objective up its chain of command.
+pub fn command_ai_system(
+    groups: Query<GroupData>,
+    mut squads: Query<CommandSquadData, (With<AIControlled>, Without<Surrendered>)>,
+) {
+    let parents: HashMap<u32, Option<u32>> = groups.iter().map(|(g, p, _)| (g.id, p.map(|p| p.0))).collect();
+    let objectives: HashMap<u32, CommandObjective> = groups.iter()
+        .filter_map(|(g, _, objective)| objective.map(|o| (g.id, *o)))
+        .collect();
+
+    // Contacts per platoon, and squads per objective in ID order
+    let mut contacts: BTreeMap<u32, Vec<(f32, f32)>> = BTreeMap::new();
+    let mut lines: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for (id, _, health, parent, _, threat, ..) in squads.iter() {
+        if !health.is_alive() {
+            continue;
+        }
+        if let Some(contact) = threat.nearest_enemy {
+            contacts.entry(parent.0).or_default().push(contact);
+        }
+        if let Some(owner) = lineage(parent.0, &parents).find(|g| objectives.contains_key(g)) {
+            lines.entry(owner).or_default().push(id.0);
+        }
+    }
+    for line in lines.values_mut() {
+        line.sort_unstable();
+    }
+
+    for (id, pos, health, parent, state, threat, mut order, in_formation) in squads.iter_mut() {
+        if !health.is_alive()
+            || in_formation
+            || order.is_standing()
+            || threat.has_enemy_contact()
+            || !matches!(state, BehaviorState::Idle | BehaviorState::Advancing)
+        {
+            continue;
+        }
+
+        let support = contacts.get(&parent.0)
+            .into_iter()
+            .flatten()
+            .map(|&(x, y)| ((x - pos.x).hypot(y - pos.y), (x, y)))
+            .filter(|(dist, _)| *dist <= SUPPORT_RANGE)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((_, (x, y))) = support {
+            *order = Order::AttackMove { x, y };
+            continue;
+        }
+
+        let Some(owner) = lineage(parent.0, &parents).find(|g| objectives.contains_key(g)) else {
+            continue;
+        };
+        let objective = objectives[&owner];
+        let line = &lines[&owner];
+        let Some(index) = line.iter().position(|&squad| squad == id.0) else { continue };
+        let (x, y) = Formation::Line.slot_position(index, line.len(), (objective.x, objective.y), objective.facing);
+        if (x - pos.x).hypot(y - pos.y) > ARRIVAL_RADIUS {
+            *order = Order::AttackMove { x, y };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_squad(world: &mut World, id: u32, parent: u32, y: f32) -> Entity {
+        world.spawn((
+            SquadId(id),
+            Position::new(0.0, y),
+            Health::new(100.0),
+            Morale::default(),
+            CommandParent(parent),
+        )).id()
+    }
+
+    #[test]
+    fn test_platoon_losses_shake_the_whole_company() {
+        let mut world = World::new();
+        world.spawn(CommandGroup::new(1, CommandLevel::Company, Faction::Blue));
+        world.spawn((CommandGroup::new(10, CommandLevel::Platoon, Faction::Blue), CommandParent(1)));
+        world.spawn((CommandGroup::new(20, CommandLevel::Platoon, Faction::Blue), CommandParent(1)));
+        let hit = spawn_squad(&mut world, 100, 10, 0.0);
+        let mate = spawn_squad(&mut world, 101, 10, 10.0);
+        let cousin = spawn_squad(&mut world, 200, 20, 20.0);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(command_morale_system);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Morale>(mate).unwrap().value, 1.0, "first tick only sets the baseline");
+
+        world.get_mut::<Health>(hit).unwrap().damage(100.0);
+        schedule.run(&mut world);
+
+        let mate_morale = world.get::<Morale>(mate).unwrap().value;
+        let cousin_morale = world.get::<Morale>(cousin).unwrap().value;
+        assert!(mate_morale < cousin_morale, "platoon-mates feel it most");
+        assert!(cousin_morale < 1.0, "the rest of the company feels it too");
+
+        // No further losses, no further shock
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Morale>(mate).unwrap().value, mate_morale);
+    }
+
+    #[test]
+    fn test_idle_ai_squads_advance_in_line_and_support_platoon_mates() {
+        let mut world = World::new();
+        world.spawn((
+            CommandGroup::new(10, CommandLevel::Platoon, Faction::Blue),
+            CommandObjective { x: 100.0, y: 0.0, facing: 0.0 },
+        ));
+        let squads: Vec<Entity> = (0..3)
+            .map(|i| {
+                let entity = spawn_squad(&mut world, i, 10, i as f32 * 10.0);
+                world.entity_mut(entity).insert((
+                    AIControlled,
+                    BehaviorState::Idle,
+                    ThreatAwareness::default(),
+                    Order::Hold,
+                ));
+                entity
+            })
+            .collect();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(command_ai_system);
+        schedule.run(&mut world);
+
+        let targets: Vec<(f32, f32)> = squads.iter()
+            .map(|&e| match *world.get::<Order>(e).unwrap() {
+                Order::AttackMove { x, y } => (x, y),
+                other => panic!("expected attack-move, got {:?}", other),
+            })
+            .collect();
+        assert!(targets.iter().all(|(x, _)| (x - 100.0).abs() < 1e-3));
+        assert!((targets[0].1 - targets[1].1).abs() > 0.9 * crate::systems::formation::FORMATION_SPACING);
+
+        // Squad 0 spots an enemy; its idle neighbours move up to it
+        world.get_mut::<ThreatAwareness>(squads[0]).unwrap().nearest_enemy = Some((40.0, 5.0));
+        *world.get_mut::<BehaviorState>(squads[0]).unwrap() = BehaviorState::Engaging;
+        schedule.run(&mut world);
+        assert!(matches!(world.get::<Order>(squads[1]).unwrap(), Order::AttackMove { x, .. } if *x == 40.0));
+        assert!(matches!(world.get::<Order>(squads[2]).unwrap(), Order::AttackMove { x, .. } if *x == 40.0));
+    }
+}
@@ -1,8 +1,31 @@
 //! Cover system - handles terrain cover and defensive bonuses.
+//!
+//! Cover at a position is the best of three sources:
+//! - the terrain cell (`TerrainType::cover_value`),
+//! - static `CoverZones` (bunkers, scripted strongpoints),
+//! - nearby `CoverProvider` entities (trees, buildings, walls), valued by
+//!   their current `DestructibleState`.
+//!
+//! All sources are expressed as a scalar cover value (0.0 = none, 1.0 = full)
+//! and classified into a `CoverType` for suppression and firing penalties.
+//!
+//! Cover is directional: against a particular shooter it is judged along the
+//! line of fire. Trench parapets and walls only protect against fire from the
+//! side they face, and a building only shields squads it stands in front of,
+//! so flanking a dug-in position pays off.
 
 use crate::components::*;
+use crate::los::BUILDING_FOOTPRINT_FRACTION;
+use crate::terrain::{directional_cover_factor, TerrainGrid, TerrainResource};
 use bevy_ecs::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Maximum fraction of damage that full cover (value 1.0) can absorb.
+pub const MAX_COVER_REDUCTION: f32 = 0.7;
+
+/// Cell size of the cover provider lookup grid (world units).
+const PROVIDER_CELL_SIZE: f32 = 10.0;
 
 /// Cover type enumeration.
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -44,57 +67,332 @@ impl CoverType {
             CoverType::Heavy => 0.2,
         }
     }
+
+    /// Scalar cover value equivalent to this cover type, such that
+    /// `value * MAX_COVER_REDUCTION == damage_reduction()`.
+    pub fn cover_value(&self) -> f32 {
+        self.damage_reduction() / MAX_COVER_REDUCTION
+    }
+
+    /// Classify a scalar cover value (0.0 - 1.0).
+    pub fn from_value(value: f32) -> Self {
+        if value >= 0.85 {
+            CoverType::Heavy
+        } else if value >= 0.55 {
+            CoverType::Medium
+        } else if value >= 0.1 {
+            CoverType::Light
+        } else {
+            CoverType::None
+        }
+    }
 }
 
 /// Component indicating a squad is in cover.
 #[derive(Component, Debug, Clone, Copy, Default)]
 pub struct InCover {
     pub cover_type: CoverType,
+    /// Scalar cover value (0.0 - 1.0) at the squad's position.
+    pub value: f32,
+}
+
+/// Result of evaluating cover at a position.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CoverEvaluation {
+    /// Scalar cover value (0.0 = none, 1.0 = full).
+    pub value: f32,
+    /// Classified cover type.
+    pub cover_type: CoverType,
+    /// Cover provider (tree, wall, building) sheltering the position along
+    /// the line of fire, if any. Fire stopped by cover wears it down.
+    pub shelter: Option<Entity>,
+}
+
+impl CoverEvaluation {
+    pub fn from_value(value: f32) -> Self {
+        let value = value.clamp(0.0, 1.0);
+        Self {
+            value,
+            cover_type: CoverType::from_value(value),
+            shelter: None,
+        }
+    }
+
+    /// Fraction of incoming fire stopped by the cover.
+    pub fn absorbed_fraction(&self) -> f32 {
+        self.value * MAX_COVER_REDUCTION
+    }
+
+    /// Multiplier applied to incoming damage (1.0 = no protection).
+    pub fn damage_multiplier(&self) -> f32 {
+        1.0 - self.value * MAX_COVER_REDUCTION
+    }
+
+    /// Multiplier applied to incoming suppression (1.0 = no protection).
+    pub fn suppression_multiplier(&self) -> f32 {
+        1.0 - self.cover_type.suppression_reduction()
+    }
+}
+
+/// A cover provider sampled for the current tick.
+#[derive(Debug, Clone, Copy)]
+pub struct CoverProviderSample {
+    pub entity: Entity,
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    /// Cover value for the provider's current destruction state.
+    pub cover: f32,
+}
+
+impl CoverProviderSample {
+    /// Cover granted to a squad at `(x, y)` against fire arriving from
+    /// `incoming` (unit direction toward the shooter).
+    ///
+    /// Inside the provider's footprint it protects from all sides; outside,
+    /// only when the provider lies between the squad and the shooter.
+    pub fn cover_from(&self, x: f32, y: f32, incoming: (f32, f32)) -> f32 {
+        let dx = self.x - x;
+        let dy = self.y - y;
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist <= self.radius * BUILDING_FOOTPRINT_FRACTION {
+            return self.cover;
+        }
+        self.cover * directional_cover_factor((dx / dist, dy / dist), incoming)
+    }
+}
+
+/// Resource indexing `CoverProvider` entities by position.
+///
+/// Rebuilt every tick by `cover_provider_index_system`, so damage to trees and
+/// buildings changes the cover they grant on the following tick.
+#[derive(Resource, Default)]
+pub struct CoverProviderIndex {
+    providers: Vec<CoverProviderSample>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl CoverProviderIndex {
+    pub fn clear(&mut self) {
+        self.providers.clear();
+        self.cells.clear();
+    }
+
+    #[inline]
+    fn cell_of(x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / PROVIDER_CELL_SIZE).floor() as i32,
+            (y / PROVIDER_CELL_SIZE).floor() as i32,
+        )
+    }
+
+    /// Add a provider, registering it in every cell its radius overlaps.
+    pub fn insert(&mut self, sample: CoverProviderSample) {
+        let index = self.providers.len();
+        let (min_x, min_y) = Self::cell_of(sample.x - sample.radius, sample.y - sample.radius);
+        let (max_x, max_y) = Self::cell_of(sample.x + sample.radius, sample.y + sample.radius);
+        for cy in min_y..=max_y {
+            for cx in min_x..=max_x {
+                self.cells.entry((cx, cy)).or_default().push(index);
+            }
+        }
+        self.providers.push(sample);
+    }
+
+    /// Providers whose cover radius contains the given point.
+    pub fn providers_at(&self, x: f32, y: f32) -> impl Iterator<Item = &CoverProviderSample> {
+        self.cells
+            .get(&Self::cell_of(x, y))
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.providers[i])
+            .filter(move |p| (p.x - x).powi(2) + (p.y - y).powi(2) <= p.radius * p.radius)
+    }
+
+    /// Best provider cover value at a position.
+    pub fn get_cover_at(&self, x: f32, y: f32) -> f32 {
+        self.providers_at(x, y).map(|p| p.cover).fold(0.0, f32::max)
+    }
+
+    /// Best provider cover at a position against fire from `incoming`.
+    pub fn get_cover_from(&self, x: f32, y: f32, incoming: (f32, f32)) -> f32 {
+        self.shelter_from(x, y, incoming).map(|(_, cover)| cover).unwrap_or(0.0)
+    }
+
+    /// The provider giving the best cover at a position against fire from
+    /// `incoming`, with that cover value.
+    pub fn shelter_from(&self, x: f32, y: f32, incoming: (f32, f32)) -> Option<(Entity, f32)> {
+        self.providers_at(x, y)
+            .map(|p| (p.entity, p.cover_from(x, y, incoming)))
+            .filter(|(_, cover)| *cover > 0.0)
+            .fold(None, |best, (entity, cover)| match best {
+                Some((_, best_cover)) if best_cover >= cover => best,
+                _ => Some((entity, cover)),
+            })
+    }
+
+    /// Number of indexed providers.
+    pub fn len(&self) -> usize {
+        self.providers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+/// Unified cover evaluation over all cover sources.
+///
+/// Every source is optional so the evaluator works in partial worlds (tests,
+/// headless tools).
+#[derive(Clone, Copy, Default)]
+pub struct CoverEvaluator<'a> {
+    pub terrain: Option<&'a TerrainGrid>,
+    pub zones: Option<&'a CoverZones>,
+    pub providers: Option<&'a CoverProviderIndex>,
+}
+
+impl CoverEvaluator<'_> {
+    /// Evaluate cover at a position: the best of terrain, zones and providers.
+    pub fn evaluate(&self, x: f32, y: f32) -> CoverEvaluation {
+        let terrain = self.terrain.map(|t| t.get_cover_at(x, y)).unwrap_or(0.0);
+        let zone = self.zones.map(|z| z.get_cover_at(x, y).cover_value()).unwrap_or(0.0);
+        let provider = self.providers.map(|p| p.get_cover_at(x, y)).unwrap_or(0.0);
+        CoverEvaluation::from_value(terrain.max(zone).max(provider))
+    }
+
+    /// Evaluate cover at a target position against a specific shooter.
+    ///
+    /// Oriented sources only count in the direction they face. The sheltering
+    /// provider is only credited when it gives the best cover; fire stopped
+    /// by better terrain or zone cover doesn't wear it down.
+    pub fn evaluate_from(&self, from_x: f32, from_y: f32, x: f32, y: f32) -> CoverEvaluation {
+        let dx = from_x - x;
+        let dy = from_y - y;
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist < 0.001 {
+            return self.evaluate(x, y);
+        }
+        let incoming = (dx / dist, dy / dist);
+
+        let terrain = self.terrain.map(|t| t.get_cover_from(from_x, from_y, x, y)).unwrap_or(0.0);
+        let zone = self.zones.map(|z| z.get_cover_from(x, y, incoming)).unwrap_or(0.0);
+        let shelter = self.providers.and_then(|p| p.shelter_from(x, y, incoming));
+        let provider = shelter.map(|(_, cover)| cover).unwrap_or(0.0);
+        let ground = terrain.max(zone);
+        CoverEvaluation {
+            shelter: shelter.filter(|_| provider >= ground).map(|(entity, _)| entity),
+            ..CoverEvaluation::from_value(ground.max(provider))
+        }
+    }
+}
+
+/// A static cover zone.
+#[derive(Debug, Clone, Copy)]
+pub struct CoverZone {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub cover_type: CoverType,
+    /// Direction (radians) the zone's cover faces. `None` protects from all sides.
+    pub facing: Option<f32>,
+}
+
+impl CoverZone {
+    fn contains(&self, x: f32, y: f32) -> bool {
+        (x - self.x).powi(2) + (y - self.y).powi(2) <= self.radius * self.radius
+    }
 }
 
 /// Resource containing cover zones on the battlefield.
 #[derive(Resource, Default)]
 pub struct CoverZones {
-    /// List of cover zones: (x, y, radius, cover_type)
-    pub zones: Vec<(f32, f32, f32, CoverType)>,
+    pub zones: Vec<CoverZone>,
 }
 
 impl CoverZones {
-    /// Add a cover zone.
+    /// Add a cover zone that protects from all sides.
     pub fn add_zone(&mut self, x: f32, y: f32, radius: f32, cover_type: CoverType) {
-        self.zones.push((x, y, radius, cover_type));
+        self.zones.push(CoverZone { x, y, radius, cover_type, facing: None });
+    }
+
+    /// Add an oriented cover zone (wall, breastwork) facing `facing` radians.
+    pub fn add_directional_zone(&mut self, x: f32, y: f32, radius: f32, cover_type: CoverType, facing: f32) {
+        self.zones.push(CoverZone { x, y, radius, cover_type, facing: Some(facing) });
     }
 
     /// Get the best cover at a position.
     pub fn get_cover_at(&self, x: f32, y: f32) -> CoverType {
         let mut best = CoverType::None;
-        for (zx, zy, radius, cover_type) in &self.zones {
-            let dx = x - zx;
-            let dy = y - zy;
-            let dist = (dx * dx + dy * dy).sqrt();
-            if dist <= *radius {
-                // Take the better cover
-                if cover_type.damage_reduction() > best.damage_reduction() {
-                    best = *cover_type;
-                }
+        for zone in self.zones.iter().filter(|z| z.contains(x, y)) {
+            // Take the better cover
+            if zone.cover_type.damage_reduction() > best.damage_reduction() {
+                best = zone.cover_type;
             }
         }
         best
     }
+
+    /// Best zone cover value at a position against fire from `incoming`
+    /// (unit direction toward the shooter).
+    pub fn get_cover_from(&self, x: f32, y: f32, incoming: (f32, f32)) -> f32 {
+        self.zones
+            .iter()
+            .filter(|z| z.contains(x, y))
+            .map(|z| {
+                let cover = z.cover_type.cover_value();
+                match z.facing {
+                    Some(angle) => cover * directional_cover_factor((angle.cos(), angle.sin()), incoming),
+                    None => cover,
+                }
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+/// System that rebuilds the cover provider index from destructibles.
+///
+/// ## Data Access
+/// - Reads: Position, CoverProvider, DestructibleState
+/// - Writes: CoverProviderIndex (resource)
+pub fn cover_provider_index_system(
+    mut index: ResMut<CoverProviderIndex>,
+    query: Query<(Entity, &Position, &CoverProvider, &DestructibleState)>,
+) {
+    index.clear();
+    for (entity, pos, provider, state) in query.iter() {
+        index.insert(CoverProviderSample {
+            entity,
+            x: pos.x,
+            y: pos.y,
+            radius: provider.radius,
+            cover: provider.get_cover(*state),
+        });
+    }
 }
 
 /// System that updates squad cover status based on position.
+///
+/// ## Data Access
+/// - Reads: TerrainResource, CoverZones, CoverProviderIndex, Position
+/// - Writes: InCover
 pub fn cover_detection_system(
+    terrain: Option<Res<TerrainResource>>,
     cover_zones: Option<Res<CoverZones>>,
+    providers: Option<Res<CoverProviderIndex>>,
     mut query: Query<(&Position, &mut InCover)>,
 ) {
-    let zones = match cover_zones {
-        Some(z) => z,
-        None => return,
+    let terrain_guard = terrain.as_ref().and_then(|t| t.read());
+    let evaluator = CoverEvaluator {
+        terrain: terrain_guard.as_deref(),
+        zones: cover_zones.as_deref(),
+        providers: providers.as_deref(),
     };
 
     for (pos, mut in_cover) in query.iter_mut() {
-        in_cover.cover_type = zones.get_cover_at(pos.x, pos.y);
+        let eval = evaluator.evaluate(pos.x, pos.y);
+        in_cover.cover_type = eval.cover_type;
+        in_cover.value = eval.value;
     }
 }
 
@@ -114,6 +412,55 @@ mod tests {
         assert_eq!(zones.get_cover_at(100.0, 100.0), CoverType::None);
     }
 
+    #[test]
+    fn test_cover_evaluator_combines_sources() {
+        let mut terrain = TerrainGrid::new(50, 50, 2.0);
+        let (gx, gy) = terrain.world_to_grid(0.0, 0.0);
+        terrain.get_cell_mut(gx, gy).unwrap().terrain_type = crate::terrain::TerrainType::Crater;
+
+        let mut zones = CoverZones::default();
+        zones.add_zone(20.0, 0.0, 5.0, CoverType::Heavy);
+
+        let mut providers = CoverProviderIndex::default();
+        providers.insert(CoverProviderSample {
+            entity: Entity::from_raw(1),
+            x: -20.0,
+            y: 0.0,
+            radius: 5.0,
+            cover: 0.7,
+        });
+
+        let evaluator = CoverEvaluator {
+            terrain: Some(&terrain),
+            zones: Some(&zones),
+            providers: Some(&providers),
+        };
+        assert_eq!(evaluator.evaluate(0.0, 0.0).cover_type, CoverType::Light);
+        assert_eq!(evaluator.evaluate(20.0, 0.0).cover_type, CoverType::Heavy);
+        assert!((evaluator.evaluate(-18.0, 0.0).value - 0.7).abs() < 0.001);
+        assert_eq!(evaluator.evaluate(0.0, 30.0).value, 0.0);
+    }
+
+    #[test]
+    fn test_destroyed_building_strips_cover() {
+        let mut world = World::new();
+        world.insert_resource(CoverProviderIndex::default());
+        let building = world.spawn(BuildingBundle::new(1, 0.0, 0.0)).id();
+        let squad = world.spawn((Position::new(3.0, 0.0), InCover::default())).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((cover_provider_index_system, cover_detection_system).chain());
+        schedule.run(&mut world);
+        let intact = *world.get::<InCover>(squad).unwrap();
+        assert!(intact.value > 0.5);
+
+        *world.get_mut::<DestructibleState>(building).unwrap() = DestructibleState::Destroyed;
+        schedule.run(&mut world);
+        let ruined = *world.get::<InCover>(squad).unwrap();
+        assert!(ruined.value < intact.value);
+        assert!(ruined.cover_type.damage_reduction() < intact.cover_type.damage_reduction());
+    }
+
     #[test]
     fn test_cover_damage_reduction() {
         assert_eq!(CoverType::None.damage_reduction(), 0.0);
@@ -121,4 +468,47 @@ mod tests {
         assert_eq!(CoverType::Medium.damage_reduction(), 0.4);
         assert_eq!(CoverType::Heavy.damage_reduction(), 0.6);
     }
+
+    #[test]
+    fn test_directional_zones_and_providers() {
+        let mut zones = CoverZones::default();
+        // Wall facing east (+x)
+        zones.add_directional_zone(0.0, 0.0, 5.0, CoverType::Heavy, 0.0);
+        let heavy = CoverType::Heavy.cover_value();
+        assert_eq!(zones.get_cover_from(0.0, 0.0, (1.0, 0.0)), heavy);
+        assert!(zones.get_cover_from(0.0, 0.0, (-1.0, 0.0)) < heavy * 0.5);
+
+        let building = CoverProviderSample {
+            entity: Entity::from_raw(1),
+            x: 0.0,
+            y: 0.0,
+            radius: 8.0,
+            cover: 0.7,
+        };
+        // Squad west of the building: shielded from the east, exposed from the west
+        assert_eq!(building.cover_from(-6.0, 0.0, (1.0, 0.0)), 0.7);
+        assert!(building.cover_from(-6.0, 0.0, (-1.0, 0.0)) < 0.7 * 0.5);
+        // Inside the footprint it protects from all sides
+        assert_eq!(building.cover_from(-1.0, 0.0, (-1.0, 0.0)), 0.7);
+    }
+
+    #[test]
+    fn test_shelter_only_credited_when_provider_gives_best_cover() {
+        let mut providers = CoverProviderIndex::default();
+        let tree = Entity::from_raw(1);
+        providers.insert(CoverProviderSample { entity: tree, x: 0.0, y: 0.0, radius: 8.0, cover: 0.3 });
+        let mut zones = CoverZones::default();
+        zones.add_zone(20.0, 0.0, 5.0, CoverType::Heavy);
+        let evaluator = CoverEvaluator { providers: Some(&providers), zones: Some(&zones), ..Default::default() };
+
+        // Behind the tree alone: the tree takes the stopped fire
+        assert_eq!(evaluator.evaluate_from(50.0, 0.0, 0.0, 0.0).shelter, Some(tree));
+
+        // Heavy zone cover around a second tree does the stopping
+        providers.insert(CoverProviderSample { entity: Entity::from_raw(2), x: 18.0, y: 0.0, radius: 8.0, cover: 0.3 });
+        let evaluator = CoverEvaluator { providers: Some(&providers), zones: Some(&zones), ..Default::default() };
+        let cover = evaluator.evaluate_from(50.0, 0.0, 20.0, 0.0);
+        assert_eq!(cover.value, CoverType::Heavy.cover_value());
+        assert_eq!(cover.shelter, None);
+    }
 }
@@ -0,0 +1,398 @@
+//! Standing orders: area defense and patrols.
+//!
+//! A squad with `Order::DefendArea` stays inside its zone. While no enemy
+//! threatens the zone it holds where it is (or walks back in); once one
+//! does, it takes the best position in the zone to fight from - cover and
+//! high ground with the enemy in range - and faces the enemy. Squads
+//! defending together spread over different positions.
+//!
+//! A squad with `Order::Patrol` walks its waypoints in a loop. When it
+//! makes contact it stops to fight, and carries on once the contact is gone.
+//!
+//! Either way the point the squad is making for is its `Station`, which
+//! `order_system` moves it to.
+
+use crate::components::*;
+use crate::los::LineOfSight;
+use crate::spatial::{SpatialEntry, SpatialGrid};
+use crate::systems::ai::{can_fight_from, nearest_visible_enemy, position_value};
+use crate::systems::performance::SimTick;
+use crate::terrain::{TerrainGrid, TerrainResource};
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+
+/// Ticks between searches for a better position (staggered across squads).
+const DEFENSE_SEARCH_INTERVAL: u64 = 15;
+
+/// Fractions of the zone radius at which positions are sampled.
+const DEFENSE_SEARCH_RINGS: [f32; 3] = [1.0 / 3.0, 2.0 / 3.0, 1.0];
+
+/// Number of directions sampled on each ring.
+const DEFENSE_SEARCH_DIRECTIONS: usize = 8;
+
+/// Value of a position with the enemy in fire range.
+const IN_RANGE_VALUE: f32 = 0.5;
+
+/// How much more valuable a position must be before a squad moves to it.
+const STATION_IMPROVEMENT_THRESHOLD: f32 = 0.15;
+
+/// Closest two squads of a side will take up positions.
+const STATION_SPACING: f32 = 8.0;
+
+/// Where a squad on a standing order is making for.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Station {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Index of the waypoint a patrolling squad is walking to.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatrolLeg(pub usize);
+
+/// Point of the zone around `center` nearest `point`.
+fn clamp_to_zone(point: (f32, f32), center: (f32, f32), radius: f32) -> (f32, f32) {
+    let (dx, dy) = (point.0 - center.0, point.1 - center.1);
+    let dist = dx.hypot(dy);
+    if dist <= radius {
+        point
+    } else {
+        (center.0 + dx / dist * radius, center.1 + dy / dist * radius)
+    }
+}
+
+/// Value of fighting `enemy` from `point`.
+fn station_value(terrain: Option<&TerrainGrid>, point: (f32, f32), enemy: (f32, f32), fire_range: f32) -> f32 {
+    let ground = terrain.map_or(0.0, |t| position_value(t, point.0, point.1, enemy));
+    let in_range = (enemy.0 - point.0).hypot(enemy.1 - point.1) <= fire_range;
+    ground + if in_range { IN_RANGE_VALUE } else { 0.0 }
+}
+
+/// Stations taken by each side, bucketed into `STATION_SPACING` cells so
+/// that checking a point only looks at the stations around it.
+#[derive(Default)]
+struct TakenStations {
+    by_squad: HashMap<Entity, (Faction, (f32, f32))>,
+    cells: HashMap<(Faction, i32, i32), Vec<Entity>>,
+}
+
+impl TakenStations {
+    fn cell(point: (f32, f32)) -> (i32, i32) {
+        ((point.0 / STATION_SPACING).floor() as i32, (point.1 / STATION_SPACING).floor() as i32)
+    }
+
+    fn insert(&mut self, entity: Entity, faction: Faction, point: (f32, f32)) {
+        self.remove(entity);
+        let (cx, cy) = Self::cell(point);
+        self.cells.entry((faction, cx, cy)).or_default().push(entity);
+        self.by_squad.insert(entity, (faction, point));
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some((faction, point)) = self.by_squad.remove(&entity) {
+            let (cx, cy) = Self::cell(point);
+            if let Some(squads) = self.cells.get_mut(&(faction, cx, cy)) {
+                squads.retain(|&e| e != entity);
+            }
+        }
+    }
+
+    /// Whether `point` is at least `STATION_SPACING` from the stations of
+    /// `faction`'s other squads.
+    fn is_free(&self, entity: Entity, faction: Faction, point: (f32, f32)) -> bool {
+        let (cx, cy) = Self::cell(point);
+        (cx - 1..=cx + 1)
+            .flat_map(|x| (cy - 1..=cy + 1).map(move |y| (faction, x, y)))
+            .filter_map(|key| self.cells.get(&key))
+            .flatten()
+            .filter(|&&other| other != entity)
+            .filter_map(|other| self.by_squad.get(other))
+            .all(|(_, p)| (p.0 - point.0).hypot(p.1 - point.1) >= STATION_SPACING)
+    }
+}
+
+/// Best position in the zone to fight `enemy` from, if clearly better than
+/// `current`. Positions that aren't `free` are skipped.
+fn best_station(
+    terrain: Option<&TerrainGrid>,
+    center: (f32, f32),
+    radius: f32,
+    current: (f32, f32),
+    enemy: (f32, f32),
+    fire_range: f32,
+    free: impl Fn(&(f32, f32)) -> bool,
+) -> Option<(f32, f32)> {
+    let rings = DEFENSE_SEARCH_RINGS.iter().flat_map(|fraction| {
+        (0..DEFENSE_SEARCH_DIRECTIONS).map(move |i| {
+            let angle = i as f32 / DEFENSE_SEARCH_DIRECTIONS as f32 * std::f32::consts::TAU;
+            (center.0 + radius * fraction * angle.cos(), center.1 + radius * fraction * angle.sin())
+        })
+    });
+    let mut best: Option<(f32, (f32, f32))> = None;
+    for point in std::iter::once(center).chain(rings).filter(&free) {
+        let value = station_value(terrain, point, enemy, fire_range);
+        if best.is_none_or(|(best_value, _)| value > best_value) {
+            best = Some((value, point));
+        }
+    }
+    let current_value = if free(&current) {
+        station_value(terrain, current, enemy, fire_range)
+    } else {
+        f32::NEG_INFINITY
+    };
+    best.filter(|(value, _)| *value > current_value + STATION_IMPROVEMENT_THRESHOLD)
+        .map(|(_, point)| point)
+}
+
+/// Components read and written by `defense_system` for squads.
+type DefendingSquadData<'a> = (
+    Entity,
+    &'a Faction,
+    &'a Position,
+    &'a Health,
+    &'a SquadStats,
+    &'a Order,
+    Option<&'a Station>,
+    Option<&'a PatrolLeg>,
+    Option<&'a mut Facing>,
+);
+
+/// System that carries out area defense and patrol orders.
+///
+/// ## Data Access
+/// - Reads: SimTick, SpatialGrid, TerrainResource, LineOfSight, Faction, Position, Health, SquadStats, Order, Garrisoned
+/// - Writes: Facing, Station, PatrolLeg
+///
+/// A defending squad's threat is the nearest visible enemy within its fire
+/// range of the zone; a patrol's contact is the nearest visible enemy within
+/// its fire range. Enemies are spotted as by `threat_awareness_system`.
+pub fn defense_system(
+    mut commands: Commands,
+    tick: Option<Res<SimTick>>,
+    grid: Res<SpatialGrid>,
+    terrain: Option<Res<TerrainResource>>,
+    los: Option<Res<LineOfSight>>,
+    mut squads: Query<DefendingSquadData, (With<SquadId>, Without<Surrendered>)>,
+    garrisoned: Query<(), With<Garrisoned>>,
+) {
+    let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
+    let terrain_guard = terrain.as_ref().and_then(|t| t.read());
+    let terrain_grid = terrain_guard.as_deref();
+
+    let mut stations = TakenStations::default();
+    for (entity, faction, _, _, _, _, station, ..) in squads.iter() {
+        if let Some(s) = station {
+            stations.insert(entity, *faction, (s.x, s.y));
+        }
+    }
+
+    for (entity, faction, pos, health, stats, order, station, leg, facing) in squads.iter_mut() {
+        if !health.is_alive() || !order.is_standing() {
+            if station.is_some() || leg.is_some() {
+                commands.entity(entity).remove::<(Station, PatrolLeg)>();
+                stations.remove(entity);
+            }
+            continue;
+        }
+
+        let here = (pos.x, pos.y);
+        let faction_id = match faction { Faction::Blue => 0, Faction::Red => 1 };
+        // Nearest visible enemy within `range` of `from`
+        let nearest_threat = |from: (f32, f32), range: f32| {
+            let mut enemies = grid.query_enemies(from.0, from.1, range, faction_id);
+            if from != here {
+                let dist = |e: &SpatialEntry| (e.x - here.0).hypot(e.y - here.1);
+                enemies.sort_by(|a, b| dist(a).total_cmp(&dist(b)));
+            }
+            nearest_visible_enemy(&enemies, here, |e| garrisoned.contains(e), los.as_deref(), terrain_grid)
+                .map(|e| (e.x, e.y))
+        };
+
+        let (target, enemy) = match *order {
+            Order::DefendArea { x, y, radius } => {
+                let center = (x, y);
+                let current = station.map(|s| (s.x, s.y))
+                    .filter(|s| (s.0 - x).hypot(s.1 - y) <= radius)
+                    .unwrap_or_else(|| clamp_to_zone(here, center, radius));
+                match nearest_threat(center, radius + stats.fire_range) {
+                    Some(enemy) => {
+                        // Look at once if the enemy is out of reach
+                        let in_reach = (enemy.0 - current.0).hypot(enemy.1 - current.1) <= stats.fire_range;
+                        let search_due = !in_reach
+                            || (current_tick + entity.index() as u64).is_multiple_of(DEFENSE_SEARCH_INTERVAL);
+                        // Untaken ground the squad can stand on and see the enemy from
+                        let free = |p: &(f32, f32)| {
+                            stations.is_free(entity, *faction, *p)
+                                && can_fight_from(terrain_grid, los.as_deref(), *p, enemy)
+                        };
+                        let better = search_due
+                            .then(|| best_station(terrain_grid, center, radius, current, enemy, stats.fire_range, free))
+                            .flatten();
+                        (better.unwrap_or(current), Some(enemy))
+                    }
+                    None => (current, None),
+                }
+            }
+            Order::Patrol { waypoints } => {
+                if waypoints.is_empty() {
+                    continue;
+                }
+                match nearest_threat(here, stats.fire_range) {
+                    Some(enemy) => (here, Some(enemy)),
+                    None => {
+                        let mut index = leg.map_or(0, |l| l.0) % waypoints.len();
+                        let (wx, wy) = waypoints.as_slice()[index];
+                        if (wx - here.0).hypot(wy - here.1) < ARRIVAL_RADIUS {
+                            index = (index + 1) % waypoints.len();
+                        }
+                        if leg.map(|l| l.0) != Some(index) {
+                            commands.entity(entity).insert(PatrolLeg(index));
+                        }
+                        (waypoints.as_slice()[index], None)
+                    }
+                }
+            }
+            _ => continue,
+        };
+
+        if station.map(|s| (s.x, s.y)) != Some(target) {
+            commands.entity(entity).insert(Station { x: target.0, y: target.1 });
+            stations.insert(entity, *faction, target);
+        }
+        // Face the enemy once in position; on the move, `order_system` faces
+        // the way the squad is going
+        let arrived = (target.0 - here.0).hypot(target.1 - here.1) < ARRIVAL_RADIUS;
+        if let (Some(enemy), Some(mut facing), true) = (enemy, facing, arrived) {
+            facing.0 = (enemy.1 - here.1).atan2(enemy.0 - here.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial::spatial_grid_update_system;
+
+    fn spawn_squad(world: &mut World, id: u32, faction: Faction, x: f32, y: f32, order: Order) -> Entity {
+        world.spawn((
+            SquadId(id),
+            faction,
+            Position::new(x, y),
+            Health::new(100.0),
+            SquadStats::default(),
+            order,
+        )).id()
+    }
+
+    fn station(world: &World, entity: Entity) -> (f32, f32) {
+        let station = world.get::<Station>(entity).expect("no station");
+        (station.x, station.y)
+    }
+
+    #[test]
+    fn test_defenders_stay_in_zone_and_spread_out_against_a_threat() {
+        let mut world = World::new();
+        let order = Order::DefendArea { x: 0.0, y: 0.0, radius: 20.0 };
+        let outside = spawn_squad(&mut world, 1, Faction::Blue, 50.0, 0.0, order);
+        let inside = spawn_squad(&mut world, 2, Faction::Blue, 5.0, 0.0, order);
+
+        world.insert_resource(SpatialGrid::default());
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, defense_system).chain());
+        schedule.run(&mut world);
+        assert_eq!(station(&world, outside), (20.0, 0.0), "walks back into the zone");
+        assert_eq!(station(&world, inside), (5.0, 0.0), "holds where it is");
+
+        // An enemy out of reach of the squads but threatening the zone
+        let range = SquadStats::default().fire_range;
+        spawn_squad(&mut world, 3, Faction::Red, -(20.0 + range * 0.7), 0.0, Order::Hold);
+        schedule.run(&mut world);
+        let (a, b) = (station(&world, outside), station(&world, inside));
+        for (x, y) in [a, b] {
+            assert!(x.hypot(y) <= 20.0 + 1e-3, "({}, {}) left the zone", x, y);
+            assert!(x < 0.0, "({}, {}) should close on the enemy", x, y);
+        }
+        assert!((a.0 - b.0).hypot(a.1 - b.1) >= STATION_SPACING, "{:?} and {:?} bunched up", a, b);
+    }
+
+    #[test]
+    fn test_defenders_only_take_stations_they_can_stand_on() {
+        use crate::terrain::TerrainType;
+
+        // A pond on the zone's side facing the enemy
+        let mut grid = TerrainGrid::new(100, 100, 2.0);
+        for gy in 0..grid.height {
+            for gx in 0..grid.width {
+                let (x, y) = grid.grid_to_world(gx, gy);
+                if x < -2.0 && y.abs() < 8.0 {
+                    grid.get_cell_mut(gx, gy).unwrap().terrain_type = TerrainType::Water;
+                }
+            }
+        }
+        let mut world = World::new();
+        world.insert_resource(TerrainResource::new(grid));
+        world.insert_resource(SpatialGrid::default());
+        let order = Order::DefendArea { x: 0.0, y: 0.0, radius: 20.0 };
+        let squad = spawn_squad(&mut world, 1, Faction::Blue, 0.0, 0.0, order);
+        let range = SquadStats::default().fire_range;
+        spawn_squad(&mut world, 2, Faction::Red, -(20.0 + range * 0.7), 0.0, Order::Hold);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, defense_system).chain());
+        schedule.run(&mut world);
+        let (x, y) = station(&world, squad);
+        assert!(x < 0.0, "({}, {}) should close on the enemy", x, y);
+        let terrain = world.resource::<TerrainResource>();
+        assert!(
+            terrain.read().unwrap().get_terrain_at(x, y).terrain_type.is_passable(),
+            "({}, {}) is under water", x, y
+        );
+    }
+
+    #[test]
+    fn test_patrol_loops_waypoints_and_stops_for_contacts() {
+        let mut world = World::new();
+        let waypoints = Waypoints::new(&[(0.0, 0.0), (30.0, 0.0)]);
+        let squad = spawn_squad(&mut world, 1, Faction::Blue, 0.0, 0.0, Order::Patrol { waypoints });
+
+        world.insert_resource(SpatialGrid::default());
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, defense_system).chain());
+        schedule.run(&mut world);
+        assert_eq!(station(&world, squad), (30.0, 0.0));
+
+        world.get_mut::<Position>(squad).unwrap().x = 30.0;
+        schedule.run(&mut world);
+        assert_eq!(station(&world, squad), (0.0, 0.0), "loops back to the start");
+
+        world.get_mut::<Position>(squad).unwrap().x = 15.0;
+        let enemy = spawn_squad(&mut world, 2, Faction::Red, 15.0, 20.0, Order::Hold);
+        schedule.run(&mut world);
+        assert_eq!(station(&world, squad), (15.0, 0.0), "stops for the contact");
+
+        world.despawn(enemy);
+        schedule.run(&mut world);
+        assert_eq!(station(&world, squad), (0.0, 0.0), "carries on");
+        assert_eq!(world.get::<PatrolLeg>(squad), Some(&PatrolLeg(0)));
+    }
+
+    #[test]
+    fn test_patrol_only_spots_garrisoned_enemies_up_close() {
+        let mut world = World::new();
+        let waypoints = Waypoints::new(&[(0.0, 0.0), (30.0, 0.0)]);
+        let squad = spawn_squad(&mut world, 1, Faction::Blue, 0.0, 0.0, Order::Patrol { waypoints });
+        let building = world.spawn_empty().id();
+        let enemy = spawn_squad(&mut world, 2, Faction::Red, 0.0, 45.0, Order::Hold);
+        world.entity_mut(enemy).insert(Garrisoned { building });
+
+        world.insert_resource(SpatialGrid::default());
+        let mut schedule = Schedule::default();
+        schedule.add_systems((spatial_grid_update_system, defense_system).chain());
+        schedule.run(&mut world);
+        assert_eq!(station(&world, squad), (30.0, 0.0), "in range but unseen in its building");
+
+        world.get_mut::<Position>(enemy).unwrap().y = 20.0;
+        schedule.run(&mut world);
+        assert_eq!(station(&world, squad), (0.0, 0.0), "stops for the contact");
+    }
+}
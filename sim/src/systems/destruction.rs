@@ -3,11 +3,29 @@
 use crate::components::*;
 use bevy_ecs::prelude::*;
 
+/// Blast radius of a collapsing building (world units).
+const COLLAPSE_RADIUS: f32 = 5.0;
+
+/// Blast depth of a collapsing building (scales damage to nearby squads and objects).
+const COLLAPSE_DEPTH: f32 = 1.0;
+
+/// Components read and written by `destruction_state_system`.
+type DestructibleStateData<'a> = (
+    &'a DestructibleHealth,
+    &'a mut DestructibleState,
+    Option<&'a Position>,
+    Has<Building>,
+);
+
 /// System that updates destructible states based on health.
+///
+/// Buildings that become destroyed collapse, spawning a `BlastKind::Collapse`
+/// explosion at their position.
 pub fn destruction_state_system(
-    mut query: Query<(&DestructibleHealth, &mut DestructibleState), Changed<DestructibleHealth>>,
+    mut commands: Commands,
+    mut query: Query<DestructibleStateData, Changed<DestructibleHealth>>,
 ) {
-    for (health, mut state) in query.iter_mut() {
+    for (health, mut state, pos, is_building) in query.iter_mut() {
         let new_state = if health.is_destroyed() {
             DestructibleState::Destroyed
         } else if health.is_damaged() {
@@ -17,14 +35,23 @@ pub fn destruction_state_system(
         };
 
         if *state != new_state {
+            // A collapsing building is an explosion in its own right
+            if new_state == DestructibleState::Destroyed && is_building {
+                if let Some(pos) = pos {
+                    commands.spawn((
+                        TerrainDamageEvent { x: pos.x, y: pos.y, radius: COLLAPSE_RADIUS, depth: COLLAPSE_DEPTH },
+                        BlastSource { faction: None, kind: BlastKind::Collapse },
+                    ));
+                }
+            }
             *state = new_state;
         }
     }
 }
 
-/// System that applies terrain damage events to nearby destructibles.
+/// System that applies new terrain damage events to nearby destructibles.
 pub fn terrain_damage_to_destructibles_system(
-    damage_events: Query<&TerrainDamageEvent>,
+    damage_events: Query<&TerrainDamageEvent, Added<TerrainDamageEvent>>,
     mut destructibles: Query<(&Position, &mut DestructibleHealth)>,
 ) {
     for event in damage_events.iter() {
@@ -149,4 +176,22 @@ mod tests {
         let health = query.single(&world);
         assert!(health.current < 30.0, "Tree should have taken damage from nearby crater");
     }
+
+    #[test]
+    fn test_building_collapse_spawns_blast() {
+        let mut world = World::new();
+        let building = world.spawn(BuildingBundle::new(1, 10.0, 0.0)).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(destruction_state_system);
+
+        world.get_mut::<DestructibleHealth>(building).unwrap().damage(10_000.0);
+        schedule.run(&mut world);
+
+        let mut query = world.query::<(&TerrainDamageEvent, &BlastSource)>();
+        let (event, source) = query.single(&world);
+        assert_eq!((event.x, event.y), (10.0, 0.0));
+        assert_eq!(source.kind, BlastKind::Collapse);
+        assert_eq!(source.faction, None);
+    }
 }
@@ -0,0 +1,92 @@
+//! Attacking a designated target.
+//!
+//! A squad on `FireStance::EngageTarget` only fires at its target (see
+//! `combat`). While its order is an attack-move, it closes on the target
+//! until the target is visible and well within range, then stands and
+//! fires; if the target moves off, it follows. Once the target is dead,
+//! surrendered or gone, the squad returns to firing at will.
+
+use crate::components::*;
+use crate::los::LineOfSight;
+use crate::terrain::TerrainResource;
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+
+/// Fraction of fire range an attacking squad closes to before standing.
+pub const ENGAGE_RANGE_FRACTION: f32 = 0.8;
+
+/// Components read and written by `engage_target_system` for attackers.
+type EngagingSquadData<'a> = (&'a Position, &'a SquadStats, &'a mut FireStance, &'a mut Order);
+
+/// System that steers squads onto their designated targets.
+///
+/// ## Data Access
+/// - Reads: TerrainResource, LineOfSight, SquadId, Position, Health, SquadStats, Surrendered
+/// - Writes: FireStance, Order
+pub fn engage_target_system(
+    terrain: Option<Res<TerrainResource>>,
+    los: Option<Res<LineOfSight>>,
+    targets: Query<(&SquadId, &Position, &Health), Without<Surrendered>>,
+    mut squads: Query<EngagingSquadData, Without<Surrendered>>,
+) {
+    let terrain_guard = terrain.as_ref().and_then(|t| t.read());
+    let terrain_grid = terrain_guard.as_deref();
+    let positions: HashMap<u32, (f32, f32)> = targets.iter()
+        .filter(|(_, _, health)| health.is_alive())
+        .map(|(id, pos, _)| (id.0, (pos.x, pos.y)))
+        .collect();
+
+    for (pos, stats, mut stance, mut order) in squads.iter_mut() {
+        let FireStance::EngageTarget(target) = *stance else { continue };
+        let Some(&(tx, ty)) = positions.get(&target) else {
+            *stance = FireStance::FireAtWill;
+            continue;
+        };
+        if !matches!(*order, Order::AttackMove { .. }) {
+            continue;
+        }
+
+        let in_range = (tx - pos.x).hypot(ty - pos.y) <= stats.fire_range * ENGAGE_RANGE_FRACTION;
+        let visible = los.as_ref()
+            .map(|l| l.has_los(terrain_grid, pos.x, pos.y, tx, ty))
+            .unwrap_or(true);
+        *order = if in_range && visible {
+            Order::AttackMove { x: pos.x, y: pos.y }
+        } else {
+            Order::AttackMove { x: tx, y: ty }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attacker_closes_then_stands_and_drops_dead_target() {
+        let mut world = World::new();
+        let stats = SquadStats::default();
+        let target = world.spawn((SquadId(2), Position::new(stats.fire_range * 2.0, 0.0), Health::new(100.0))).id();
+        let attacker = world.spawn((
+            SquadId(1),
+            Position::new(0.0, 0.0),
+            Health::new(100.0),
+            stats,
+            FireStance::EngageTarget(2),
+            Order::AttackMove { x: 0.0, y: 0.0 },
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(engage_target_system);
+        schedule.run(&mut world);
+        assert!(matches!(world.get::<Order>(attacker).unwrap(), Order::AttackMove { x, .. } if *x == stats.fire_range * 2.0));
+
+        world.get_mut::<Position>(target).unwrap().x = stats.fire_range * 0.5;
+        schedule.run(&mut world);
+        assert!(matches!(world.get::<Order>(attacker).unwrap(), Order::AttackMove { x, .. } if *x == 0.0));
+
+        world.get_mut::<Health>(target).unwrap().damage(100.0);
+        schedule.run(&mut world);
+        assert_eq!(*world.get::<FireStance>(attacker).unwrap(), FireStance::FireAtWill);
+    }
+}
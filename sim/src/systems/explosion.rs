@@ -0,0 +1,391 @@
+//! Explosion system - blast damage to squads, indirect fire dispersion and
+//! friendly-fire tracking.
+//!
+//! Every `TerrainDamageEvent` is an explosion. Blasts do not discriminate:
+//! squads of every faction inside the blast radius take falloff damage, and
+//! everyone within the wider suppression radius is suppressed and shaken.
+//! Cover at the squad's position reduces all three. When the event carries a
+//! `BlastSource` whose faction matches the victim, the hit is recorded as
+//! friendly fire in the `AfterActionReport`.
+//!
+//! Indirect fire does not land where it is aimed. `DispersionConfig` scatters
+//! shells around the aim point with a radius that grows with the distance
+//! from the firing faction's battery, so calling a barrage close to your own
+//! lines is a real risk.
+
+use crate::components::*;
+use crate::systems::cover::{CoverEvaluation, InCover};
+use crate::systems::performance::SimTick;
+use bevy_ecs::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Blast radius relative to the crater radius (matches destructible damage).
+pub const BLAST_RADIUS_FACTOR: f32 = 1.5;
+
+/// Squad damage at the blast center per unit of crater depth.
+pub const BLAST_DAMAGE_PER_DEPTH: f32 = 25.0;
+
+/// Suppression radius relative to the blast radius (near misses still pin).
+pub const SUPPRESSION_RADIUS_FACTOR: f32 = 2.0;
+
+/// Suppression at the blast center per unit of crater depth.
+const BLAST_SUPPRESSION_PER_DEPTH: f32 = 0.8;
+
+/// Morale shock at the blast center per unit of crater depth.
+const BLAST_MORALE_SHOCK_PER_DEPTH: f32 = 0.15;
+
+/// Dispersion model for indirect fire.
+///
+/// A shell aimed at a point lands uniformly within
+/// `base_radius + range_factor * range` of it, where `range` is the distance
+/// from the firing faction's battery.
+#[derive(Debug, Clone, Copy)]
+pub struct DispersionConfig {
+    /// Dispersion radius at zero range (world units).
+    pub base_radius: f32,
+    /// Additional dispersion radius per world unit of range.
+    pub range_factor: f32,
+    /// Battery position for Blue indirect fire.
+    pub blue_battery: (f32, f32),
+    /// Battery position for Red indirect fire.
+    pub red_battery: (f32, f32),
+}
+
+impl Default for DispersionConfig {
+    fn default() -> Self {
+        Self {
+            base_radius: 6.0,
+            range_factor: 0.03,
+            blue_battery: (-400.0, 0.0),
+            red_battery: (400.0, 0.0),
+        }
+    }
+}
+
+impl DispersionConfig {
+    /// Battery position for a faction.
+    pub fn battery(&self, faction: Faction) -> (f32, f32) {
+        match faction {
+            Faction::Blue => self.blue_battery,
+            Faction::Red => self.red_battery,
+        }
+    }
+
+    /// Dispersion radius for a faction firing at a target point.
+    pub fn dispersion_radius(&self, faction: Faction, target_x: f32, target_y: f32) -> f32 {
+        let (bx, by) = self.battery(faction);
+        let range = ((target_x - bx).powi(2) + (target_y - by).powi(2)).sqrt();
+        self.base_radius + self.range_factor * range
+    }
+
+    /// Impact points for `count` shells aimed at a target.
+    ///
+    /// Deterministic for a given `seed`, so replays land the same shells.
+    pub fn shell_impacts(
+        &self,
+        faction: Faction,
+        target_x: f32,
+        target_y: f32,
+        count: usize,
+        seed: u32,
+    ) -> Vec<(f32, f32)> {
+        let radius = self.dispersion_radius(faction, target_x, target_y);
+        (0..count as u64)
+            .map(|i| {
+                let angle = hash01(seed, 2 * i) * std::f32::consts::TAU;
+                // sqrt gives a uniform spread over the disc
+                let dist = radius * hash01(seed, 2 * i + 1).sqrt();
+                (target_x + dist * angle.cos(), target_y + dist * angle.sin())
+            })
+            .collect()
+    }
+}
+
+/// Seed for the scatter of one volley, from the tick it lands on and a
+/// salt that tells apart volleys landing on the same tick.
+pub fn shell_seed(tick: u64, salt: u64) -> u32 {
+    let mut h = tick.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ salt.wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h ^= h >> 29;
+    h = h.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h ^= h >> 32;
+    h as u32
+}
+
+/// Deterministic pseudo-random value in [0, 1) for draw `i` from `seed`,
+/// mixed in integers (splitmix64) so it is the same on every platform.
+fn hash01(seed: u32, i: u64) -> f32 {
+    let mut h = ((seed as u64) << 32 ^ i).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^= h >> 31;
+    // The top 24 bits convert to f32 exactly, keeping the value below 1.0
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// An artillery barrage fired over time (see `SimWorld::fire_barrage`).
+///
+/// Shells land evenly spread from `start_tick` until `end_tick`. Once all
+/// have landed the barrage has lifted: it is despawned and its ID recorded
+/// in `LiftedBarrages`, which scheduled orders wait on.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Barrage {
+    pub id: u32,
+    pub faction: Faction,
+    pub x: f32,
+    pub y: f32,
+    pub shells: u32,
+    /// Shells landed so far.
+    pub fired: u32,
+    pub start_tick: u64,
+    pub end_tick: u64,
+}
+
+impl Barrage {
+    /// Shells that should have landed by the end of `tick`.
+    pub fn shells_due(&self, tick: u64) -> u32 {
+        if tick < self.start_tick {
+            return 0;
+        }
+        let span = self.end_tick.saturating_sub(self.start_tick).max(1);
+        let elapsed = (tick - self.start_tick + 1).min(span);
+        (self.shells as u64 * elapsed / span) as u32
+    }
+
+    /// Whether every shell has landed.
+    pub fn is_lifted(&self) -> bool {
+        self.fired >= self.shells
+    }
+}
+
+/// IDs of barrages that have lifted.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct LiftedBarrages(pub HashSet<u32>);
+
+/// A squad hit by its own side's explosives.
+#[derive(Debug, Clone, Serialize)]
+pub struct FriendlyFireIncident {
+    pub tick: u64,
+    pub faction: Faction,
+    pub squad_id: u32,
+    pub kind: BlastKind,
+    pub damage: f32,
+    /// Estimated soldiers lost (damage / health per soldier).
+    pub casualties: f32,
+    /// Whether the hit wiped out the squad.
+    pub killed: bool,
+}
+
+/// Battle statistics collected for the after-action report.
+#[derive(Resource, Debug, Clone, Default, Serialize)]
+pub struct AfterActionReport {
+    /// Blast damage taken per faction, from any source.
+    pub blast_damage: HashMap<Faction, f32>,
+    /// Every friendly-fire hit, in order.
+    pub friendly_fire: Vec<FriendlyFireIncident>,
+}
+
+impl AfterActionReport {
+    /// Total damage a faction inflicted on itself.
+    pub fn friendly_fire_damage(&self, faction: Faction) -> f32 {
+        self.friendly_fire.iter().filter(|i| i.faction == faction).map(|i| i.damage).sum()
+    }
+
+    /// Estimated soldiers a faction lost to its own fire.
+    pub fn friendly_fire_casualties(&self, faction: Faction) -> f32 {
+        self.friendly_fire.iter().filter(|i| i.faction == faction).map(|i| i.casualties).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.blast_damage.clear();
+        self.friendly_fire.clear();
+    }
+}
+
+/// Components read and written by `blast_damage_system`.
+type BlastTargetData<'a> = (
+    &'a SquadId,
+    &'a Faction,
+    &'a Position,
+    &'a SquadStats,
+    &'a mut Health,
+    Option<&'a mut Suppression>,
+    Option<&'a mut Morale>,
+    Option<&'a InCover>,
+);
+
+/// System that applies new explosions to squads of every faction: falloff
+/// damage inside the blast radius, suppression and a morale shock out to the
+/// suppression radius, all reduced by the squad's cover.
+///
+/// ## Data Access
+/// - Reads: SimTick, TerrainDamageEvent (newly added), BlastSource, SquadId, Faction, Position, SquadStats, InCover
+/// - Writes: Health, Suppression, Morale, AfterActionReport
+pub fn blast_damage_system(
+    tick: Option<Res<SimTick>>,
+    mut report: Option<ResMut<AfterActionReport>>,
+    events: Query<(&TerrainDamageEvent, Option<&BlastSource>), Added<TerrainDamageEvent>>,
+    mut squads: Query<BlastTargetData>,
+) {
+    let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
+
+    for (event, source) in events.iter() {
+        let event_pos = Position::new(event.x, event.y);
+        let blast_radius = event.radius * BLAST_RADIUS_FACTOR;
+        let suppression_radius = blast_radius * SUPPRESSION_RADIUS_FACTOR;
+        let base_damage = event.depth * BLAST_DAMAGE_PER_DEPTH;
+
+        for (squad_id, faction, pos, stats, mut health, suppression, morale, in_cover) in squads.iter_mut() {
+            if !health.is_alive() {
+                continue;
+            }
+            let dist = pos.distance_to(&event_pos);
+            if dist > suppression_radius {
+                continue;
+            }
+            let cover = CoverEvaluation::from_value(in_cover.map(|c| c.value).unwrap_or(0.0));
+
+            // Near misses suppress and shake even when they do not wound
+            let shock = (1.0 - dist / suppression_radius) * event.depth * cover.suppression_multiplier();
+            if let Some(mut suppression) = suppression {
+                suppression.add(BLAST_SUPPRESSION_PER_DEPTH * shock);
+            }
+            if let Some(mut morale) = morale {
+                morale.decrease(BLAST_MORALE_SHOCK_PER_DEPTH * shock);
+            }
+
+            if dist > blast_radius {
+                continue;
+            }
+            let falloff = 1.0 - (dist / blast_radius);
+            let before = health.current;
+            health.damage(base_damage * falloff * falloff * cover.damage_multiplier());
+            let dealt = before - health.current;
+
+            let Some(report) = report.as_mut() else { continue };
+            *report.blast_damage.entry(*faction).or_insert(0.0) += dealt;
+
+            if source.and_then(|s| s.faction) == Some(*faction) {
+                let health_per_soldier = health.max / stats.size.max(1) as f32;
+                report.friendly_fire.push(FriendlyFireIncident {
+                    tick: current_tick,
+                    faction: *faction,
+                    squad_id: squad_id.0,
+                    kind: source.map(|s| s.kind).unwrap_or_default(),
+                    damage: dealt,
+                    casualties: dealt / health_per_soldier,
+                    killed: !health.is_alive(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_squad(world: &mut World, id: u32, faction: Faction, x: f32) -> Entity {
+        world.spawn((
+            SquadId(id),
+            faction,
+            Position::new(x, 0.0),
+            SquadStats::default(),
+            Health::new(100.0),
+        )).id()
+    }
+
+    #[test]
+    fn test_blast_hits_all_factions_and_records_friendly_fire() {
+        let mut world = World::new();
+        world.insert_resource(AfterActionReport::default());
+        let blue = spawn_squad(&mut world, 1, Faction::Blue, 0.0);
+        let red = spawn_squad(&mut world, 2, Faction::Red, 3.0);
+        let far = spawn_squad(&mut world, 3, Faction::Blue, 50.0);
+
+        world.spawn((
+            TerrainDamageEvent { x: 1.0, y: 0.0, radius: 4.0, depth: 1.5 },
+            BlastSource { faction: Some(Faction::Blue), kind: BlastKind::Artillery },
+        ));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(blast_damage_system);
+        schedule.run(&mut world);
+
+        let blue_health = world.get::<Health>(blue).unwrap().current;
+        assert!(blue_health < 100.0);
+        assert!(world.get::<Health>(red).unwrap().current < 100.0);
+        assert_eq!(world.get::<Health>(far).unwrap().current, 100.0);
+
+        let report = world.resource::<AfterActionReport>();
+        assert_eq!(report.friendly_fire.len(), 1);
+        assert_eq!(report.friendly_fire[0].squad_id, 1);
+        assert!((report.friendly_fire_damage(Faction::Blue) - (100.0 - blue_health)).abs() < 0.001);
+        assert!(report.friendly_fire_casualties(Faction::Blue) > 0.0);
+        assert_eq!(report.friendly_fire_damage(Faction::Red), 0.0);
+
+        // Blasts are applied once, not every tick
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Health>(blue).unwrap().current, blue_health);
+    }
+
+    #[test]
+    fn test_blast_suppresses_and_shakes_reduced_by_cover() {
+        let mut world = World::new();
+        let spawn = |world: &mut World, x: f32, cover: f32| {
+            world.spawn((
+                SquadId(1),
+                Faction::Blue,
+                Position::new(x, 0.0),
+                SquadStats::default(),
+                Health::new(100.0),
+                Suppression::default(),
+                Morale::default(),
+                InCover { cover_type: crate::systems::cover::CoverType::from_value(cover), value: cover },
+            )).id()
+        };
+        let exposed = spawn(&mut world, 2.0, 0.0);
+        let dug_in = spawn(&mut world, -2.0, 0.9);
+        // Outside the blast radius (6) but inside the suppression radius (12)
+        let near_miss = spawn(&mut world, 9.0, 0.0);
+
+        world.spawn(TerrainDamageEvent { x: 0.0, y: 0.0, radius: 4.0, depth: 1.5 });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(blast_damage_system);
+        schedule.run(&mut world);
+
+        let health = |world: &World, e: Entity| world.get::<Health>(e).unwrap().current;
+        let suppression = |world: &World, e: Entity| world.get::<Suppression>(e).unwrap().value;
+        let morale = |world: &World, e: Entity| world.get::<Morale>(e).unwrap().value;
+
+        assert!(health(&world, dug_in) > health(&world, exposed));
+        assert!(suppression(&world, dug_in) < suppression(&world, exposed));
+        assert!(morale(&world, dug_in) > morale(&world, exposed));
+
+        assert_eq!(health(&world, near_miss), 100.0);
+        assert!(suppression(&world, near_miss) > 0.0);
+        assert!(morale(&world, near_miss) < 1.0);
+    }
+
+    #[test]
+    fn test_dispersion_grows_with_range() {
+        let config = DispersionConfig::default();
+        let near = config.dispersion_radius(Faction::Blue, -300.0, 0.0);
+        let far = config.dispersion_radius(Faction::Blue, 300.0, 0.0);
+        assert!(far > near);
+
+        let impacts = config.shell_impacts(Faction::Blue, 0.0, 0.0, 16, 7);
+        let radius = config.dispersion_radius(Faction::Blue, 0.0, 0.0);
+        assert_eq!(impacts.len(), 16);
+        assert!(impacts.iter().all(|(x, y)| (x * x + y * y).sqrt() <= radius + 0.001));
+        assert!(impacts.iter().any(|(x, y)| (x * x + y * y).sqrt() > radius * 0.3));
+        assert_eq!(impacts, config.shell_impacts(Faction::Blue, 0.0, 0.0, 16, 7));
+        // Seeds too large for an f32 to tell apart still scatter differently
+        let high = 1 << 30;
+        assert_ne!(
+            config.shell_impacts(Faction::Blue, 0.0, 0.0, 16, high),
+            config.shell_impacts(Faction::Blue, 0.0, 0.0, 16, high + 1)
+        );
+    }
+}
@@ -0,0 +1,481 @@
+//! Flow fields for mass movement.
+//!
+//! When hundreds of squads share a destination, finding each a route with
+//! A* repeats the same search hundreds of times. Instead the destination
+//! cell gets a flow field: an integration field holding the cost of
+//! reaching the target from every cell (a search outward from the target,
+//! with the same costs as `pathfinding`), and a direction field pointing
+//! each cell at the neighbour that leads on toward it. Any number of squads
+//! then steer by looking up the cell they stand in.
+//!
+//! Fields are built for destination cells that at least
+//! `FlowFields::min_squads` squads are making for, and cached per cell
+//! until no squad is. Building is time-sliced like route searches, so a
+//! field over the whole map costs a bounded number of cells per tick;
+//! squads head straight for the target until it is ready.
+//!
+//! A terrain change (new craters, trenches) marks stale the fields whose
+//! explored cells it touches; a building going up or coming down marks
+//! them all. A stale field is rebuilt at most once every
+//! `FlowFields::rebuild_interval` ticks, so a barrage that keeps landing
+//! merges into one rebuild per interval. A field being built when cells it
+//! has explored change is finished anyway and rebuilt in turn. Squads keep
+//! steering by a stale field until its replacement is ready.
+
+use crate::components::*;
+use crate::systems::defense::Station;
+use crate::systems::pathfinding::{blocked_cells, cell_at, footprint_cells, goal_exemptions, steering_goal, ObstacleData, OpenCell, Passability};
+use crate::systems::performance::SimTick;
+use crate::systems::retreat::Fallback;
+use crate::terrain::{TerrainGrid, TerrainResource};
+use bevy_ecs::prelude::*;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// Cells expanded per tick building flow fields: one field over a 200x200
+/// map. Counted in cells rather than time so runs stay deterministic.
+pub const DEFAULT_FLOW_EXPANSIONS_PER_TICK: usize = 40_000;
+
+/// Fewest ticks between rebuilds of one flow field: one second at 30 Hz.
+pub const DEFAULT_FLOW_REBUILD_INTERVAL: u64 = 30;
+
+/// Squads that must share a destination cell before it gets a flow field.
+pub const DEFAULT_FLOW_FIELD_MIN_SQUADS: usize = 8;
+
+/// Marks a cell with no way on toward the target.
+const NO_NEXT: u32 = u32::MAX;
+
+/// Integration and direction fields toward one destination cell.
+#[derive(Debug, Clone)]
+pub struct FlowField {
+    /// Destination cell.
+    pub target: usize,
+    /// Point the squads are making for, inside the target cell.
+    pub goal: (f32, f32),
+    /// Cost of reaching the target from each cell; infinite where it can't
+    /// be reached.
+    pub integration: Vec<f32>,
+    /// Neighbouring cell each cell leads on to toward the target.
+    next: Vec<u32>,
+}
+
+impl FlowField {
+    /// Cost of reaching the target from a world position, if it can be
+    /// reached.
+    pub fn cost_at(&self, grid: &TerrainGrid, x: f32, y: f32) -> Option<f32> {
+        self.integration.get(cell_at(grid, x, y)).copied().filter(|cost| cost.is_finite())
+    }
+
+    /// Point to head for from a world position: the center of the next
+    /// cell toward the target. `None` in the target cell and where the
+    /// target can't be reached.
+    pub fn next_point(&self, grid: &TerrainGrid, x: f32, y: f32) -> Option<(f32, f32)> {
+        let next = *self.next.get(cell_at(grid, x, y))?;
+        (next != NO_NEXT).then(|| grid.grid_to_world(next as usize % grid.width, next as usize / grid.width))
+    }
+
+    /// Whether the search outward from the target has reached a cell.
+    fn explored(&self, cell: usize) -> bool {
+        self.integration.get(cell).is_some_and(|cost| cost.is_finite())
+    }
+}
+
+/// A flow field being built, resumable across ticks.
+#[derive(Debug)]
+struct FieldBuild {
+    field: FlowField,
+    open: BinaryHeap<OpenCell>,
+    /// Blocked cells the field may lead through (the goal's building).
+    exempt: HashSet<usize>,
+    /// Cells already explored changed during the build.
+    dirty: bool,
+}
+
+impl FieldBuild {
+    fn new(grid: &TerrainGrid, target: usize, goal: (f32, f32), exempt: HashSet<usize>) -> Self {
+        let cells = grid.width * grid.height;
+        let mut field = FlowField {
+            target,
+            goal,
+            integration: vec![f32::INFINITY; cells],
+            next: vec![NO_NEXT; cells],
+        };
+        field.integration[target] = 0.0;
+        let mut open = BinaryHeap::new();
+        open.push(OpenCell { cell: target, estimate: 0.0, cost: 0.0 });
+        Self { field, open, exempt, dirty: false }
+    }
+
+    /// Expand cells outward from the target until every cell that can
+    /// reach it has its cost, or `budget` is spent. Each expanded cell
+    /// takes one from `budget`. Returns whether the field is complete.
+    fn run(&mut self, grid: &TerrainGrid, blocked: &HashSet<usize>, budget: &mut usize) -> bool {
+        let passability = Passability { grid, blocked, exempt: &self.exempt };
+        while *budget > 0 {
+            let Some(OpenCell { cell, cost, .. }) = self.open.pop() else {
+                return true;
+            };
+            if cost > self.field.integration[cell] {
+                continue; // Superseded by a cheaper way to the target
+            }
+            *budget -= 1;
+            // Squads leave each neighbour by stepping into this cell
+            let Some(entry_cost) = passability.cost(cell) else { continue };
+            for (from, len) in passability.moves(cell) {
+                let through = cost + len * entry_cost;
+                if through < self.field.integration[from] {
+                    self.field.integration[from] = through;
+                    self.field.next[from] = cell as u32;
+                    self.open.push(OpenCell { cell: from, estimate: through, cost: through });
+                }
+            }
+        }
+        self.open.is_empty()
+    }
+}
+
+/// Cache of flow fields by destination cell, and the fields being built.
+#[derive(Resource, Debug)]
+pub struct FlowFields {
+    /// Cells expanded per tick building fields.
+    pub expansions_per_tick: usize,
+    /// Squads that must share a destination cell before it gets a field.
+    pub min_squads: usize,
+    /// Fewest ticks between rebuilds of one field.
+    pub rebuild_interval: u64,
+    fields: HashMap<usize, FlowField>,
+    /// Cached fields built on terrain that has since changed.
+    stale: HashSet<usize>,
+    /// Tick each destination cell's field was last started.
+    started: HashMap<usize, u64>,
+    /// Areas changed since the last run, as center and radius.
+    changed: Vec<(f32, f32, f32)>,
+    /// Whether terrain changed somewhere unknown since the last run.
+    changed_everywhere: bool,
+    /// Destination cells waiting for a field, with their goal point.
+    queue: VecDeque<(usize, (f32, f32))>,
+    building: Option<FieldBuild>,
+    /// Destination cells whose squads steer by a field.
+    shared: HashSet<usize>,
+    /// Standing buildings when the fields were built.
+    obstacles: usize,
+    /// Terrain and obstacle changes applied so far.
+    version: u64,
+}
+
+impl Default for FlowFields {
+    fn default() -> Self {
+        Self {
+            expansions_per_tick: DEFAULT_FLOW_EXPANSIONS_PER_TICK,
+            min_squads: DEFAULT_FLOW_FIELD_MIN_SQUADS,
+            rebuild_interval: DEFAULT_FLOW_REBUILD_INTERVAL,
+            fields: HashMap::new(),
+            stale: HashSet::new(),
+            started: HashMap::new(),
+            changed: Vec::new(),
+            changed_everywhere: false,
+            queue: VecDeque::new(),
+            building: None,
+            shared: HashSet::new(),
+            obstacles: 0,
+            version: 0,
+        }
+    }
+}
+
+impl FlowFields {
+    /// Cached field toward a destination cell.
+    pub fn field(&self, target: usize) -> Option<&FlowField> {
+        self.fields.get(&target)
+    }
+
+    /// Number of cached fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Whether no fields are cached.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Whether squads making for a destination cell steer by its field
+    /// rather than finding their own routes.
+    pub fn covers(&self, target: usize) -> bool {
+        self.shared.contains(&target)
+    }
+
+    /// Whether a cached field was built on terrain that has since changed.
+    pub fn is_stale(&self, target: usize) -> bool {
+        self.stale.contains(&target)
+    }
+
+    /// Number of terrain and obstacle changes applied so far. Goes up
+    /// whenever `flow_field_system` picks up a change, so other systems
+    /// can tell that routes found before may no longer be the best.
+    pub fn terrain_version(&self) -> u64 {
+        self.version
+    }
+
+    /// Mark every field stale after a terrain change anywhere on the map.
+    /// Takes effect on the next run of `flow_field_system`.
+    pub fn invalidate(&mut self) {
+        self.changed_everywhere = true;
+    }
+
+    /// Mark stale the fields that have explored any cell of a circular
+    /// area that changed. Takes effect on the next run of
+    /// `flow_field_system`.
+    pub fn invalidate_area(&mut self, x: f32, y: f32, radius: f32) {
+        self.changed.push((x, y, radius));
+    }
+
+    /// Point a squad at `pos` should head for on its way to `goal`, if
+    /// there is a field toward the goal's cell: the next cell toward the
+    /// target, or the goal itself once in the target cell or if the field
+    /// can't lead there.
+    pub fn steer(&self, grid: &TerrainGrid, pos: &Position, goal: (f32, f32)) -> Option<(f32, f32)> {
+        let field = self.fields.get(&cell_at(grid, goal.0, goal.1))?;
+        Some(field.next_point(grid, pos.x, pos.y).unwrap_or(goal))
+    }
+
+    /// Apply the changes noted since the last run: cached fields that
+    /// explored a changed cell go stale, and so does the field being built
+    /// once it is finished.
+    fn apply_changes(&mut self, grid: &TerrainGrid) {
+        if !self.changed_everywhere && self.changed.is_empty() {
+            return;
+        }
+        self.version += 1;
+        let cells: HashSet<usize> = self.changed.drain(..)
+            .flat_map(|(x, y, radius)| footprint_cells(grid, x, y, radius))
+            .collect();
+        let everywhere = std::mem::take(&mut self.changed_everywhere);
+        let touched = |field: &FlowField| everywhere || cells.iter().any(|&cell| field.explored(cell));
+        self.stale.extend(self.fields.iter().filter(|(_, field)| touched(field)).map(|(cell, _)| *cell));
+        if let Some(build) = self.building.as_mut() {
+            build.dirty |= touched(&build.field);
+        }
+    }
+
+    /// Whether a stale field may be rebuilt yet.
+    fn rebuild_due(&self, target: usize, now: u64) -> bool {
+        self.started.get(&target).is_none_or(|&started| now >= started + self.rebuild_interval)
+    }
+
+    fn is_queued(&self, target: usize) -> bool {
+        self.building.as_ref().is_some_and(|b| b.field.target == target)
+            || self.queue.iter().any(|&(cell, _)| cell == target)
+    }
+}
+
+/// Components read by `flow_field_system` for squads.
+type FlowSquadData<'a> = (&'a Order, Option<&'a Fallback>, Option<&'a Station>);
+
+/// System that keeps flow fields for the destinations squads share.
+///
+/// ## Data Access
+/// - Reads: SimTick, TerrainResource, Order, Fallback, Station, Position, CoverProvider, DestructibleState
+/// - Writes: FlowFields
+///
+/// Runs before `pathfinding_system`, which leaves squads covered by a
+/// field to steer by it.
+pub fn flow_field_system(
+    tick: Option<Res<SimTick>>,
+    flow_fields: Option<ResMut<FlowFields>>,
+    terrain: Option<Res<TerrainResource>>,
+    buildings: Query<ObstacleData, With<Building>>,
+    squads: Query<FlowSquadData, Without<Surrendered>>,
+) {
+    let (Some(mut flow_fields), Some(terrain)) = (flow_fields, terrain) else { return };
+    let Some(grid) = terrain.read() else { return };
+    let fields = &mut *flow_fields;
+    let now = tick.map_or(0, |t| t.0);
+
+    // Squads making for each destination cell, in cell order so fields are
+    // queued the same way on every run
+    let mut destinations: BTreeMap<usize, (usize, (f32, f32))> = BTreeMap::new();
+    for (order, fallback, station) in squads.iter() {
+        if let Some(goal) = steering_goal(order, fallback, station) {
+            destinations.entry(cell_at(&grid, goal.0, goal.1)).or_insert((0, goal)).0 += 1;
+        }
+    }
+
+    // Drop fields nobody is making for
+    fields.fields.retain(|cell, _| destinations.contains_key(cell));
+    fields.stale.retain(|cell| destinations.contains_key(cell));
+    fields.started.retain(|cell, _| destinations.contains_key(cell));
+    fields.queue.retain(|(cell, _)| destinations.contains_key(cell));
+    if fields.building.as_ref().is_some_and(|b| !destinations.contains_key(&b.field.target)) {
+        fields.building = None;
+    }
+
+    let standing = buildings.iter().filter(|(.., state)| **state != DestructibleState::Destroyed).count();
+    if standing != fields.obstacles {
+        fields.obstacles = standing;
+        fields.invalidate();
+    }
+    fields.apply_changes(&grid);
+
+    // A destination keeps its field while anyone is still making for it
+    fields.shared = destinations.iter()
+        .filter(|(cell, (count, _))| *count >= fields.min_squads || fields.fields.contains_key(cell) || fields.is_queued(**cell))
+        .map(|(cell, _)| *cell)
+        .collect();
+    for (&cell, &(_, goal)) in &destinations {
+        let needed = fields.shared.contains(&cell)
+            && (!fields.fields.contains_key(&cell) || (fields.stale.contains(&cell) && fields.rebuild_due(cell, now)));
+        if needed && !fields.is_queued(cell) {
+            fields.queue.push_back((cell, goal));
+        }
+    }
+
+    if fields.building.is_none() && fields.queue.is_empty() {
+        return;
+    }
+    let blocked = blocked_cells(&grid, buildings.iter());
+    let mut budget = fields.expansions_per_tick;
+    while budget > 0 {
+        let mut build = match fields.building.take() {
+            Some(build) => build,
+            None => {
+                let Some((cell, goal)) = fields.queue.pop_front() else { break };
+                fields.started.insert(cell, now);
+                FieldBuild::new(&grid, cell, goal, goal_exemptions(&grid, goal, buildings.iter()))
+            }
+        };
+        if build.run(&grid, &blocked, &mut budget) {
+            if build.dirty {
+                fields.stale.insert(build.field.target);
+            } else {
+                fields.stale.remove(&build.field.target);
+            }
+            fields.fields.insert(build.field.target, build.field);
+        } else {
+            fields.building = Some(build);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::pathfinding::river_grid;
+
+    fn spawn_squads(world: &mut World, count: usize, goal: (f32, f32)) {
+        for i in 0..count {
+            world.spawn((Position::new(-15.0, -15.0 + i as f32), Order::MoveTo { x: goal.0, y: goal.1 }));
+        }
+    }
+
+    #[test]
+    fn test_field_leads_round_water() {
+        let grid = river_grid();
+        let target = cell_at(&grid, 10.0, -10.0);
+        let mut build = FieldBuild::new(&grid, target, (10.0, -10.0), HashSet::new());
+        let mut budget = usize::MAX;
+        assert!(build.run(&grid, &HashSet::new(), &mut budget));
+        let field = build.field;
+
+        // Follow the field from across the river
+        let (mut x, mut y) = (-10.0, -10.0);
+        for _ in 0..100 {
+            assert!(grid.get_terrain_at(x, y).terrain_type.is_passable(), "led into water at ({x}, {y})");
+            match field.next_point(&grid, x, y) {
+                Some(next) => (x, y) = next,
+                None => break,
+            }
+        }
+        assert_eq!(cell_at(&grid, x, y), target);
+        assert!(field.cost_at(&grid, -10.0, -10.0).unwrap() > 20.0, "detour costs more than the straight line");
+        assert_eq!(field.cost_at(&grid, 0.0, -10.0), None, "water can't be stood in");
+    }
+
+    #[test]
+    fn test_fields_only_for_shared_destinations() {
+        let mut world = World::new();
+        world.insert_resource(TerrainResource::new(river_grid()));
+        world.insert_resource(FlowFields::default());
+        spawn_squads(&mut world, DEFAULT_FLOW_FIELD_MIN_SQUADS, (10.0, -10.0));
+        spawn_squads(&mut world, 2, (10.0, 10.0));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(flow_field_system);
+        schedule.run(&mut world);
+
+        let grid = river_grid();
+        let fields = world.resource::<FlowFields>();
+        assert_eq!(fields.len(), 1);
+        assert!(fields.covers(cell_at(&grid, 10.0, -10.0)));
+        assert!(!fields.covers(cell_at(&grid, 10.0, 10.0)));
+        let ahead = fields.steer(&grid, &Position::new(-15.0, -15.0), (10.0, -10.0)).unwrap();
+        assert!(ahead.1 > -15.0, "should head up toward the ford, not along the river: {ahead:?}");
+
+        // Terrain change: the stale field is kept until it is rebuilt
+        world.resource_mut::<FlowFields>().expansions_per_tick = 10;
+        world.resource_mut::<FlowFields>().invalidate();
+        world.insert_resource(SimTick(DEFAULT_FLOW_REBUILD_INTERVAL));
+        schedule.run(&mut world);
+        let target = cell_at(&grid, 10.0, -10.0);
+        assert!(world.resource::<FlowFields>().is_stale(target));
+        assert!(world.resource::<FlowFields>().field(target).is_some());
+        world.resource_mut::<FlowFields>().expansions_per_tick = DEFAULT_FLOW_EXPANSIONS_PER_TICK;
+        schedule.run(&mut world);
+        assert!(!world.resource::<FlowFields>().is_stale(target));
+
+        // Squads done with the destination drop its field
+        let mut orders = world.query::<&mut Order>();
+        for mut order in orders.iter_mut(&mut world) {
+            *order = Order::Hold;
+        }
+        schedule.run(&mut world);
+        assert!(world.resource::<FlowFields>().is_empty());
+    }
+
+    #[test]
+    fn test_changes_merge_into_one_rebuild_per_interval() {
+        let grid = river_grid();
+        let target = cell_at(&grid, 10.0, -10.0);
+        let mut world = World::new();
+        world.insert_resource(TerrainResource::new(river_grid()));
+        world.insert_resource(FlowFields::default());
+        world.insert_resource(SimTick(0));
+        spawn_squads(&mut world, DEFAULT_FLOW_FIELD_MIN_SQUADS, (10.0, -10.0));
+        let mut schedule = Schedule::default();
+        schedule.add_systems(flow_field_system);
+        schedule.run(&mut world);
+        assert!(world.resource::<FlowFields>().field(target).is_some());
+
+        // Water the field never explored doesn't make it stale
+        world.resource_mut::<FlowFields>().invalidate_area(0.0, -10.0, 0.5);
+        world.insert_resource(SimTick(1));
+        schedule.run(&mut world);
+        assert!(!world.resource::<FlowFields>().is_stale(target));
+
+        // A crater it crosses does, but the rebuild waits for the interval
+        // however many more land meanwhile
+        for t in 2..DEFAULT_FLOW_REBUILD_INTERVAL {
+            world.resource_mut::<FlowFields>().invalidate_area(-10.0, -10.0, 3.0);
+            world.insert_resource(SimTick(t));
+            schedule.run(&mut world);
+            assert!(world.resource::<FlowFields>().is_stale(target));
+            assert!(!world.resource::<FlowFields>().is_queued(target), "rebuilt early at tick {t}");
+        }
+
+        // The rebuild keeps going through craters in cells it has explored,
+        // and the field it finishes is stale again
+        world.resource_mut::<FlowFields>().expansions_per_tick = 10;
+        world.insert_resource(SimTick(DEFAULT_FLOW_REBUILD_INTERVAL));
+        schedule.run(&mut world);
+        world.resource_mut::<FlowFields>().invalidate_area(10.0, -10.0, 1.0);
+        schedule.run(&mut world);
+        let fields = world.resource::<FlowFields>();
+        let build = fields.building.as_ref().expect("rebuild in progress");
+        assert!(build.dirty);
+        assert!(build.field.explored(cell_at(&grid, 8.0, -10.0)), "progress kept");
+        world.resource_mut::<FlowFields>().expansions_per_tick = DEFAULT_FLOW_EXPANSIONS_PER_TICK;
+        schedule.run(&mut world);
+        let fields = world.resource::<FlowFields>();
+        assert!(fields.building.is_none());
+        assert!(fields.is_stale(target));
+    }
+}
@@ -0,0 +1,269 @@
+//! Group formations.
+//!
+//! A group move gives each squad a slot relative to a moving anchor and the
+//! group's facing. The anchor advances toward the destination at the pace
+//! of the slowest squad and waits for stragglers, so the group keeps its
+//! shape on the march; at the destination the squads hold their slots.
+//!
+//! A group can march in one formation and deploy into another once it is
+//! within `DEPLOY_DISTANCE` of the destination, e.g. a column along a road
+//! that shakes out into line at the front.
+//!
+//! A squad leaves its group when given any other order.
+
+use crate::components::*;
+use crate::systems::movement::DeltaTime;
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Distance between neighbouring slots (world units).
+pub const FORMATION_SPACING: f32 = 10.0;
+
+/// Slot spacing multiplier for skirmish order.
+const SKIRMISH_SPACING_FACTOR: f32 = 2.0;
+
+/// Remaining distance at which a marching group deploys.
+pub const DEPLOY_DISTANCE: f32 = 40.0;
+
+/// Anchor speed relative to the slowest squad, so squads can keep up.
+const ANCHOR_SPEED_FACTOR: f32 = 0.8;
+
+/// The anchor waits while any squad is farther than this from its slot.
+const MAX_SLOT_LAG: f32 = 2.0 * FORMATION_SPACING;
+
+/// Arrangement of squads in a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Formation {
+    /// Side by side, facing the front.
+    #[default]
+    Line,
+    /// One behind the other, behind the anchor.
+    Column,
+    /// Arrowhead with the first squad at the point.
+    Wedge,
+    /// Wide, staggered line in extended order.
+    Skirmish,
+}
+
+impl Formation {
+    /// Slot of squad `index` of `count`, as (forward, right) offsets from the
+    /// anchor in the group's frame.
+    pub fn slot_offset(&self, index: usize, count: usize) -> (f32, f32) {
+        let centered = index as f32 - (count.max(1) - 1) as f32 / 2.0;
+        match self {
+            Formation::Line => (0.0, centered * FORMATION_SPACING),
+            Formation::Column => (-(index as f32) * FORMATION_SPACING, 0.0),
+            Formation::Wedge => {
+                let rank = index.div_ceil(2) as f32;
+                let side = if index % 2 == 1 { -1.0 } else { 1.0 };
+                (-rank * FORMATION_SPACING, side * rank * FORMATION_SPACING)
+            }
+            Formation::Skirmish => {
+                let stagger = if index % 2 == 1 { -0.5 * FORMATION_SPACING } else { 0.0 };
+                (stagger, centered * FORMATION_SPACING * SKIRMISH_SPACING_FACTOR)
+            }
+        }
+    }
+
+    /// World position of a slot for an anchor and facing (radians).
+    pub fn slot_position(&self, index: usize, count: usize, anchor: (f32, f32), facing: f32) -> (f32, f32) {
+        let (forward, right) = self.slot_offset(index, count);
+        let (sin, cos) = facing.sin_cos();
+        (
+            anchor.0 + forward * cos + right * sin,
+            anchor.1 + forward * sin - right * cos,
+        )
+    }
+}
+
+/// A group of squads moving in formation. Lives on its own entity.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FormationGroup {
+    pub formation: Formation,
+    /// Formation to switch to within `DEPLOY_DISTANCE` of the destination.
+    pub deploy: Option<Formation>,
+    pub anchor_x: f32,
+    pub anchor_y: f32,
+    /// Direction the group faces (radians, 0 = +x).
+    pub facing: f32,
+    pub destination_x: f32,
+    pub destination_y: f32,
+    /// Whether squads attack-move to their slots.
+    pub attack: bool,
+}
+
+impl FormationGroup {
+    /// A group at `anchor` heading for `destination`, facing it.
+    pub fn new(formation: Formation, anchor: (f32, f32), destination: (f32, f32)) -> Self {
+        let facing = (destination.1 - anchor.1).atan2(destination.0 - anchor.0);
+        Self {
+            formation,
+            deploy: None,
+            anchor_x: anchor.0,
+            anchor_y: anchor.1,
+            facing,
+            destination_x: destination.0,
+            destination_y: destination.1,
+            attack: false,
+        }
+    }
+
+    fn remaining(&self) -> f32 {
+        (self.destination_x - self.anchor_x).hypot(self.destination_y - self.anchor_y)
+    }
+}
+
+/// Membership of a squad in a `FormationGroup`. Squads take slots in
+/// `rank` order.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormationMember {
+    pub group: Entity,
+    pub rank: u32,
+}
+
+/// Components read and written by `formation_system` for member squads.
+type MemberData<'a> = (
+    Entity,
+    &'a FormationMember,
+    &'a Position,
+    &'a SquadStats,
+    &'a Health,
+    &'a mut Order,
+);
+
+/// System that moves formation anchors and assigns squads their slots.
+///
+/// ## Data Access
+/// - Reads: DeltaTime, FormationMember, Position, SquadStats, Health
+/// - Writes: FormationGroup, Order (despawns empty groups)
+pub fn formation_system(
+    mut commands: Commands,
+    dt: Res<DeltaTime>,
+    mut groups: Query<(Entity, &mut FormationGroup)>,
+    mut members: Query<MemberData, Without<Surrendered>>,
+) {
+    // Living members per group, in rank order
+    let mut rosters: BTreeMap<Entity, Vec<(u32, Entity)>> = BTreeMap::new();
+    for (entity, member, _, _, health, _) in members.iter() {
+        if health.is_alive() {
+            rosters.entry(member.group).or_default().push((member.rank, entity));
+        }
+    }
+
+    for (group_entity, mut group) in groups.iter_mut() {
+        let Some(mut roster) = rosters.remove(&group_entity) else {
+            commands.entity(group_entity).despawn();
+            continue;
+        };
+        roster.sort_unstable();
+        let count = roster.len();
+
+        if let Some(deploy) = group.deploy {
+            if group.remaining() <= DEPLOY_DISTANCE {
+                group.formation = deploy;
+                group.deploy = None;
+            }
+        }
+
+        // Advance the anchor unless squads are still falling in
+        let anchor = (group.anchor_x, group.anchor_y);
+        let mut slowest = f32::MAX;
+        let mut lagging = false;
+        for (index, &(_, entity)) in roster.iter().enumerate() {
+            let Ok((_, _, pos, stats, ..)) = members.get(entity) else { continue };
+            let (sx, sy) = group.formation.slot_position(index, count, anchor, group.facing);
+            lagging |= (sx - pos.x).hypot(sy - pos.y) > MAX_SLOT_LAG;
+            slowest = slowest.min(stats.speed);
+        }
+        let remaining = group.remaining();
+        if !lagging && remaining > 0.0 {
+            let step = (slowest * ANCHOR_SPEED_FACTOR * dt.0).min(remaining);
+            let (dx, dy) = (group.destination_x - group.anchor_x, group.destination_y - group.anchor_y);
+            group.anchor_x += dx / remaining * step;
+            group.anchor_y += dy / remaining * step;
+        }
+
+        let anchor = (group.anchor_x, group.anchor_y);
+        for (index, &(_, entity)) in roster.iter().enumerate() {
+            let Ok((.., mut order)) = members.get_mut(entity) else { continue };
+            let (x, y) = group.formation.slot_position(index, count, anchor, group.facing);
+            *order = if group.attack { Order::AttackMove { x, y } } else { Order::MoveTo { x, y } };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::movement::{movement_system, order_system};
+
+    #[test]
+    fn test_formation_slots() {
+        // Facing +x: a line spreads along y, a column trails along -x
+        let line: Vec<_> = (0..3).map(|i| Formation::Line.slot_position(i, 3, (0.0, 0.0), 0.0)).collect();
+        assert!(line.iter().all(|(x, _)| x.abs() < 1e-4));
+        assert!((line[0].1 - line[2].1).abs() > 1.9 * FORMATION_SPACING);
+
+        let column: Vec<_> = (0..3).map(|i| Formation::Column.slot_position(i, 3, (0.0, 0.0), 0.0)).collect();
+        assert!(column.iter().all(|(_, y)| y.abs() < 1e-4));
+        assert!(column[2].0 < column[1].0 && column[1].0 < column[0].0);
+
+        let wedge: Vec<_> = (0..3).map(|i| Formation::Wedge.slot_offset(i, 3)).collect();
+        assert_eq!(wedge[0], (0.0, 0.0));
+        assert_eq!(wedge[1].0, wedge[2].0);
+        assert_eq!(wedge[1].1, -wedge[2].1);
+
+        let skirmish = Formation::Skirmish.slot_offset(0, 3).1 - Formation::Skirmish.slot_offset(2, 3).1;
+        assert!(skirmish.abs() > (line[0].1 - line[2].1).abs());
+    }
+
+    #[test]
+    fn test_group_marches_in_column_and_deploys_into_line() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.1));
+
+        let mut group = FormationGroup::new(Formation::Column, (0.0, 0.0), (120.0, 0.0));
+        group.deploy = Some(Formation::Line);
+        let group = world.spawn(group).id();
+        let squads: Vec<Entity> = (0..3)
+            .map(|i| {
+                world.spawn((
+                    Position::new(0.0, i as f32 * 5.0),
+                    Velocity::default(),
+                    SquadStats::default(),
+                    Health::new(100.0),
+                    Suppression::default(),
+                    Morale::default(),
+                    Order::Hold,
+                    FormationMember { group, rank: i },
+                )).id()
+            })
+            .collect();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((formation_system, order_system, movement_system).chain());
+        let ys = |world: &World| -> Vec<f32> {
+            squads.iter().map(|&e| world.get::<Position>(e).unwrap().y).collect()
+        };
+
+        for _ in 0..150 {
+            schedule.run(&mut world);
+        }
+        // Marching: strung out along the road
+        assert_eq!(world.get::<FormationGroup>(group).unwrap().formation, Formation::Column);
+        assert!(ys(&world).iter().all(|y| y.abs() < 1.0));
+
+        for _ in 0..600 {
+            schedule.run(&mut world);
+        }
+        let group_state = world.get::<FormationGroup>(group).unwrap();
+        assert_eq!(group_state.formation, Formation::Line);
+        assert!((group_state.anchor_x - 120.0).abs() < 1e-3 && group_state.anchor_y.abs() < 1e-3);
+        // Deployed: abreast at the destination, one slot apart
+        let mut deployed = ys(&world);
+        deployed.sort_by(f32::total_cmp);
+        assert!((deployed[1] - deployed[0] - FORMATION_SPACING).abs() < 1.0);
+        assert!(squads.iter().all(|&e| (world.get::<Position>(e).unwrap().x - 120.0).abs() < 1.0));
+    }
+}
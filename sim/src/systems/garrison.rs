@@ -0,0 +1,261 @@
+//! Garrisoned buildings.
+//!
+//! A squad given `Order::Garrison` walks to the building and, on reaching its
+//! footprint, occupies it if there is room (`GarrisonCapacity`). Occupants
+//! sit at the building's center, so they get its `CoverProvider` cover from
+//! every side, and they fire from an upper floor (`GARRISON_ELEVATION`).
+//! Enemies only spot them from within `GARRISON_SPOTTING_RANGE`; area fire
+//! still reaches them.
+//!
+//! Any movement order, or `Order::Ungarrison`, takes the squad back outside.
+//! When the building is destroyed the occupants are thrown out with
+//! casualties and a morale shock, on top of the collapse blast itself.
+
+use crate::components::*;
+use crate::los::BUILDING_FOOTPRINT_FRACTION;
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+
+/// Height above the ground that occupants fire from (world units).
+pub const GARRISON_ELEVATION: f32 = 4.0;
+
+/// Distance within which enemies can spot a garrisoned squad.
+pub const GARRISON_SPOTTING_RANGE: f32 = 30.0;
+
+/// Fraction of max health lost by occupants of a collapsing building.
+const COLLAPSE_CASUALTIES: f32 = 0.25;
+
+/// Morale lost by occupants of a collapsing building.
+const COLLAPSE_MORALE_SHOCK: f32 = 0.4;
+
+/// A building as seen by `garrison_system`.
+#[derive(Debug, Clone, Copy)]
+struct GarrisonSite {
+    entity: Entity,
+    x: f32,
+    y: f32,
+    /// Distance from the center at which a squad is inside.
+    footprint: f32,
+    /// Distance from the center at which departing squads are placed.
+    exit_radius: f32,
+    capacity: u32,
+    destroyed: bool,
+}
+
+impl GarrisonSite {
+    /// Where a squad leaving the building stands, on the side facing
+    /// `toward` (east if there's no destination).
+    fn exit_point(&self, toward: Option<(f32, f32)>) -> (f32, f32) {
+        let (dx, dy) = toward
+            .map(|(x, y)| (x - self.x, y - self.y))
+            .filter(|(dx, dy)| dx * dx + dy * dy > 0.001)
+            .unwrap_or((1.0, 0.0));
+        let dist = (dx * dx + dy * dy).sqrt();
+        (self.x + dx / dist * self.exit_radius, self.y + dy / dist * self.exit_radius)
+    }
+}
+
+/// Components read by `garrison_system` for buildings.
+type BuildingData<'a> = (
+    Entity,
+    &'a DestructibleId,
+    &'a Position,
+    &'a CoverProvider,
+    &'a DestructibleState,
+    Option<&'a GarrisonCapacity>,
+);
+
+/// Components read and written by `garrison_system` for squads.
+type GarrisonSquadData<'a> = (
+    Entity,
+    &'a mut Position,
+    Option<&'a mut Velocity>,
+    &'a mut Order,
+    &'a mut Health,
+    Option<&'a mut Morale>,
+    Option<&'a Garrisoned>,
+);
+
+/// Destination of a movement order, if the order moves the squad.
+fn destination(order: &Order) -> Option<(f32, f32)> {
+    match *order {
+        Order::MoveTo { x, y } | Order::AttackMove { x, y } | Order::Garrison { x, y, .. } => Some((x, y)),
+        _ => None,
+    }
+}
+
+/// System that moves squads into and out of buildings.
+///
+/// ## Data Access
+/// - Reads: DestructibleId, CoverProvider, DestructibleState, GarrisonCapacity, Position (buildings)
+/// - Writes: Position, Velocity, Order, Health, Morale, Garrisoned (squads)
+///
+/// Runs after movement so occupants are pinned to their building before
+/// cover detection and combat.
+pub fn garrison_system(
+    mut commands: Commands,
+    buildings: Query<BuildingData, With<Building>>,
+    mut squads: Query<GarrisonSquadData, (Without<Building>, Without<Surrendered>)>,
+) {
+    let sites: HashMap<Entity, GarrisonSite> = buildings.iter()
+        .map(|(entity, _, pos, cover, state, capacity)| {
+            (entity, GarrisonSite {
+                entity,
+                x: pos.x,
+                y: pos.y,
+                footprint: cover.radius * BUILDING_FOOTPRINT_FRACTION,
+                exit_radius: cover.radius,
+                capacity: capacity.map(|c| c.0).unwrap_or(0),
+                destroyed: *state == DestructibleState::Destroyed,
+            })
+        })
+        .collect();
+    let by_id: HashMap<u32, Entity> = buildings.iter().map(|(entity, id, ..)| (id.0, entity)).collect();
+
+    let mut occupancy: HashMap<Entity, u32> = HashMap::new();
+    for (.., health, _, garrisoned) in squads.iter() {
+        if let (Some(g), true) = (garrisoned, health.is_alive()) {
+            *occupancy.entry(g.building).or_insert(0) += 1;
+        }
+    }
+
+    for (entity, mut pos, vel, mut order, mut health, morale, garrisoned) in squads.iter_mut() {
+        if !health.is_alive() {
+            if garrisoned.is_some() {
+                commands.entity(entity).remove::<Garrisoned>();
+            }
+            continue;
+        }
+
+        if let Some(garrisoned) = garrisoned {
+            let site = sites.get(&garrisoned.building).filter(|s| !s.destroyed);
+            let staying = match *order {
+                Order::Garrison { building, .. } => by_id.get(&building) == Some(&garrisoned.building),
+                Order::Hold | Order::SuppressArea { .. } => true,
+                _ => false,
+            };
+            match site {
+                Some(site) if staying => {
+                    pos.x = site.x;
+                    pos.y = site.y;
+                    if let Some(mut vel) = vel {
+                        vel.vx = 0.0;
+                        vel.vy = 0.0;
+                    }
+                }
+                _ => {
+                    commands.entity(entity).remove::<Garrisoned>();
+                    if let Some(site) = sites.get(&garrisoned.building) {
+                        (pos.x, pos.y) = site.exit_point(destination(&order));
+                        if site.destroyed {
+                            let max = health.max;
+                            health.damage(max * COLLAPSE_CASUALTIES);
+                            if let Some(mut morale) = morale {
+                                morale.decrease(COLLAPSE_MORALE_SHOCK);
+                            }
+                        }
+                    }
+                    if staying || matches!(*order, Order::Ungarrison) {
+                        *order = Order::Hold;
+                    }
+                }
+            }
+            continue;
+        }
+
+        match *order {
+            Order::Ungarrison => *order = Order::Hold,
+            Order::Garrison { building, .. } => {
+                let Some(site) = by_id.get(&building).and_then(|e| sites.get(e)) else {
+                    *order = Order::Hold;
+                    continue;
+                };
+                if site.destroyed {
+                    *order = Order::Hold;
+                    continue;
+                }
+                if (pos.x - site.x).powi(2) + (pos.y - site.y).powi(2) > site.footprint * site.footprint {
+                    continue;
+                }
+                let occupants = occupancy.entry(site.entity).or_insert(0);
+                if *occupants >= site.capacity {
+                    // Full: stay outside
+                    *order = Order::Hold;
+                    continue;
+                }
+                *occupants += 1;
+                commands.entity(entity).insert(Garrisoned { building: site.entity });
+                pos.x = site.x;
+                pos.y = site.y;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_squad(world: &mut World, id: u32, x: f32, order: Order) -> Entity {
+        world.spawn((
+            SquadId(id),
+            Faction::Blue,
+            Position::new(x, 0.0),
+            Velocity::default(),
+            Health::new(100.0),
+            Morale::default(),
+            order,
+        )).id()
+    }
+
+    fn garrison_order() -> Order {
+        Order::Garrison { building: 7, x: 0.0, y: 0.0 }
+    }
+
+    #[test]
+    fn test_garrison_respects_capacity_and_ungarrisons() {
+        let mut world = World::new();
+        let building = world.spawn(BuildingBundle::new(7, 0.0, 0.0)).id();
+        let squads: Vec<Entity> = (0..3).map(|i| spawn_squad(&mut world, i, 1.0, garrison_order())).collect();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(garrison_system);
+        schedule.run(&mut world);
+
+        let inside: Vec<Entity> = squads.iter().copied()
+            .filter(|&e| world.get::<Garrisoned>(e) == Some(&Garrisoned { building }))
+            .collect();
+        assert_eq!(inside.len(), 2, "building holds two squads");
+        assert_eq!(world.get::<Position>(inside[0]).unwrap().x, 0.0);
+        let left_out = squads.iter().find(|e| !inside.contains(e)).unwrap();
+        assert!(matches!(world.get::<Order>(*left_out).unwrap(), Order::Hold));
+
+        *world.get_mut::<Order>(inside[0]).unwrap() = Order::Ungarrison;
+        schedule.run(&mut world);
+        assert!(world.get::<Garrisoned>(inside[0]).is_none());
+        assert!(world.get::<Position>(inside[0]).unwrap().x >= 5.0);
+        assert!(world.get::<Garrisoned>(inside[1]).is_some());
+    }
+
+    #[test]
+    fn test_destroyed_building_ejects_occupants_with_casualties() {
+        let mut world = World::new();
+        let building = world.spawn(BuildingBundle::new(7, 0.0, 0.0)).id();
+        let squad = spawn_squad(&mut world, 1, 0.0, garrison_order());
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(garrison_system);
+        schedule.run(&mut world);
+        assert!(world.get::<Garrisoned>(squad).is_some());
+
+        *world.get_mut::<DestructibleState>(building).unwrap() = DestructibleState::Destroyed;
+        schedule.run(&mut world);
+
+        assert!(world.get::<Garrisoned>(squad).is_none());
+        assert!(matches!(world.get::<Order>(squad).unwrap(), Order::Hold));
+        assert!(world.get::<Health>(squad).unwrap().current < 100.0);
+        assert!(world.get::<Morale>(squad).unwrap().value < Morale::default().value);
+        assert!(world.get::<Position>(squad).unwrap().x > 0.0);
+    }
+}
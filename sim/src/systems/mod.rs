@@ -16,6 +16,8 @@
 //! | System | Reads | Writes |
 //! |--------|-------|--------|
 //! | `spatial_grid_update_system` | Position, Faction, Health | SpatialGrid |
+//! | `los_update_system` | Position, CoverProvider, DestructibleState, SmokeCloud | LineOfSight |
+//! | `cover_provider_index_system` | Position, CoverProvider, DestructibleState | CoverProviderIndex |
 //! | `lod_assignment_system` | Position, SimConfig | SimLod |
 //! | `sector_assignment_system` | Position, SimConfig | SectorId |
 //! | `activity_flags_system` | Velocity, Suppression, SimTick | ActivityFlags |
@@ -28,7 +30,7 @@
 //! 
 //! | System | Reads | Writes |
 //! |--------|-------|--------|
-//! | `threat_awareness_system` | SpatialGrid, Position, Faction, SquadStats, SimLod | ThreatAwareness |
+//! | `threat_awareness_system` | SpatialGrid, LineOfSight, TerrainResource, Position, Faction, SquadStats, SimLod, Garrisoned | ThreatAwareness |
 //! | `nearby_friendlies_system` | SpatialGrid, Position, Faction, FlockingWeights | NearbyFriendlies |
 //! | `behavior_state_system` | ThreatAwareness, Suppression, Morale, Order | BehaviorState |
 //! 
@@ -40,10 +42,13 @@
 //! 
 //! | System | Reads | Writes |
 //! |--------|-------|--------|
-//! | `ai_order_system` | BehaviorState, ThreatAwareness, Position | Order |
-//! | `flocking_system` | NearbyFriendlies, ThreatAwareness, Position, FlockingWeights | Velocity |
+//! | `ai_order_system` | BehaviorState, ThreatAwareness, TerrainResource, LineOfSight, SimTick, Position, SquadStats | Order |
+//! | `flocking_system` | NearbyFriendlies, ThreatAwareness, Position, FlockingWeights, FlowFields, TerrainResource | Velocity |
+//! | `ai_fire_stance_system` | SimTick, TacticalPreferences, BehaviorState, ThreatAwareness, SquadStats, IncomingFire, ExplicitFireStance | FireStance |
+//! | `command_ai_system` | CommandGroup, CommandParent, CommandObjective, SquadId, Position, Health, BehaviorState, ThreatAwareness, FormationMember | Order |
 //! 
 //! **Parallelization potential**: HIGH - Different write targets.
+//! `command_ai_system` runs after `ai_order_system` (both write Order).
 //! 
 //! ### Group 4: Core Simulation (After Group 3)
 //! 
@@ -51,15 +56,30 @@
 //! 
 //! | System | Reads | Writes | Notes |
 //! |--------|-------|--------|-------|
-//! | `order_system` | Order, SquadStats | Velocity | |
-//! | `movement_system` | Velocity, Suppression, Morale, TerrainResource | Position | |
-//! | `combat_system` | SpatialGrid, Position, Faction, SquadStats, SimLod, Morale | Health, Suppression, ActivityFlags | HEAVIEST |
+//! | `order_trigger_system` | SimTick, SimConfig, LiftedBarrages, SquadId, Faction, Position, Health, Morale | ScheduledOrders, Order, OrderQueue | Releases scheduled orders |
+//! | `engage_target_system` | TerrainResource, LineOfSight, SquadId, Position, Health, SquadStats | FireStance, Order | Closes on designated targets |
+//! | `retreat_system` | DeltaTime, RallyPoint, Faction, Position, Health, Suppression | Morale, Order, Fallback, ResumeOrder, FormationMember | Routed squads run for rally points |
+//! | `defense_system` | SimTick, SpatialGrid, TerrainResource, LineOfSight, Faction, Position, Health, SquadStats, Order, Garrisoned | Facing, Station, PatrolLeg | Area defense and patrols |
+//! | `formation_system` | DeltaTime, FormationMember, Position, SquadStats, Health | FormationGroup, Order | Slots for group moves |
+//! | `flow_field_system` | SimTick, TerrainResource, Order, Fallback, Station, Position, CoverProvider, DestructibleState | FlowFields | Fields for shared destinations |
+//! | `pathfinding_system` | SimTick, TerrainResource, FlowFields, Order, Fallback, Station, Position, CoverProvider, DestructibleState | Pathfinder, NavPath | Time-sliced A* |
+//! | `order_system` | TerrainResource, FlowFields, SquadStats, Suppression, Morale, Fallback, Station | Velocity, Order, OrderQueue, Facing, NavPath | Advances queued orders, follows routes and flow fields |
+//! | `movement_system` | Velocity, Suppression, Morale, Order, TerrainResource | Position | |
+//! | `garrison_system` | Order, DestructibleId, DestructibleState, CoverProvider, GarrisonCapacity | Position, Velocity, Order, Health, Morale, Garrisoned | After movement |
+//! | `cover_detection_system` | TerrainResource, CoverZones, CoverProviderIndex, Position | InCover | |
+//! | `fatigue_system` | Velocity, SquadStats | Fatigue | |
+//! | `assault_system` | SpatialGrid, Position, Faction, SquadStats, Suppression, InCover | Health, Morale, Order, Velocity, Fatigue, CloseAssaults | |
+//! | `combat_gather_system` | SpatialGrid, LineOfSight, CoverZones, CoverProviderIndex, Position, Faction, SquadStats, Health, Suppression, SimLod, Morale, InCover, UnitClass, FirePriority, Order, Garrisoned, FireStance, SquadId, IncomingFire | PendingCombatResults | HEAVIEST |
+//! | `sector_combat_system` | SectorId, SimLod, Position, Faction, SquadStats, Morale, InCover, FireStance, UnitClass | Health, Suppression, CombatStats, ActivityFlags, IncomingFire, SectorCombatData, PendingCombatResults | Low-LOD only, every 4 ticks |
+//! | `combat_apply_system` | PendingCombatResults | Health, Suppression, CombatStats, IncomingFire, ActivityFlags, DestructibleHealth | Credits kills; fire stopped by cover wears it down |
+//! | `combat_log_system` | PendingCombatResults, SquadId, Position | CombatLog | Only while logging |
 //! | `suppression_decay_system` | DeltaTime | Suppression | |
 //! | `morale_system` | Suppression, NearbyFriendlies | Morale | |
-//! | `rout_system` | Morale | Velocity, Order | |
+//! | `command_morale_system` | CommandParent, Health, Surrendered | CommandGroup, Morale | Losses shake the whole group |
+//! | `rout_system` | Morale | Order, ResumeOrder | |
 //! 
 //! **Parallelization potential**: LOW - Sequential dependencies.
-//! **Optimization target**: `combat_system` is the heaviest, consider `par_iter`.
+//! **Optimization target**: `combat_gather_system` is the heaviest, consider `par_iter`.
 //! 
 //! ### Group 5: Environment (After Group 4)
 //! 
@@ -68,14 +88,17 @@
 //! | System | Reads | Writes |
 //! |--------|-------|--------|
 //! | `terrain_damage_to_destructibles_system` | TerrainDamageEvent | DestructibleHealth |
-//! | `destruction_state_system` | DestructibleHealth | DestructibleState |
+//! | `blast_damage_system` | TerrainDamageEvent, BlastSource, Position, Faction, SquadStats, InCover | Health, Suppression, Morale, AfterActionReport |
+//! | `clear_terrain_damage_system` | TerrainDamageEvent | TerrainDamageBuffer (despawns events) |
+//! | `destruction_state_system` | DestructibleHealth, Position, Building | DestructibleState, TerrainDamageEvent (collapse) |
+//! | `smoke_system` | DeltaTime, Wind | SmokeCloud, Position (despawns dispersed clouds) |
 //! 
 //! **Parallelization potential**: HIGH - Different entity types.
 //! 
 //! ## Next Steps for Parallelization
 //! 
-//! 1. **Intra-system parallelism**: Use `par_iter()` in `combat_system` for the
-//!    attacker loop (collect phase only, apply phase must be sequential).
+//! 1. **Intra-system parallelism**: Use `par_iter()` in `combat_gather_system`
+//!    for the attacker loop (`combat_apply_system` must stay sequential).
 //! 
 //! 2. **Group-level parallelism**: Groups 1, 2, and 5 have high potential for
 //!    running systems in parallel within the group.
@@ -84,23 +107,53 @@
 //!    are actually the bottleneck before optimizing.
 
 pub mod ai;
+pub mod assault;
 pub mod combat;
+pub mod combat_log;
+pub mod command;
 pub mod cover;
+pub mod defense;
 pub mod destruction;
+pub mod engagement;
+pub mod explosion;
+pub mod flowfield;
+pub mod formation;
+pub mod garrison;
 pub mod morale;
 pub mod movement;
+pub mod pathfinding;
 pub mod performance;
+pub mod retreat;
+pub mod sector_combat;
 pub mod serialization;
+pub mod smoke;
 pub mod suppression;
+pub mod targeting;
 pub mod terrain_damage;
+pub mod trigger;
 
 pub use ai::*;
+pub use assault::*;
 pub use combat::*;
+pub use combat_log::*;
+pub use command::*;
 pub use cover::*;
+pub use defense::*;
 pub use destruction::*;
+pub use engagement::*;
+pub use explosion::*;
+pub use flowfield::*;
+pub use formation::*;
+pub use garrison::*;
 pub use morale::{morale_system, rout_system};
 pub use movement::*;
+pub use pathfinding::*;
 pub use performance::*;
+pub use retreat::*;
+pub use sector_combat::*;
 pub use serialization::*;
+pub use smoke::*;
 pub use suppression::*;
+pub use targeting::*;
 pub use terrain_damage::*;
+pub use trigger::*;
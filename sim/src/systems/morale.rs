@@ -2,6 +2,7 @@
 
 use crate::components::*;
 use crate::systems::movement::DeltaTime;
+use crate::systems::retreat::ResumeOrder;
 use bevy_ecs::prelude::*;
 
 /// Rate at which morale recovers per second (when not suppressed).
@@ -81,15 +82,18 @@ pub fn morale_system(
 }
 
 /// System that handles squad behavior when morale breaks (rout).
+///
+/// The interrupted order is kept as a `ResumeOrder` for when the squad
+/// rallies.
 pub fn rout_system(
-    mut query: Query<(&Morale, &mut Order, &Faction, &Position)>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &Morale, &mut Order), Without<Surrendered>>,
 ) {
-    for (morale, mut order, _faction, _pos) in query.iter_mut() {
+    for (entity, morale, mut order) in query.iter_mut() {
         // Broken morale forces retreat
-        if morale.is_broken() {
-            if !matches!(*order, Order::Retreat) {
-                *order = Order::Retreat;
-            }
+        if morale.is_broken() && !matches!(*order, Order::Retreat) {
+            commands.entity(entity).insert(ResumeOrder(*order));
+            *order = Order::Retreat;
         }
     }
 }
@@ -163,5 +167,6 @@ mod tests {
 
         let order = world.get::<Order>(entity).unwrap();
         assert!(matches!(*order, Order::Retreat));
+        assert!(matches!(world.get::<ResumeOrder>(entity), Some(ResumeOrder(Order::Hold))));
     }
 }
@@ -1,6 +1,10 @@
 //! Movement system - applies velocity to position and handles orders.
 
 use crate::components::*;
+use crate::systems::defense::Station;
+use crate::systems::flowfield::FlowFields;
+use crate::systems::pathfinding::NavPath;
+use crate::systems::retreat::Fallback;
 use crate::terrain::TerrainResource;
 use bevy_ecs::prelude::*;
 
@@ -8,17 +12,24 @@ use bevy_ecs::prelude::*;
 #[derive(Resource, Default)]
 pub struct DeltaTime(pub f32);
 
+/// Whether a squad can't act on its order. Pinned squads are stuck; broken
+/// squads can only run.
+fn is_frozen(suppression: &Suppression, morale: &Morale, order: Option<&Order>) -> bool {
+    suppression.is_pinned() || (morale.is_broken() && !matches!(order, Some(Order::Retreat)))
+}
+
 /// System that applies velocity to position.
 /// Takes terrain into account for movement speed.
 pub fn movement_system(
     dt: Res<DeltaTime>,
     terrain: Option<Res<TerrainResource>>,
-    mut query: Query<(&mut Position, &Velocity, &Suppression, &Morale)>,
+    mut query: Query<(&mut Position, &Velocity, &Suppression, &Morale, Option<&Order>)>,
 ) {
     let delta = dt.0;
-    for (mut pos, vel, suppression, morale) in query.iter_mut() {
-        // Don't move if pinned or broken
-        if suppression.is_pinned() || morale.is_broken() {
+    let terrain_guard = terrain.as_ref().and_then(|t| t.read());
+    for (mut pos, vel, suppression, morale, order) in query.iter_mut() {
+        // Don't move if pinned, or broken and not running
+        if is_frozen(suppression, morale, order) {
             continue;
         }
 
@@ -32,9 +43,8 @@ pub fn movement_system(
         };
 
         // Apply terrain movement modifier
-        if let Some(ref terrain_res) = terrain {
-            let terrain_mult = terrain_res.get_movement_multiplier(pos.x, pos.y);
-            speed_mult *= terrain_mult;
+        if let Some(ref grid) = terrain_guard {
+            speed_mult *= grid.get_movement_multiplier(pos.x, pos.y);
         }
 
         pos.x += vel.vx * delta * speed_mult;
@@ -42,47 +52,118 @@ pub fn movement_system(
     }
 }
 
-/// System that updates velocity based on orders.
+/// Components read and written by `order_system`.
+type OrderData<'a> = (
+    &'a mut Velocity,
+    &'a Position,
+    &'a mut Order,
+    &'a SquadStats,
+    &'a Suppression,
+    &'a Morale,
+    Option<&'a mut OrderQueue>,
+    Option<&'a mut Facing>,
+    Option<&'a Fallback>,
+    Option<&'a Station>,
+    Option<&'a mut NavPath>,
+);
+
+/// System that updates velocity based on orders. Surrendered squads ignore orders.
+///
+/// When the current order completes, the next one in the squad's
+/// `OrderQueue` takes over and the squad turns to the queued facing.
+/// Moving squads face their direction of travel and steer by the flow
+/// field toward their goal if it has one, else follow their `NavPath`.
+/// Retreating squads run for their `Fallback` point. Squads on standing
+/// orders make for their `Station` at attack-move pace.
 pub fn order_system(
-    mut query: Query<(&mut Velocity, &Position, &Order, &SquadStats, &Suppression, &Morale)>,
+    flow_fields: Option<Res<FlowFields>>,
+    terrain: Option<Res<TerrainResource>>,
+    mut query: Query<OrderData, Without<Surrendered>>,
 ) {
-    for (mut vel, pos, order, stats, suppression, morale) in query.iter_mut() {
-        // Can't execute orders if pinned or broken
-        if suppression.is_pinned() || morale.is_broken() {
+    let terrain_guard = terrain.as_ref().and_then(|t| t.read());
+    let flow = flow_fields.as_deref().zip(terrain_guard.as_deref());
+    for (mut vel, pos, mut order, stats, suppression, morale, queue, mut facing, fallback, station, mut path) in query.iter_mut() {
+        if let Some(mut queue) = queue.filter(|q| q.has_work()) {
+            if order.is_complete(pos) {
+                if let (Some(angle), Some(facing)) = (queue.current_facing.take(), facing.as_mut()) {
+                    facing.0 = angle;
+                }
+                if let Some(next) = queue.advance() {
+                    *order = next;
+                }
+            }
+        }
+
+        // Can't execute orders if pinned, or broken and not running
+        if is_frozen(suppression, morale, Some(&order)) {
             vel.vx = 0.0;
             vel.vy = 0.0;
             continue;
         }
 
-        match order {
-            Order::Hold => {
+        // Head for the next cell of the flow field or waypoint of the route to `goal`
+        let mut steer = |goal: (f32, f32)| {
+            let (tx, ty) = flow
+                .and_then(|(fields, grid)| fields.steer(grid, pos, goal))
+                .unwrap_or_else(|| path.as_mut().map_or(goal, |p| p.steer(pos, goal)));
+            (tx - pos.x, ty - pos.y)
+        };
+
+        match *order {
+            Order::Hold | Order::SuppressArea { .. } | Order::Ungarrison => {
                 vel.vx = 0.0;
                 vel.vy = 0.0;
             }
-            Order::MoveTo { x, y } | Order::AttackMove { x, y } => {
-                let dx = x - pos.x;
-                let dy = y - pos.y;
-                let dist = (dx * dx + dy * dy).sqrt();
-
-                if dist < 1.0 {
+            Order::MoveTo { x, y } | Order::AttackMove { x, y } | Order::Garrison { x, y, .. } => {
+                if (x - pos.x).hypot(y - pos.y) < ARRIVAL_RADIUS {
                     // Arrived at destination
                     vel.vx = 0.0;
                     vel.vy = 0.0;
                 } else {
+                    let (dx, dy) = steer((x, y));
+                    let dist = dx.hypot(dy).max(f32::EPSILON);
                     // Move toward target
-                    let speed = if matches!(order, Order::AttackMove { .. }) {
+                    let speed = if matches!(*order, Order::AttackMove { .. }) {
                         stats.speed * 0.6 // Attack-move is slower
                     } else {
                         stats.speed
                     };
                     vel.vx = (dx / dist) * speed;
                     vel.vy = (dy / dist) * speed;
+                    if let Some(facing) = facing.as_mut() {
+                        facing.0 = dy.atan2(dx);
+                    }
                 }
             }
             Order::Retreat => {
-                // For now, just stop. Later: move away from enemies.
-                vel.vx = 0.0;
-                vel.vy = 0.0;
+                let goal = fallback.map_or((pos.x, pos.y), |f| (f.x, f.y));
+                if (goal.0 - pos.x).hypot(goal.1 - pos.y) < ARRIVAL_RADIUS {
+                    vel.vx = 0.0;
+                    vel.vy = 0.0;
+                } else {
+                    let (dx, dy) = steer(goal);
+                    let dist = dx.hypot(dy).max(f32::EPSILON);
+                    vel.vx = (dx / dist) * stats.speed;
+                    vel.vy = (dy / dist) * stats.speed;
+                    if let Some(facing) = facing.as_mut() {
+                        facing.0 = dy.atan2(dx);
+                    }
+                }
+            }
+            Order::DefendArea { .. } | Order::Patrol { .. } => {
+                let goal = station.map_or((pos.x, pos.y), |s| (s.x, s.y));
+                if (goal.0 - pos.x).hypot(goal.1 - pos.y) < ARRIVAL_RADIUS {
+                    vel.vx = 0.0;
+                    vel.vy = 0.0;
+                } else {
+                    let (dx, dy) = steer(goal);
+                    let dist = dx.hypot(dy).max(f32::EPSILON);
+                    vel.vx = (dx / dist) * stats.speed * 0.6;
+                    vel.vy = (dy / dist) * stats.speed * 0.6;
+                    if let Some(facing) = facing.as_mut() {
+                        facing.0 = dy.atan2(dx);
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,870 @@
+//! Pathfinding over the terrain grid.
+//!
+//! Squads with somewhere to go ask the `Pathfinder` for a route. Routes are
+//! found with A* over `TerrainGrid` cells, where entering a cell costs its
+//! length divided by the terrain's `movement_multiplier`, so squads keep to
+//! roads and go round mud when that's quicker. Water and standing buildings
+//! are impassable; squads only enter a building's footprint when their goal
+//! is inside it (a building they are to garrison).
+//!
+//! Searches are time-sliced: requests wait in a queue and the pathfinder
+//! expands at most `Pathfinder::expansions_per_tick` cells each tick,
+//! carrying an unfinished search over to the next. Counting cells rather
+//! than milliseconds keeps replays deterministic. Until its first route is
+//! found a squad heads straight for its goal, as it did before it had one.
+//!
+//! Goals that drift (formation slots, AI flanking points) don't cost a
+//! search per cell crossed: a squad keeps its route until the goal is
+//! `REROUTE_CELLS` from where the route was found for, and keeps following
+//! it while the new one is searched for. A goal that can't be entered is
+//! routed to the nearest cell that can. When terrain or buildings change,
+//! squads search again, so a route through a building that has since gone
+//! is straightened and a goal that was unreachable is tried again.
+//!
+//! Routes are kept on the squad as a `NavPath` of waypoints, which
+//! `order_system` steers along. Squads sharing their destination with many
+//! others steer by its flow field instead (see `flowfield`).
+
+use crate::components::*;
+use crate::los::BUILDING_FOOTPRINT_FRACTION;
+use crate::systems::defense::Station;
+use crate::systems::flowfield::FlowFields;
+use crate::systems::performance::SimTick;
+use crate::systems::retreat::Fallback;
+use crate::terrain::{TerrainGrid, TerrainResource, TerrainType};
+use bevy_ecs::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::f32::consts::SQRT_2;
+
+/// Cells the pathfinder expands per tick, across all searches.
+pub const DEFAULT_EXPANSIONS_PER_TICK: usize = 20_000;
+
+/// Cells one search may expand before it gives the goal up as unreachable.
+pub const DEFAULT_MAX_SEARCH_EXPANSIONS: usize = 50_000;
+
+/// Fewest ticks between re-routing every squad after terrain changes.
+pub const DEFAULT_REROUTE_INTERVAL: u64 = 30;
+
+/// Cells a squad's goal may drift from the goal its route was found for
+/// before it asks for a new route.
+const REROUTE_CELLS: f32 = 4.0;
+
+/// Cells searched around a goal that can't be entered for one that can.
+const GOAL_SEARCH_RADIUS: i32 = 8;
+
+/// Neighbour offsets and step lengths in cells, orthogonal first.
+const NEIGHBOURS: [(i32, i32, f32); 8] = [
+    (1, 0, 1.0), (-1, 0, 1.0), (0, 1, 1.0), (0, -1, 1.0),
+    (1, 1, SQRT_2), (1, -1, SQRT_2), (-1, 1, SQRT_2), (-1, -1, SQRT_2),
+];
+
+/// Whether a squad's route has been found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathStatus {
+    /// Waiting for the pathfinder.
+    #[default]
+    Pending,
+    /// Route found.
+    Ready,
+    /// No route to the goal; the squad heads straight for it.
+    Unreachable,
+    /// Many squads share the goal; the squad steers by its flow field.
+    FlowField,
+}
+
+/// Route a squad is following to its goal.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct NavPath {
+    /// The goal the route was found for.
+    pub goal: (f32, f32),
+    /// Points to pass through, ending at the goal, or at the nearest cell
+    /// that can be entered if the goal can't.
+    pub waypoints: Vec<(f32, f32)>,
+    /// Index of the waypoint the squad is making for.
+    pub next: usize,
+    pub status: PathStatus,
+    /// A new route has been asked for; the squad keeps to this one until
+    /// it is found.
+    pub searching: bool,
+}
+
+impl NavPath {
+    /// A route to `goal` that hasn't been found yet.
+    pub fn pending(goal: (f32, f32)) -> Self {
+        Self { goal, waypoints: Vec::new(), next: 0, status: PathStatus::Pending, searching: true }
+    }
+
+    /// Steering toward `goal` by its flow field instead of a route.
+    pub fn flow_field(goal: (f32, f32)) -> Self {
+        Self { status: PathStatus::FlowField, searching: false, ..Self::pending(goal) }
+    }
+
+    /// Waypoints the squad has still to pass.
+    pub fn remaining(&self) -> &[(f32, f32)] {
+        self.waypoints.get(self.next..).unwrap_or(&[])
+    }
+
+    /// Point a squad at `pos` should head for on its way to `goal`: the next
+    /// waypoint of the route, or the goal itself while there is no route.
+    /// The route's last leg heads for `goal` even if it has drifted from
+    /// where the route was found for, unless the route stops short of a
+    /// goal that can't be entered. Waypoints within `ARRIVAL_RADIUS` count
+    /// as passed.
+    pub fn steer(&mut self, pos: &Position, goal: (f32, f32)) -> (f32, f32) {
+        if self.status != PathStatus::Ready {
+            return goal;
+        }
+        while let Some(&(x, y)) = self.waypoints.get(self.next) {
+            if self.next + 1 == self.waypoints.len() {
+                return if (x, y) == self.goal { goal } else { (x, y) };
+            }
+            if (x - pos.x).hypot(y - pos.y) >= ARRIVAL_RADIUS {
+                return (x, y);
+            }
+            self.next += 1;
+        }
+        goal
+    }
+}
+
+/// Where a squad is making for under its order: the order's destination,
+/// its `Fallback` point when retreating or its `Station` on standing
+/// orders. `None` if the order doesn't move it.
+pub fn steering_goal(order: &Order, fallback: Option<&Fallback>, station: Option<&Station>) -> Option<(f32, f32)> {
+    match *order {
+        Order::MoveTo { x, y } | Order::AttackMove { x, y } | Order::Garrison { x, y, .. } => Some((x, y)),
+        Order::Retreat => fallback.map(|f| (f.x, f.y)),
+        Order::DefendArea { .. } | Order::Patrol { .. } => station.map(|s| (s.x, s.y)),
+        Order::Hold | Order::SuppressArea { .. } | Order::Ungarrison => None,
+    }
+}
+
+/// Cost of crossing one world unit of the terrain, or `None` if squads
+/// can't cross it.
+pub fn terrain_cost(terrain: TerrainType) -> Option<f32> {
+    terrain.is_passable().then(|| 1.0 / terrain.movement_multiplier())
+}
+
+/// Cheapest cost per world unit of any terrain, for the A* heuristic.
+fn min_terrain_cost() -> f32 {
+    1.0 / TerrainType::Road.movement_multiplier()
+}
+
+/// Index of the grid cell at a world position (clamped to the grid).
+pub fn cell_at(grid: &TerrainGrid, x: f32, y: f32) -> usize {
+    let (gx, gy) = grid.world_to_grid(x, y);
+    gy * grid.width + gx
+}
+
+/// Nearest point to `point` a squad can stand on: the point itself if it
+/// is on the grid and can be entered, else the centre of the nearest cell
+/// that can (see `Passability::nearest_open`). `None` if there is none.
+pub fn nearest_open_point(grid: &TerrainGrid, blocked: &HashSet<usize>, point: (f32, f32)) -> Option<(f32, f32)> {
+    let exempt = HashSet::new();
+    let passability = Passability { grid, blocked, exempt: &exempt };
+    let cell = cell_at(grid, point.0, point.1);
+    match passability.nearest_open(cell)? {
+        open if open == cell && grid.contains(point.0, point.1) => Some(point),
+        open => Some(grid.grid_to_world(open % grid.width, open / grid.width)),
+    }
+}
+
+/// Indices of the cells covered by a circular footprint: those whose
+/// centers lie inside it, plus the cell at its center.
+pub fn footprint_cells(grid: &TerrainGrid, x: f32, y: f32, radius: f32) -> impl Iterator<Item = usize> + '_ {
+    let (min_x, min_y) = grid.world_to_grid(x - radius, y - radius);
+    let (max_x, max_y) = grid.world_to_grid(x + radius, y + radius);
+    let center = cell_at(grid, x, y);
+    (min_y..=max_y)
+        .flat_map(move |gy| (min_x..=max_x).map(move |gx| (gx, gy)))
+        .filter(move |&(gx, gy)| {
+            let (cx, cy) = grid.grid_to_world(gx, gy);
+            gy * grid.width + gx == center || (cx - x).hypot(cy - y) <= radius
+        })
+        .map(|(gx, gy)| gy * grid.width + gx)
+}
+
+/// Footprint radius of a building.
+fn building_footprint(cover: &CoverProvider) -> f32 {
+    cover.radius * BUILDING_FOOTPRINT_FRACTION
+}
+
+/// Components read from buildings, which block movement until destroyed.
+pub type ObstacleData<'a> = (&'a Position, &'a CoverProvider, &'a DestructibleState);
+
+/// Cells covered by standing buildings.
+pub fn blocked_cells<'a>(grid: &TerrainGrid, buildings: impl Iterator<Item = ObstacleData<'a>>) -> HashSet<usize> {
+    buildings
+        .filter(|(.., state)| **state != DestructibleState::Destroyed)
+        .flat_map(|(pos, cover, _)| footprint_cells(grid, pos.x, pos.y, building_footprint(cover)).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Blocked cells a route to `goal` may enter: the footprint of any
+/// building the goal is inside.
+pub fn goal_exemptions<'a>(
+    grid: &TerrainGrid,
+    goal: (f32, f32),
+    buildings: impl Iterator<Item = ObstacleData<'a>>,
+) -> HashSet<usize> {
+    buildings
+        .filter(|(pos, cover, _)| (pos.x - goal.0).hypot(pos.y - goal.1) <= building_footprint(cover))
+        .flat_map(|(pos, cover, _)| footprint_cells(grid, pos.x, pos.y, building_footprint(cover)).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Cells a route may pass through: passable terrain not blocked by a
+/// building, unless the building is exempt.
+pub struct Passability<'a> {
+    pub grid: &'a TerrainGrid,
+    pub blocked: &'a HashSet<usize>,
+    pub exempt: &'a HashSet<usize>,
+}
+
+impl Passability<'_> {
+    /// Cost per world unit of crossing a cell, or `None` if it can't be entered.
+    pub fn cost(&self, cell: usize) -> Option<f32> {
+        if self.blocked.contains(&cell) && !self.exempt.contains(&cell) {
+            return None;
+        }
+        terrain_cost(self.grid.cells[cell].terrain_type)
+    }
+
+    /// Neighbours of a cell that can be stepped into, with the length of
+    /// the step in world units. Diagonal steps may not cut the corner of a
+    /// cell that can't be entered.
+    pub fn moves(&self, cell: usize) -> impl Iterator<Item = (usize, f32)> + '_ {
+        let width = self.grid.width as i32;
+        let height = self.grid.height as i32;
+        let (x, y) = ((cell % self.grid.width) as i32, (cell / self.grid.width) as i32);
+        let index = move |nx: i32, ny: i32| {
+            (nx >= 0 && ny >= 0 && nx < width && ny < height).then(|| (ny * width + nx) as usize)
+        };
+        NEIGHBOURS.iter().filter_map(move |&(dx, dy, len)| {
+            let next = index(x + dx, y + dy)?;
+            if dx != 0 && dy != 0 {
+                self.cost(index(x + dx, y)?)?;
+                self.cost(index(x, y + dy)?)?;
+            }
+            self.cost(next)?;
+            Some((next, len * self.grid.cell_size))
+        })
+    }
+
+    /// Neighbours of a cell that can be stepped into, with the cost of the
+    /// step.
+    pub fn steps(&self, cell: usize) -> impl Iterator<Item = (usize, f32)> + '_ {
+        self.moves(cell).filter_map(|(next, len)| Some((next, len * self.cost(next)?)))
+    }
+
+    /// The cell nearest `cell` that can be entered, no more than
+    /// `GOAL_SEARCH_RADIUS` cells away. Ties go to the lowest index.
+    pub fn nearest_open(&self, cell: usize) -> Option<usize> {
+        let (width, height) = (self.grid.width as i32, self.grid.height as i32);
+        let (cx, cy) = ((cell % self.grid.width) as i32, (cell / self.grid.width) as i32);
+        let mut best: Option<(i32, usize)> = None;
+        for ring in 0..=GOAL_SEARCH_RADIUS {
+            // Cells on later rings are at least `ring` away
+            if best.is_some_and(|(dist_sq, _)| dist_sq < ring * ring) {
+                break;
+            }
+            for dy in -ring..=ring {
+                for dx in -ring..=ring {
+                    let (x, y) = (cx + dx, cy + dy);
+                    if dx.abs().max(dy.abs()) != ring || x < 0 || y < 0 || x >= width || y >= height {
+                        continue;
+                    }
+                    let candidate = (y * width + x) as usize;
+                    let dist_sq = dx * dx + dy * dy;
+                    if self.cost(candidate).is_some() && best.is_none_or(|b| (dist_sq, candidate) < b) {
+                        best = Some((dist_sq, candidate));
+                    }
+                }
+            }
+        }
+        best.map(|(_, cell)| cell)
+    }
+}
+
+/// A cell on the open list, ordered so the cheapest comes out of the heap
+/// first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OpenCell {
+    pub(crate) cell: usize,
+    /// Cost so far plus the heuristic estimate to the goal.
+    pub(crate) estimate: f32,
+    pub(crate) cost: f32,
+}
+
+impl Eq for OpenCell {}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// How far a search got in the cells it was allowed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchStep {
+    /// Out of budget; resume next tick.
+    Working,
+    /// Waypoints from the start to the goal.
+    Found(Vec<(f32, f32)>),
+    /// Every reachable cell was searched without finding the goal, or the
+    /// search reached its expansion limit.
+    Unreachable,
+}
+
+/// An A* search that can be paused and resumed.
+#[derive(Debug)]
+pub struct PathSearch {
+    pub entity: Entity,
+    pub goal: (f32, f32),
+    start_cell: usize,
+    goal_cell: usize,
+    open: BinaryHeap<OpenCell>,
+    /// Cheapest known cost to each reached cell and the cell it was reached
+    /// from.
+    reached: HashMap<usize, (f32, usize)>,
+    /// Blocked cells this search may enter.
+    exempt: HashSet<usize>,
+    /// Cells expanded so far, and how many the search may expand.
+    expanded: usize,
+    limit: usize,
+}
+
+impl PathSearch {
+    /// Start a search for a route from `start` to `goal`.
+    pub fn new(entity: Entity, grid: &TerrainGrid, start: (f32, f32), goal: (f32, f32), exempt: HashSet<usize>) -> Self {
+        let start_cell = cell_at(grid, start.0, start.1);
+        let mut search = Self {
+            entity,
+            goal,
+            start_cell,
+            goal_cell: cell_at(grid, goal.0, goal.1),
+            open: BinaryHeap::new(),
+            reached: HashMap::new(),
+            exempt,
+            expanded: 0,
+            limit: usize::MAX,
+        };
+        search.reached.insert(start_cell, (0.0, start_cell));
+        search.open.push(OpenCell { cell: start_cell, estimate: search.heuristic(grid, start_cell), cost: 0.0 });
+        search
+    }
+
+    /// Give up as unreachable after expanding `limit` cells.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Lower bound on the cost from a cell to the goal: octile distance
+    /// over the fastest terrain.
+    fn heuristic(&self, grid: &TerrainGrid, cell: usize) -> f32 {
+        let dx = (cell % grid.width).abs_diff(self.goal_cell % grid.width) as f32;
+        let dy = (cell / grid.width).abs_diff(self.goal_cell / grid.width) as f32;
+        let cells = dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy);
+        cells * grid.cell_size * min_terrain_cost()
+    }
+
+    /// Expand cells until the goal is found, the reachable cells or the
+    /// search's limit run out, or `budget` is spent. Each expanded cell
+    /// takes one from `budget`.
+    pub fn run(&mut self, grid: &TerrainGrid, blocked: &HashSet<usize>, budget: &mut usize) -> SearchStep {
+        let passability = Passability { grid, blocked, exempt: &self.exempt };
+        while *budget > 0 {
+            let Some(OpenCell { cell, cost, .. }) = self.open.pop() else {
+                return SearchStep::Unreachable;
+            };
+            if cost > self.reached.get(&cell).map_or(f32::INFINITY, |r| r.0) {
+                continue; // Superseded by a cheaper route to the cell
+            }
+            if cell == self.goal_cell {
+                return SearchStep::Found(self.waypoints(grid));
+            }
+            if self.expanded >= self.limit {
+                return SearchStep::Unreachable;
+            }
+            self.expanded += 1;
+            *budget -= 1;
+            for (next, step) in passability.steps(cell) {
+                let next_cost = cost + step;
+                if next_cost < self.reached.get(&next).map_or(f32::INFINITY, |r| r.0) {
+                    self.reached.insert(next, (next_cost, cell));
+                    let estimate = next_cost + self.heuristic(grid, next);
+                    self.open.push(OpenCell { cell: next, estimate, cost: next_cost });
+                }
+            }
+        }
+        SearchStep::Working
+    }
+
+    /// Waypoints along the route found to the goal cell: the cells where
+    /// the route turns, ending at the goal itself.
+    fn waypoints(&self, grid: &TerrainGrid) -> Vec<(f32, f32)> {
+        let mut cells = vec![self.goal_cell];
+        let mut cell = self.goal_cell;
+        while cell != self.start_cell {
+            cell = self.reached[&cell].1;
+            cells.push(cell);
+        }
+        cells.reverse();
+
+        let direction = |a: usize, b: usize| {
+            (b as i64 % grid.width as i64 - a as i64 % grid.width as i64, b as i64 / grid.width as i64 - a as i64 / grid.width as i64)
+        };
+        let mut waypoints: Vec<(f32, f32)> = cells.windows(3)
+            .filter(|w| direction(w[0], w[1]) != direction(w[1], w[2]))
+            .map(|w| grid.grid_to_world(w[1] % grid.width, w[1] / grid.width))
+            .collect();
+        waypoints.push(self.goal);
+        waypoints
+    }
+}
+
+/// Queue of route requests and the search in progress.
+#[derive(Resource, Debug)]
+pub struct Pathfinder {
+    /// Cells expanded per tick, across all searches.
+    pub expansions_per_tick: usize,
+    /// Cells one search may expand before giving up.
+    pub max_search_expansions: usize,
+    /// Fewest ticks between re-routing every squad after terrain changes.
+    pub reroute_interval: u64,
+    /// Squads waiting for a route. Each searches for its goal at the time
+    /// its turn comes.
+    queue: VecDeque<Entity>,
+    /// The search in progress and the goal the squad asked for.
+    active: Option<(PathSearch, (f32, f32))>,
+    /// `FlowFields::terrain_version` when squads were last re-routed.
+    seen_version: u64,
+    /// Tick squads were last re-routed after a terrain change.
+    rerouted_at: Option<u64>,
+}
+
+impl Default for Pathfinder {
+    fn default() -> Self {
+        Self {
+            expansions_per_tick: DEFAULT_EXPANSIONS_PER_TICK,
+            max_search_expansions: DEFAULT_MAX_SEARCH_EXPANSIONS,
+            reroute_interval: DEFAULT_REROUTE_INTERVAL,
+            queue: VecDeque::new(),
+            active: None,
+            seen_version: 0,
+            rerouted_at: None,
+        }
+    }
+}
+
+impl Pathfinder {
+    /// Number of routes waiting for or being searched.
+    pub fn pending(&self) -> usize {
+        self.queue.len() + usize::from(self.active.is_some())
+    }
+}
+
+/// Components read and written by `pathfinding_system` for squads.
+type PathSquadData<'a> = (
+    Entity,
+    &'a Position,
+    &'a Order,
+    Option<&'a Fallback>,
+    Option<&'a Station>,
+    Option<&'a mut NavPath>,
+);
+
+/// The goal a squad that asked for a route is now making for, if it still
+/// wants a route: it is still going somewhere, and its goal hasn't since
+/// got a flow field.
+fn wants_route(
+    squads: &Query<PathSquadData, Without<Surrendered>>,
+    flow_fields: Option<&FlowFields>,
+    grid: &TerrainGrid,
+    entity: Entity,
+) -> Option<(f32, f32)> {
+    let (_, _, order, fallback, station, path) = squads.get(entity).ok()?;
+    // A squad without a `NavPath` asked this tick; it is still being inserted
+    if path.is_some_and(|p| !p.searching) {
+        return None;
+    }
+    let goal = steering_goal(order, fallback, station)?;
+    (!flow_fields.is_some_and(|f| f.covers(cell_at(grid, goal.0, goal.1)))).then_some(goal)
+}
+
+/// System that finds routes for squads whose goal has moved.
+///
+/// ## Data Access
+/// - Reads: SimTick, TerrainResource, FlowFields, Order, Fallback, Station, Position (squads and buildings), CoverProvider, DestructibleState
+/// - Writes: Pathfinder, NavPath
+///
+/// A squad asks for a route when it gets a goal or its goal drifts
+/// `REROUTE_CELLS` from the one its route was found for, and drops its
+/// route when its order no longer moves it. When terrain or buildings
+/// change (`FlowFields::terrain_version`), every squad with a route, or
+/// whose goal was unreachable, asks again, at most once every
+/// `Pathfinder::reroute_interval` ticks. Requests are served in the
+/// order they were made, one per squad at a time. Squads whose goal has a
+/// flow field (`FlowFields::covers`) steer by that instead.
+pub fn pathfinding_system(
+    mut commands: Commands,
+    tick: Option<Res<SimTick>>,
+    pathfinder: Option<ResMut<Pathfinder>>,
+    flow_fields: Option<Res<FlowFields>>,
+    terrain: Option<Res<TerrainResource>>,
+    buildings: Query<ObstacleData, With<Building>>,
+    mut squads: Query<PathSquadData, Without<Surrendered>>,
+) {
+    let (Some(mut pathfinder), Some(terrain)) = (pathfinder, terrain) else { return };
+    let Some(grid) = terrain.read() else { return };
+    let flow_fields = flow_fields.as_deref();
+
+    let reroute_distance = REROUTE_CELLS * grid.cell_size;
+    // Routes found before a terrain change may now be blocked, or a goal
+    // once unreachable open
+    let now = tick.map_or(0, |t| t.0);
+    let version = flow_fields.map_or(pathfinder.seen_version, |f| f.terrain_version());
+    let reroute_all = version != pathfinder.seen_version
+        && pathfinder.rerouted_at.is_none_or(|at| now >= at + pathfinder.reroute_interval);
+    if reroute_all {
+        pathfinder.seen_version = version;
+        pathfinder.rerouted_at = Some(now);
+    }
+    for (entity, _, order, fallback, station, path) in squads.iter_mut() {
+        let Some(goal) = steering_goal(order, fallback, station) else {
+            if path.is_some() {
+                commands.entity(entity).remove::<NavPath>();
+            }
+            continue;
+        };
+        let by_field = flow_fields.is_some_and(|f| f.covers(cell_at(&grid, goal.0, goal.1)));
+        match path {
+            Some(mut path) if by_field => {
+                if path.status != PathStatus::FlowField {
+                    *path = NavPath::flow_field(goal);
+                } else if path.goal != goal {
+                    path.goal = goal;
+                }
+            }
+            Some(mut path) if path.status == PathStatus::FlowField => {
+                *path = NavPath::pending(goal);
+                pathfinder.queue.push_back(entity);
+            }
+            Some(mut path) => {
+                // Keep the route while the goal drifts near it and the
+                // terrain stays as it was, or while a new one is on its way
+                let drift = (goal.0 - path.goal.0).hypot(goal.1 - path.goal.1);
+                if (drift > reroute_distance || reroute_all) && !path.searching {
+                    path.searching = true;
+                    pathfinder.queue.push_back(entity);
+                }
+            }
+            None if by_field => {
+                commands.entity(entity).insert(NavPath::flow_field(goal));
+            }
+            None => {
+                commands.entity(entity).insert(NavPath::pending(goal));
+                pathfinder.queue.push_back(entity);
+            }
+        }
+    }
+
+    if pathfinder.pending() == 0 {
+        return;
+    }
+    let blocked = blocked_cells(&grid, buildings.iter());
+    let mut budget = pathfinder.expansions_per_tick;
+    while budget > 0 {
+        let (mut search, goal) = match pathfinder.active.take() {
+            Some((search, goal)) if wants_route(&squads, flow_fields, &grid, search.entity).is_some() => (search, goal),
+            Some(_) => continue,
+            None => {
+                let Some(entity) = pathfinder.queue.pop_front() else { break };
+                let Some(goal) = wants_route(&squads, flow_fields, &grid, entity) else { continue };
+                let Ok((_, pos, ..)) = squads.get(entity) else { continue };
+                let exempt = goal_exemptions(&grid, goal, buildings.iter());
+                let passability = Passability { grid: &grid, blocked: &blocked, exempt: &exempt };
+                let goal_cell = cell_at(&grid, goal.0, goal.1);
+                // Route a goal that can't be entered to the nearest cell that can
+                let target = match passability.nearest_open(goal_cell) {
+                    Some(cell) if cell == goal_cell => goal,
+                    Some(cell) => grid.grid_to_world(cell % grid.width, cell / grid.width),
+                    None => {
+                        let route = NavPath { status: PathStatus::Unreachable, searching: false, ..NavPath::pending(goal) };
+                        set_route(&mut commands, &mut squads, entity, route);
+                        continue;
+                    }
+                };
+                let search = PathSearch::new(entity, &grid, (pos.x, pos.y), target, exempt)
+                    .with_limit(pathfinder.max_search_expansions);
+                (search, goal)
+            }
+        };
+        let (waypoints, status) = match search.run(&grid, &blocked, &mut budget) {
+            SearchStep::Working => {
+                pathfinder.active = Some((search, goal));
+                break;
+            }
+            SearchStep::Found(waypoints) => (waypoints, PathStatus::Ready),
+            SearchStep::Unreachable => (Vec::new(), PathStatus::Unreachable),
+        };
+        let route = NavPath { goal, waypoints, next: 0, status, searching: false };
+        set_route(&mut commands, &mut squads, search.entity, route);
+    }
+}
+
+/// Give a squad the route found for it.
+fn set_route(
+    commands: &mut Commands,
+    squads: &mut Query<PathSquadData, Without<Surrendered>>,
+    entity: Entity,
+    route: NavPath,
+) {
+    match squads.get_mut(entity) {
+        Ok((.., Some(mut path))) => *path = route,
+        Ok((.., None)) => {
+            commands.entity(entity).insert(route);
+        }
+        Err(_) => {}
+    }
+}
+
+/// 20x20 open grid of 2-unit cells centered on the origin, with a river
+/// along x = 0 that has one ford at the top. Shared by the routing tests
+/// here and in `flowfield`.
+#[cfg(test)]
+pub(crate) fn river_grid() -> TerrainGrid {
+    let mut grid = TerrainGrid::new(20, 20, 2.0);
+    for gy in 0..18 {
+        grid.get_cell_mut(10, gy).unwrap().terrain_type = TerrainType::Water;
+    }
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(grid: &TerrainGrid, start: (f32, f32), goal: (f32, f32), budget: usize) -> SearchStep {
+        let mut search = PathSearch::new(Entity::PLACEHOLDER, grid, start, goal, HashSet::new());
+        let mut budget = budget;
+        search.run(grid, &HashSet::new(), &mut budget)
+    }
+
+    #[test]
+    fn test_route_goes_round_water() {
+        let grid = river_grid();
+        let SearchStep::Found(waypoints) = find(&grid, (-10.0, -10.0), (10.0, -10.0), usize::MAX) else {
+            panic!("no route across the river");
+        };
+        assert_eq!(waypoints.last(), Some(&(10.0, -10.0)));
+        for &(x, y) in &waypoints {
+            assert!(grid.get_terrain_at(x, y).terrain_type.is_passable(), "waypoint ({x}, {y}) in water");
+        }
+        assert!(waypoints.iter().any(|&(_, y)| y > 15.0), "route should use the ford: {waypoints:?}");
+
+        let mut flooded = grid.clone();
+        flooded.get_cell_mut(10, 18).unwrap().terrain_type = TerrainType::Water;
+        flooded.get_cell_mut(10, 19).unwrap().terrain_type = TerrainType::Water;
+        assert_eq!(find(&flooded, (-10.0, -10.0), (10.0, -10.0), usize::MAX), SearchStep::Unreachable);
+    }
+
+    #[test]
+    fn test_search_resumes_across_ticks() {
+        let grid = river_grid();
+        let mut search = PathSearch::new(Entity::PLACEHOLDER, &grid, (-10.0, -10.0), (10.0, -10.0), HashSet::new());
+        let mut ticks = 0;
+        loop {
+            ticks += 1;
+            match search.run(&grid, &HashSet::new(), &mut 20) {
+                SearchStep::Working => continue,
+                step => {
+                    assert_eq!(step, find(&grid, (-10.0, -10.0), (10.0, -10.0), usize::MAX));
+                    break;
+                }
+            }
+        }
+        assert!(ticks > 1, "search should have been spread over several ticks");
+    }
+
+    #[test]
+    fn test_buildings_block_unless_they_are_the_goal() {
+        let grid = TerrainGrid::new(20, 20, 2.0);
+        let building = (Position::new(0.0, 0.0), CoverProvider::building(), DestructibleState::Intact);
+        let blocked = blocked_cells(&grid, std::iter::once((&building.0, &building.1, &building.2)));
+        assert!(blocked.contains(&cell_at(&grid, 0.0, 0.0)));
+
+        let mut budget = usize::MAX;
+        let mut through = PathSearch::new(Entity::PLACEHOLDER, &grid, (-8.0, 0.0), (8.0, 0.0), HashSet::new());
+        let SearchStep::Found(waypoints) = through.run(&grid, &blocked, &mut budget) else {
+            panic!("no route past the building");
+        };
+        assert!(waypoints.len() > 1, "route should bend round the building");
+
+        let exempt = goal_exemptions(&grid, (0.0, 0.0), std::iter::once((&building.0, &building.1, &building.2)));
+        let mut into = PathSearch::new(Entity::PLACEHOLDER, &grid, (-8.0, 0.0), (0.0, 0.0), exempt);
+        assert!(matches!(into.run(&grid, &blocked, &mut budget), SearchStep::Found(_)));
+    }
+
+    #[test]
+    fn test_squad_follows_its_route() {
+        let mut world = World::new();
+        world.insert_resource(TerrainResource::new(river_grid()));
+        world.insert_resource(Pathfinder::default());
+        let squad = world.spawn((Position::new(-10.0, -10.0), Order::MoveTo { x: 10.0, y: -10.0 })).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(pathfinding_system);
+        schedule.run(&mut world);
+
+        let mut path = world.get::<NavPath>(squad).unwrap().clone();
+        assert_eq!(path.status, PathStatus::Ready);
+        let first = path.steer(&Position::new(-10.0, -10.0), (10.0, -10.0));
+        assert_eq!(first, path.waypoints[0]);
+        assert_ne!(first, (10.0, -10.0), "should not head straight across the river");
+
+        *world.get_mut::<Order>(squad).unwrap() = Order::Hold;
+        schedule.run(&mut world);
+        assert!(world.get::<NavPath>(squad).is_none());
+    }
+
+    #[test]
+    fn test_route_kept_while_goal_drifts_or_new_route_is_searched() {
+        let mut world = World::new();
+        world.insert_resource(TerrainResource::new(river_grid()));
+        world.insert_resource(Pathfinder::default());
+        let squad = world.spawn((Position::new(-10.0, -10.0), Order::MoveTo { x: 10.0, y: -10.0 })).id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(pathfinding_system);
+        schedule.run(&mut world);
+        let route = world.get::<NavPath>(squad).unwrap().clone();
+        assert_eq!(route.status, PathStatus::Ready);
+
+        // Drifting a couple of cells: same route, heading for the new goal
+        // at its end, and no new search
+        world.resource_mut::<Pathfinder>().expansions_per_tick = 0;
+        *world.get_mut::<Order>(squad).unwrap() = Order::MoveTo { x: 12.0, y: -8.0 };
+        schedule.run(&mut world);
+        let mut path = world.get::<NavPath>(squad).unwrap().clone();
+        assert_eq!(path, route);
+        assert_eq!(world.resource::<Pathfinder>().pending(), 0);
+        let mut end = path.clone();
+        end.next = end.waypoints.len() - 1;
+        assert_eq!(end.steer(&Position::new(10.0, -10.0), (12.0, -8.0)), (12.0, -8.0));
+
+        // Far off: one request, and the old route is followed meanwhile
+        for goal in [(-10.0, 10.0), (-12.0, 12.0), (-14.0, 14.0)] {
+            *world.get_mut::<Order>(squad).unwrap() = Order::MoveTo { x: goal.0, y: goal.1 };
+            schedule.run(&mut world);
+        }
+        assert_eq!(world.resource::<Pathfinder>().pending(), 1);
+        path = world.get::<NavPath>(squad).unwrap().clone();
+        assert!(path.searching);
+        assert_eq!(path.steer(&Position::new(-10.0, -10.0), (-14.0, 14.0)), route.waypoints[0]);
+
+        world.resource_mut::<Pathfinder>().expansions_per_tick = DEFAULT_EXPANSIONS_PER_TICK;
+        schedule.run(&mut world);
+        let path = world.get::<NavPath>(squad).unwrap();
+        assert_eq!((path.status, path.goal, path.searching), (PathStatus::Ready, (-14.0, 14.0), false));
+    }
+
+    #[test]
+    fn test_goal_that_cant_be_entered() {
+        let mut world = World::new();
+        let mut grid = river_grid();
+        // A lake far wider than the search for an open cell
+        for gy in 0..20 {
+            for gx in 0..9 {
+                grid.get_cell_mut(gx, gy).unwrap().terrain_type = TerrainType::Water;
+            }
+        }
+        world.insert_resource(TerrainResource::new(grid));
+        world.insert_resource(Pathfinder::default());
+        // Goal mid-lake: unreachable without searching, so the wader's
+        // search still gets this tick's cell
+        let swimmer = world.spawn((Position::new(15.0, 10.0), Order::MoveTo { x: -19.0, y: 0.0 })).id();
+        // Goal in the river: routed to the bank
+        let wader = world.spawn((Position::new(15.0, -10.0), Order::MoveTo { x: 1.0, y: -10.0 })).id();
+        world.resource_mut::<Pathfinder>().expansions_per_tick = 1;
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(pathfinding_system);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<NavPath>(swimmer).unwrap().status, PathStatus::Unreachable);
+        assert_eq!(world.resource::<Pathfinder>().pending(), 1, "only the wader's search is left");
+
+        world.resource_mut::<Pathfinder>().expansions_per_tick = DEFAULT_EXPANSIONS_PER_TICK;
+        schedule.run(&mut world);
+        let mut path = world.get::<NavPath>(wader).unwrap().clone();
+        assert_eq!(path.status, PathStatus::Ready);
+        let &(x, y) = path.waypoints.last().unwrap();
+        assert!(world.resource::<TerrainResource>().read().unwrap().get_terrain_at(x, y).terrain_type.is_passable());
+        assert!((x - 1.0).hypot(y + 10.0) <= 2.0 * SQRT_2, "({x}, {y}) is not next to the goal");
+        path.next = path.waypoints.len() - 1;
+        assert_eq!(path.steer(&Position::new(x, y), (1.0, -10.0)), (x, y), "should stop on the bank");
+    }
+
+    #[test]
+    fn test_routes_found_again_when_terrain_changes() {
+        use crate::systems::flowfield::flow_field_system;
+
+        let mut world = World::new();
+        let mut grid = river_grid();
+        grid.get_cell_mut(10, 18).unwrap().terrain_type = TerrainType::Water;
+        grid.get_cell_mut(10, 19).unwrap().terrain_type = TerrainType::Water;
+        world.insert_resource(TerrainResource::new(grid));
+        world.insert_resource(Pathfinder::default());
+        world.insert_resource(FlowFields::default());
+        world.insert_resource(SimTick(0));
+        let house = world.spawn((Building, Position::new(-10.0, 0.0), CoverProvider::building(), DestructibleState::Intact)).id();
+        let walker = world.spawn((Position::new(-18.0, 0.0), Order::MoveTo { x: -2.0, y: 0.0 })).id();
+        let swimmer = world.spawn((Position::new(-10.0, -10.0), Order::MoveTo { x: 10.0, y: -10.0 })).id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems((flow_field_system, pathfinding_system).chain());
+        let mut run = |world: &mut World| {
+            schedule.run(world);
+            world.resource_mut::<SimTick>().0 += 1;
+        };
+        run(&mut world);
+        let bent = world.get::<NavPath>(walker).unwrap().clone();
+        assert_eq!(bent.status, PathStatus::Ready);
+        assert!(bent.waypoints.len() > 1, "route should bend round the house");
+        assert_eq!(world.get::<NavPath>(swimmer).unwrap().status, PathStatus::Unreachable);
+
+        // The house comes down and the ford is drained, within the reroute
+        // interval of the first routes: nothing is searched again yet
+        *world.get_mut::<DestructibleState>(house).unwrap() = DestructibleState::Destroyed;
+        world.resource::<TerrainResource>().0.write().unwrap()
+            .get_cell_mut(10, 19).unwrap().terrain_type = TerrainType::Open;
+        world.resource_mut::<FlowFields>().invalidate();
+        run(&mut world);
+        assert_eq!(world.get::<NavPath>(walker).unwrap(), &bent);
+
+        while world.resource::<SimTick>().0 <= DEFAULT_REROUTE_INTERVAL {
+            run(&mut world);
+        }
+        let straight = world.get::<NavPath>(walker).unwrap();
+        assert_eq!((straight.status, straight.waypoints.len()), (PathStatus::Ready, 1), "{straight:?}");
+        let path = world.get::<NavPath>(swimmer).unwrap();
+        assert_eq!(path.status, PathStatus::Ready);
+        assert!(path.waypoints.iter().any(|&(_, y)| y > 15.0), "route should use the ford: {path:?}");
+    }
+
+    #[test]
+    fn test_search_gives_up_at_its_limit() {
+        let grid = river_grid();
+        let mut search = PathSearch::new(Entity::PLACEHOLDER, &grid, (-10.0, -10.0), (10.0, -10.0), HashSet::new())
+            .with_limit(10);
+        let mut budget = usize::MAX;
+        assert_eq!(search.run(&grid, &HashSet::new(), &mut budget), SearchStep::Unreachable);
+        assert_eq!(usize::MAX - budget, 10);
+    }
+}
@@ -17,6 +17,7 @@
 //! - `sector_assignment_system`: Read-only Position, writes SectorId. Can run in parallel with other read-only systems.
 
 use crate::components::*;
+use crate::systems::explosion::DispersionConfig;
 use bevy_ecs::prelude::*;
 
 // ============================================================================
@@ -135,6 +136,8 @@ pub struct SimConfig {
     pub lod_reference_point: (f32, f32),
     /// Soft limits for unit counts.
     pub limits: SimLimits,
+    /// Dispersion model for indirect fire.
+    pub dispersion: DispersionConfig,
 }
 
 impl Default for SimConfig {
@@ -149,6 +152,7 @@ impl Default for SimConfig {
             damage_memory_ticks: 60,     // ~2 seconds at 30 Hz
             lod_reference_point: (0.0, 0.0), // Center of battlefield
             limits: SimLimits::default(),
+            dispersion: DispersionConfig::default(),
         }
     }
 }
@@ -293,7 +297,7 @@ pub struct SectorCombatStats {
 }
 
 /// Resource holding aggregated sector combat data.
-/// Rebuilt each tick by the sector aggregation system.
+/// Rebuilt on each low-LOD interval by `sector_combat_system`.
 #[derive(Resource, Debug, Default)]
 pub struct SectorCombatData {
     /// Map from (sector_x, sector_y, faction) to combat stats.
@@ -0,0 +1,303 @@
+//! Falling back to rally points and friendly lines.
+//!
+//! A squad with `Order::Retreat` is given a `Fallback` point. It is the
+//! nearest of its faction's `RallyPoint`s, or failing that the nearest
+//! friendly squad that is further from the enemy. A point is skipped if the
+//! way there passes close to an enemy. With neither available, the squad
+//! falls back straight away from the nearest enemy. On a map, the point is
+//! moved onto the nearest ground the squad can stand on.
+//!
+//! Retreating squads run even while broken. Once at the fallback point and
+//! out of fire, they recover morale quickly. When rallied, a squad takes up
+//! the order it had before it routed (`ResumeOrder`), or holds there.
+
+use crate::components::*;
+use crate::systems::formation::FormationMember;
+use crate::systems::movement::DeltaTime;
+use crate::systems::pathfinding::{blocked_cells, nearest_open_point, ObstacleData};
+use crate::terrain::TerrainResource;
+use bevy_ecs::prelude::*;
+
+/// Distance from the fallback point at which a squad can rally.
+pub const RALLY_RADIUS: f32 = 15.0;
+
+/// Morale recovered per second at the fallback point while not suppressed.
+const RALLY_RECOVERY_RATE: f32 = 0.05;
+
+/// Morale at which a retreating squad has rallied.
+pub const RALLIED_MORALE: f32 = 0.5;
+
+/// Distance to fall back when there's nowhere to fall back to.
+const RETREAT_DISTANCE: f32 = 50.0;
+
+/// Enemies further than this from a retreating squad are ignored.
+const THREAT_RANGE: f32 = 150.0;
+
+/// A route passing within this distance of an enemy is unsafe.
+const DANGER_RADIUS: f32 = 30.0;
+
+/// Friendly squads closer than this aren't worth falling back to.
+const MIN_FALLBACK_DISTANCE: f32 = 20.0;
+
+/// A point where a faction's retreating squads gather. Spawned with a
+/// `Position`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RallyPoint {
+    pub id: u32,
+    pub faction: Faction,
+}
+
+/// Where a retreating squad is falling back to.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Fallback {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Order a routed squad takes up again once it has rallied.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ResumeOrder(pub Order);
+
+/// Distance from `point` to the segment `from`-`to`.
+fn distance_to_segment(point: (f32, f32), from: (f32, f32), to: (f32, f32)) -> f32 {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0.0 {
+        (((point.0 - from.0) * dx + (point.1 - from.1) * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (from.0 + t * dx - point.0).hypot(from.1 + t * dy - point.1)
+}
+
+/// Where a squad at `pos` should fall back to. `enemies` are nearby
+/// enemy positions.
+fn fallback_point(
+    pos: (f32, f32),
+    rally_points: &[(f32, f32)],
+    friendlies: &[(f32, f32)],
+    enemies: &[(f32, f32)],
+) -> (f32, f32) {
+    let dist = |p: &(f32, f32)| (p.0 - pos.0).hypot(p.1 - pos.1);
+    let safe = |p: &&(f32, f32)| enemies.iter().all(|&e| distance_to_segment(e, pos, **p) > DANGER_RADIUS);
+    let nearest_enemy = enemies.iter().copied().min_by(|a, b| dist(a).total_cmp(&dist(b)));
+    let nearest = |points: &mut dyn Iterator<Item = &(f32, f32)>| {
+        points.min_by(|a, b| dist(a).total_cmp(&dist(b))).copied()
+    };
+
+    if let Some(rally) = nearest(&mut rally_points.iter().filter(safe)) {
+        return rally;
+    }
+
+    // Friendly squads behind us, relative to the nearest enemy
+    let threat_dist = |p: &(f32, f32)| nearest_enemy.map_or(0.0, |e| (p.0 - e.0).hypot(p.1 - e.1));
+    let own_threat_dist = threat_dist(&pos);
+    let mut lines = friendlies.iter()
+        .filter(|p| dist(p) >= MIN_FALLBACK_DISTANCE && threat_dist(p) > own_threat_dist)
+        .filter(safe);
+    if let Some(line) = nearest(&mut lines) {
+        return line;
+    }
+
+    match nearest_enemy {
+        Some(enemy) => {
+            let (dx, dy) = (pos.0 - enemy.0, pos.1 - enemy.1);
+            let d = dx.hypot(dy).max(0.1);
+            (pos.0 + dx / d * RETREAT_DISTANCE, pos.1 + dy / d * RETREAT_DISTANCE)
+        }
+        None => pos,
+    }
+}
+
+/// Components read and written by `retreat_system` for squads.
+type RetreatSquadData<'a> = (
+    Entity,
+    &'a Faction,
+    &'a Position,
+    &'a Health,
+    &'a Suppression,
+    &'a mut Morale,
+    &'a mut Order,
+    Option<&'a Fallback>,
+    Option<&'a ResumeOrder>,
+    Has<FormationMember>,
+);
+
+/// System that picks fallback points for retreating squads and rallies
+/// them there.
+///
+/// ## Data Access
+/// - Reads: DeltaTime, TerrainResource, RallyPoint, Building, Faction, Position, Health, Suppression
+/// - Writes: Morale, Order, Fallback, ResumeOrder, FormationMember (removes)
+///
+/// The fallback point is picked once, when the retreat starts.
+pub fn retreat_system(
+    mut commands: Commands,
+    dt: Res<DeltaTime>,
+    terrain: Option<Res<TerrainResource>>,
+    rally_points: Query<(&RallyPoint, &Position)>,
+    buildings: Query<ObstacleData, With<Building>>,
+    mut squads: Query<RetreatSquadData, (With<SquadId>, Without<Surrendered>)>,
+) {
+    // (faction, position, retreating) of every living squad
+    let units: Vec<(Faction, (f32, f32), bool)> = squads.iter()
+        .filter(|(_, _, _, health, ..)| health.is_alive())
+        .map(|(_, faction, pos, _, _, _, order, ..)| (*faction, (pos.x, pos.y), matches!(*order, Order::Retreat)))
+        .collect();
+
+    for (entity, faction, pos, health, suppression, mut morale, mut order, fallback, resume, in_formation) in squads.iter_mut() {
+        if !health.is_alive() {
+            continue;
+        }
+        if !matches!(*order, Order::Retreat) {
+            if fallback.is_some() || resume.is_some() {
+                commands.entity(entity).remove::<(Fallback, ResumeOrder)>();
+            }
+            continue;
+        }
+        if in_formation {
+            commands.entity(entity).remove::<FormationMember>();
+        }
+
+        let Some(fallback) = fallback else {
+            let here = (pos.x, pos.y);
+            let in_range = |p: &(f32, f32)| (p.0 - here.0).hypot(p.1 - here.1) <= THREAT_RANGE;
+            let rallies: Vec<(f32, f32)> = rally_points.iter()
+                .filter(|(rally, _)| rally.faction == *faction)
+                .map(|(_, p)| (p.x, p.y))
+                .collect();
+            let friendlies: Vec<(f32, f32)> = units.iter()
+                .filter(|(f, _, retreating)| f == faction && !retreating)
+                .map(|(_, p, _)| *p)
+                .collect();
+            let enemies: Vec<(f32, f32)> = units.iter()
+                .filter(|(f, p, _)| f != faction && in_range(p))
+                .map(|(_, p, _)| *p)
+                .collect();
+            let mut point = fallback_point(here, &rallies, &friendlies, &enemies);
+            if let Some(grid) = terrain.as_ref().and_then(|t| t.read()) {
+                let blocked = blocked_cells(&grid, buildings.iter());
+                point = nearest_open_point(&grid, &blocked, point).unwrap_or(here);
+            }
+            let (x, y) = point;
+            commands.entity(entity).insert(Fallback { x, y });
+            continue;
+        };
+
+        if (fallback.x - pos.x).hypot(fallback.y - pos.y) > RALLY_RADIUS {
+            continue;
+        }
+        if !suppression.is_suppressed() {
+            morale.recover(RALLY_RECOVERY_RATE * dt.0);
+        }
+        if morale.value >= RALLIED_MORALE {
+            *order = resume.map(|r| r.0).unwrap_or(Order::Hold);
+            commands.entity(entity).remove::<(Fallback, ResumeOrder)>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_avoids_rally_points_past_the_enemy() {
+        let enemy = [(50.0, 0.0)];
+        // The nearer rally point is on the far side of the enemy
+        let rallies = [(80.0, 0.0), (-120.0, 0.0)];
+        assert_eq!(fallback_point((0.0, 0.0), &rallies, &[], &enemy), (-120.0, 0.0));
+
+        // No safe rally point: fall back on a friendly squad behind us
+        let friendlies = [(60.0, 10.0), (-40.0, 5.0)];
+        assert_eq!(fallback_point((0.0, 0.0), &rallies[..1], &friendlies, &enemy), (-40.0, 5.0));
+
+        // Nowhere to go: straight away from the enemy
+        let (x, y) = fallback_point((0.0, 0.0), &[], &[], &enemy);
+        assert!((x + RETREAT_DISTANCE).abs() < 1e-3 && y.abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_rout_at_map_edge_falls_back_onto_open_ground() {
+        use crate::terrain::{TerrainGrid, TerrainType};
+
+        // 100 x 100 map with a river along its western edge
+        let mut grid = TerrainGrid::new(50, 50, 2.0);
+        for gy in 0..50 {
+            for gx in 0..3 {
+                grid.cells[gy * 50 + gx].terrain_type = TerrainType::Water;
+            }
+        }
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(1.0));
+        world.insert_resource(TerrainResource::new(grid.clone()));
+        let squad = world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(-40.0, 0.0),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::new(0.1),
+            Order::Retreat,
+        )).id();
+        world.spawn((
+            SquadId(2),
+            Faction::Red,
+            Position::new(0.0, 0.0),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::default(),
+            Order::Hold,
+        ));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(retreat_system);
+        schedule.run(&mut world);
+
+        let fallback = *world.get::<Fallback>(squad).unwrap();
+        assert!(grid.contains(fallback.x, fallback.y), "fell back off the map: {:?}", fallback);
+        assert!(grid.get_terrain_at(fallback.x, fallback.y).terrain_type.is_passable());
+
+        // Standing there, the squad rallies
+        world.get_mut::<Position>(squad).unwrap().x = fallback.x;
+        world.get_mut::<Position>(squad).unwrap().y = fallback.y;
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+        assert!(matches!(world.get::<Order>(squad).unwrap(), Order::Hold));
+    }
+
+    #[test]
+    fn test_squad_rallies_at_fallback_and_resumes_order() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(1.0));
+        world.spawn((RallyPoint { id: 1, faction: Faction::Blue }, Position::new(5.0, 0.0)));
+        let squad = world.spawn((
+            SquadId(1),
+            Faction::Blue,
+            Position::new(0.0, 0.0),
+            Health::new(100.0),
+            Suppression::default(),
+            Morale::new(0.1),
+            Order::Retreat,
+            ResumeOrder(Order::AttackMove { x: 100.0, y: 0.0 }),
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(retreat_system);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Fallback>(squad), Some(&Fallback { x: 5.0, y: 0.0 }));
+
+        for _ in 0..5 {
+            schedule.run(&mut world);
+        }
+        assert!(matches!(world.get::<Order>(squad).unwrap(), Order::Retreat), "still rallying");
+
+        for _ in 0..5 {
+            schedule.run(&mut world);
+        }
+        assert!(matches!(world.get::<Order>(squad).unwrap(), Order::AttackMove { x, .. } if *x == 100.0));
+        assert!(world.get::<Fallback>(squad).is_none());
+        assert!(world.get::<ResumeOrder>(squad).is_none());
+    }
+}
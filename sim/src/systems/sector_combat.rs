@@ -0,0 +1,432 @@
+//! Sector-batched aggregate combat for low-LOD squads.
+//!
+//! Distant fights don't need per-attacker target selection. Low-LOD squads
+//! are pooled per (sector, faction); each pool fires at the enemy pools in
+//! range with its combined firepower, and incoming fire is spread over the
+//! pool's squads. This is the Lanchester square law: a pool's losses grow
+//! with the enemy's firepower, and its own firepower shrinks as it loses
+//! strength.
+//!
+//! High- and Medium-LOD squads keep individual targeting in
+//! `combat_gather_system`. A low-LOD squad facing one of them fires at it
+//! individually too, and adds no firepower to its pool on those ticks. So
+//! does a squad on `FireStance::EngageTarget`, which only fires at its
+//! target.
+//! Aggregate fire ignores line of sight; cover at each squad's position
+//! still reduces what it takes.
+//!
+//! Pool fire is attributed like individual fire: each firing squad gets a
+//! `FireRecord` per squad hit, weighted by its share of its pool's
+//! firepower, in `PendingCombatResults`. Kills go to the top damage dealer.
+//!
+//! ## Complexity
+//!
+//! O(n + p × k) where n = low-LOD squads, p = pools and k = sectors within
+//! fire range, instead of a spatial query per squad.
+
+use crate::components::*;
+use crate::systems::combat::{
+    CombatResults, FireRecord, PendingCombatResults, BASE_HIT_CHANCE, DAMAGE_PER_HIT, SUPPRESSION_PER_HIT,
+};
+use crate::systems::cover::{CoverEvaluation, InCover};
+use crate::systems::movement::DeltaTime;
+use crate::systems::performance::{SectorCombatData, SimConfig, SimTick};
+use bevy_ecs::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+
+/// Average accuracy falloff over a pool's engagement range.
+const AGGREGATE_RANGE_FACTOR: f32 = 0.75;
+
+/// Shots per soldier per second, as in individual fire.
+const SHOTS_PER_SOLDIER: f32 = 2.0;
+
+/// Pool key: (sector x, sector y, faction).
+type PoolKey = (i32, i32, u8);
+
+/// Components read and written by `sector_combat_system`.
+type PooledSquadData<'a> = (
+    Entity,
+    &'a Faction,
+    &'a Position,
+    &'a SquadStats,
+    &'a SectorId,
+    &'a SimLod,
+    &'a mut Health,
+    &'a mut Suppression,
+    &'a Morale,
+    Option<&'a InCover>,
+    Option<&'a mut CombatStats>,
+    Option<&'a mut ActivityFlags>,
+    Option<&'a FireStance>,
+    Option<&'a mut IncomingFire>,
+);
+
+/// Low-LOD squads of one faction in one sector.
+#[derive(Debug, Default)]
+struct FirepowerPool {
+    /// Effective soldiers firing (size × accuracy × readiness).
+    firepower: f32,
+    /// Combined health, used to share out incoming fire.
+    strength: f32,
+    x_sum: f32,
+    y_sum: f32,
+    range_sum: f32,
+    /// (squad, firepower contributed, health)
+    members: Vec<(Entity, f32, f32)>,
+}
+
+impl FirepowerPool {
+    fn centroid(&self) -> (f32, f32) {
+        let n = self.members.len().max(1) as f32;
+        (self.x_sum / n, self.y_sum / n)
+    }
+
+    fn fire_range(&self) -> f32 {
+        self.range_sum / self.members.len().max(1) as f32
+    }
+}
+
+/// Firepower a squad contributes to its pool.
+///
+/// Mirrors the individual fire model: suppression and morale degrade
+/// accuracy, and pinned or broken squads don't fire at all.
+fn squad_firepower(stats: &SquadStats, suppression: &Suppression, morale: &Morale) -> f32 {
+    if suppression.value >= 1.0 || morale.value < 0.2 {
+        return 0.0;
+    }
+    let suppression_penalty = 1.0 - (suppression.value * 0.5).min(0.8);
+    let morale_factor = 0.5 + morale.value * 0.5;
+    stats.size as f32 * stats.accuracy * suppression_penalty * morale_factor
+}
+
+/// System that resolves combat between low-LOD squads through per-sector,
+/// per-faction firepower pools.
+///
+/// Runs on the low-LOD update interval with the time accumulated since the
+/// last run. Fire from each pool is split across the enemy pools whose
+/// centroid is in range, in proportion to their strength; each squad in a
+/// target pool takes a share of the hits in proportion to its health.
+/// The damage each squad takes after cover is credited to the squads that
+/// fired it.
+///
+/// ## Data Access
+/// - Reads: DeltaTime, SimTick, SimConfig, Faction, Position, SquadStats, SectorId, SimLod, Morale, InCover, FireStance, UnitClass
+/// - Writes: Health, Suppression, CombatStats, ActivityFlags, IncomingFire, SectorCombatData
+/// - Writes: PendingCombatResults (pool fire records and kill attribution)
+pub fn sector_combat_system(
+    dt: Res<DeltaTime>,
+    tick: Option<Res<SimTick>>,
+    config: Res<SimConfig>,
+    mut sectors: ResMut<SectorCombatData>,
+    mut pending: Option<ResMut<PendingCombatResults>>,
+    mut query: Query<PooledSquadData, Without<Surrendered>>,
+    classes: Query<&UnitClass>,
+) {
+    let current_tick = tick.as_ref().map(|t| t.0).unwrap_or(0);
+    if !SimLod::Low.should_update(current_tick) {
+        return;
+    }
+    let delta = dt.0 * SimLod::Low.tick_interval() as f32;
+    sectors.clear();
+
+    // Squads that already fired individually this tick (at higher-LOD enemies)
+    let fired: HashSet<Entity> = pending.as_ref().map(|p| p.0.fired.iter().copied().collect()).unwrap_or_default();
+
+    // Build pools. BTreeMap keeps the summation order deterministic.
+    let mut pools: BTreeMap<PoolKey, FirepowerPool> = BTreeMap::new();
+    for (entity, faction, pos, stats, sector, lod, health, suppression, morale, .., stance, incoming) in query.iter() {
+        if *lod != SimLod::Low || !health.is_alive() {
+            continue;
+        }
+        let faction_id = match faction { Faction::Blue => 0, Faction::Red => 1 };
+        let under_fire = incoming.is_some_and(|i| i.is_recent(current_tick));
+        let holds_pool_fire = fired.contains(&entity)
+            || stance.is_some_and(|s| s.holds_fire(under_fire) || matches!(s, FireStance::EngageTarget(_)));
+        let firepower = if holds_pool_fire {
+            0.0
+        } else {
+            squad_firepower(stats, suppression, morale)
+        };
+        let pool = pools.entry((sector.0, sector.1, faction_id)).or_default();
+        pool.firepower += firepower;
+        pool.strength += health.current;
+        pool.x_sum += pos.x;
+        pool.y_sum += pos.y;
+        pool.range_sum += stats.fire_range;
+        pool.members.push((entity, firepower, health.current));
+        sectors.register_unit(*sector, faction_id);
+    }
+
+    // Exchange fire: expected hits landing on each pool, by firing pool
+    let mut incoming: BTreeMap<PoolKey, Vec<(PoolKey, f32)>> = BTreeMap::new();
+    for (&(sx, sy, faction), pool) in &pools {
+        if pool.firepower <= 0.0 {
+            continue;
+        }
+        let (x, y) = pool.centroid();
+        let range = pool.fire_range();
+        let reach = (range / config.sector_size).ceil() as i32;
+
+        let mut targets: Vec<(PoolKey, f32)> = Vec::new();
+        for tx in (sx - reach)..=(sx + reach) {
+            for ty in (sy - reach)..=(sy + reach) {
+                let key = (tx, ty, 1 - faction);
+                let Some(enemy) = pools.get(&key) else { continue };
+                let (ex, ey) = enemy.centroid();
+                if ((ex - x).powi(2) + (ey - y).powi(2)).sqrt() <= range {
+                    targets.push((key, enemy.strength));
+                }
+            }
+        }
+        let total_strength: f32 = targets.iter().map(|(_, s)| s).sum();
+        if total_strength <= 0.0 {
+            continue;
+        }
+
+        let hits = pool.firepower * SHOTS_PER_SOLDIER * delta * BASE_HIT_CHANCE * AGGREGATE_RANGE_FACTOR;
+        for (key, strength) in targets {
+            incoming.entry(key).or_default().push(((sx, sy, faction), hits * strength / total_strength));
+        }
+    }
+
+    // Spread incoming fire over each pool's squads by health share, and
+    // attribute it to the firing squads by firepower share
+    let mut fire = Vec::new();
+    let mut killed = Vec::new();
+    for (key, sources) in &incoming {
+        let pool = &pools[key];
+        let hits: f32 = sources.iter().map(|(_, h)| h).sum();
+        let mut pool_damage = 0.0;
+        let mut pool_suppression = 0.0;
+        for &(entity, _, squad_health) in &pool.members {
+            let Ok((.., mut health, mut suppression, _, in_cover, stats, flags, _, incoming)) = query.get_mut(entity) else {
+                continue;
+            };
+            let share = hits * squad_health / pool.strength;
+            let cover = CoverEvaluation::from_value(in_cover.map(|c| c.value).unwrap_or(0.0));
+            let damage = share * DAMAGE_PER_HIT * cover.damage_multiplier();
+            let suppress = share * SUPPRESSION_PER_HIT * cover.suppression_multiplier();
+
+            let was_alive = health.is_alive();
+            health.damage(damage);
+            if was_alive && !health.is_alive() {
+                killed.push(entity);
+            }
+            suppression.add(suppress);
+            if let Some(mut stats) = stats {
+                stats.damage_taken += damage;
+            }
+            if let Some(mut flags) = flags {
+                flags.mark_damaged(current_tick);
+            }
+            if let Some(mut incoming) = incoming {
+                incoming.record(current_tick, None);
+            }
+            pool_damage += damage;
+            pool_suppression += suppress;
+
+            for (source, source_hits) in sources {
+                let source_pool = &pools[source];
+                for &(shooter, firepower, _) in source_pool.members.iter().filter(|m| m.1 > 0.0) {
+                    let weight = source_hits / hits * firepower / source_pool.firepower;
+                    fire.push(FireRecord {
+                        shooter,
+                        target: entity,
+                        weapon: classes.get(shooter).copied().unwrap_or_default(),
+                        hits: share * weight,
+                        damage: damage * weight,
+                        suppression: suppress * weight,
+                        cover: cover.value,
+                        killed: false,
+                    });
+                }
+            }
+        }
+        sectors.add_damage(SectorId(key.0, key.1), key.2, pool_damage, pool_suppression);
+    }
+
+    // Kills go to the top damage dealer this tick, individual fire included
+    let mut local = CombatResults::default();
+    let results = match pending.as_deref_mut() {
+        Some(pending) => &mut pending.0,
+        None => &mut local,
+    };
+    let first_pool_record = results.fire.len();
+    results.fire.extend(fire);
+    results.credit_kills(&killed);
+
+    for (i, record) in results.fire.iter().enumerate() {
+        let pool_record = i >= first_pool_record;
+        let kill = record.killed && killed.contains(&record.target);
+        if !pool_record && !kill {
+            continue;
+        }
+        let Ok((.., stats, flags, _, _)) = query.get_mut(record.shooter) else { continue };
+        if let Some(mut stats) = stats {
+            if pool_record {
+                stats.hits += record.hits;
+                stats.damage_dealt += record.damage;
+            }
+            stats.kills += u32::from(kill);
+        }
+        if let Some(mut flags) = flags {
+            flags.is_firing = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_squads(world: &mut World, faction: Faction, x: f32, count: usize, lod: SimLod) -> Vec<Entity> {
+        (0..count)
+            .map(|i| {
+                let y = i as f32 * 5.0;
+                world.spawn((
+                    SquadId(i as u32),
+                    faction,
+                    Position::new(x, y),
+                    SquadStats::default(),
+                    SectorId::from_position(x, y, 40.0),
+                    lod,
+                    Health::new(100.0),
+                    Suppression::default(),
+                    Morale::default(),
+                    CombatStats::default(),
+                )).id()
+            })
+            .collect()
+    }
+
+    fn run(world: &mut World, ticks: u64) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(sector_combat_system);
+        for t in 0..ticks {
+            world.insert_resource(SimTick(t));
+            schedule.run(world);
+        }
+    }
+
+    fn new_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(0.05));
+        world.insert_resource(SimConfig::default());
+        world.insert_resource(SectorCombatData::default());
+        world
+    }
+
+    #[test]
+    fn test_larger_pool_wins_lanchester_exchange() {
+        let mut world = new_world();
+        let blue = spawn_squads(&mut world, Faction::Blue, 500.0, 6, SimLod::Low);
+        let red = spawn_squads(&mut world, Faction::Red, 540.0, 3, SimLod::Low);
+        run(&mut world, 40);
+
+        let lost = |world: &World, squads: &[Entity]| -> f32 {
+            squads.iter().map(|&e| 100.0 - world.get::<Health>(e).unwrap().current).sum::<f32>()
+        };
+        let (blue_lost, red_lost) = (lost(&world, &blue), lost(&world, &red));
+        assert!(blue_lost > 0.0 && red_lost > 0.0);
+        // Twice the force: red's total loss is much more than twice blue's
+        assert!(red_lost > 2.0 * blue_lost, "blue lost {}, red lost {}", blue_lost, red_lost);
+
+        let stats = world.get::<CombatStats>(blue[0]).unwrap();
+        assert!(stats.damage_dealt > 0.0 && stats.damage_taken > 0.0);
+        let sectors = world.resource::<SectorCombatData>();
+        let red_sector = *world.get::<SectorId>(red[0]).unwrap();
+        assert!(sectors.get_stats(red_sector, 1).unwrap().incoming_damage > 0.0);
+    }
+
+    #[test]
+    fn test_pool_credits_damage_after_cover_and_kills() {
+        let mut world = new_world();
+        world.insert_resource(PendingCombatResults::default());
+        let blue = spawn_squads(&mut world, Faction::Blue, 500.0, 3, SimLod::Low);
+        let red = spawn_squads(&mut world, Faction::Red, 540.0, 1, SimLod::Low)[0];
+        world.entity_mut(red).insert((
+            Health::new(5.0),
+            InCover { cover_type: crate::systems::cover::CoverType::from_value(0.6), value: 0.6 },
+        ));
+        run(&mut world, 40);
+
+        let stats = |e: Entity| *world.get::<CombatStats>(e).unwrap();
+        assert!(!world.get::<Health>(red).unwrap().is_alive());
+        let dealt: f32 = blue.iter().map(|&e| stats(e).damage_dealt).sum();
+        let taken = stats(red).damage_taken;
+        assert!(taken > 0.0 && (dealt - taken).abs() < 1e-3, "dealt {}, taken {}", dealt, taken);
+        assert_eq!(blue.iter().map(|&e| stats(e).kills).sum::<u32>(), 1);
+    }
+
+    #[test]
+    fn test_high_lod_squads_are_not_pooled() {
+        let mut world = new_world();
+        let blue = spawn_squads(&mut world, Faction::Blue, 500.0, 3, SimLod::High);
+        let red = spawn_squads(&mut world, Faction::Red, 540.0, 3, SimLod::Low);
+        run(&mut world, 8);
+
+        for &e in blue.iter().chain(&red) {
+            assert_eq!(world.get::<Health>(e).unwrap().current, 100.0);
+        }
+    }
+
+    #[test]
+    fn test_low_lod_squad_returns_fire_on_high_lod_enemy() {
+        use crate::spatial::{spatial_grid_update_system, SpatialGrid};
+        use crate::systems::combat::{combat_apply_system, combat_gather_system};
+
+        let mut world = new_world();
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.insert_resource(PendingCombatResults::default());
+        let low = spawn_squads(&mut world, Faction::Blue, 500.0, 1, SimLod::Low)[0];
+        let high = spawn_squads(&mut world, Faction::Red, 520.0, 1, SimLod::High)[0];
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            spatial_grid_update_system,
+            combat_gather_system,
+            combat_apply_system,
+            sector_combat_system,
+        ).chain());
+        for t in 0..8 {
+            world.insert_resource(SimTick(t));
+            schedule.run(&mut world);
+        }
+
+        let health = |e: Entity| world.get::<Health>(e).unwrap().current;
+        assert!(health(low) < 100.0);
+        assert!(health(high) < 100.0, "the low-LOD squad never fired back");
+        assert!(world.get::<CombatStats>(low).unwrap().damage_dealt > 0.0);
+    }
+
+    #[test]
+    fn test_engage_target_stays_out_of_the_pool() {
+        use crate::spatial::{spatial_grid_update_system, SpatialGrid};
+        use crate::systems::combat::{combat_apply_system, combat_gather_system};
+
+        let mut world = new_world();
+        world.insert_resource(SpatialGrid::new(20.0));
+        world.insert_resource(PendingCombatResults::default());
+        let blue = spawn_squads(&mut world, Faction::Blue, 500.0, 1, SimLod::Low)[0];
+        world.entity_mut(blue).insert((SquadId(10), FireStance::EngageTarget(21)));
+        let red = spawn_squads(&mut world, Faction::Red, 520.0, 2, SimLod::Low);
+        world.entity_mut(red[0]).insert((SquadId(20), FireStance::HoldFire));
+        world.entity_mut(red[1]).insert((SquadId(21), FireStance::HoldFire));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            spatial_grid_update_system,
+            combat_gather_system,
+            combat_apply_system,
+            sector_combat_system,
+        ).chain());
+        for t in 0..8 {
+            world.insert_resource(SimTick(t));
+            schedule.run(&mut world);
+        }
+
+        let health = |e: Entity| world.get::<Health>(e).unwrap().current;
+        assert_eq!(health(red[0]), 100.0, "pooled fire hit a squad other than the target");
+        assert!(health(red[1]) < 100.0);
+    }
+}
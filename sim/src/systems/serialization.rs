@@ -45,11 +45,26 @@ mod tests {
                 morale: 1.0,
                 suppression: 0.0,
                 order: "Hold".to_string(),
+                cover: 0.0,
+                fatigue: 0.0,
+                surrendered: false,
+                kills: 0,
+                damage_dealt: 0.0,
+                garrison: None,
+                facing: 0.0,
+                queue: vec![],
+                parent: None,
+                fallback: None,
+                fire_stance: "FireAtWill".to_string(),
+                scheduled: vec![],
             }],
             destructibles: vec![],
             terrain_damage: vec![],
             new_craters: vec![],
             terrain_dirty: false,
+            smoke: vec![],
+            command_groups: vec![],
+            rally_points: vec![],
         };
 
         let json = snapshot_to_json_string(&snapshot).unwrap();
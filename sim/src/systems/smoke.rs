@@ -0,0 +1,88 @@
+//! Smoke screens.
+//!
+//! Smoke clouds come from artillery smoke rounds and squad smoke grenades.
+//! They drift with the wind, thin out toward the end of their lifetime and
+//! are removed once dispersed. Line of sight and accuracy through smoke are
+//! handled by `LineOfSight` (see `los.rs`), which samples the clouds each
+//! tick.
+
+use crate::components::*;
+use crate::systems::movement::DeltaTime;
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Radius of the cloud from one artillery smoke round.
+pub const SMOKE_SHELL_RADIUS: f32 = 12.0;
+
+/// Lifetime of an artillery smoke cloud (seconds).
+pub const SMOKE_SHELL_LIFETIME: f32 = 60.0;
+
+/// Radius of the cloud from a smoke grenade.
+pub const SMOKE_GRENADE_RADIUS: f32 = 6.0;
+
+/// Lifetime of a smoke grenade cloud (seconds).
+pub const SMOKE_GRENADE_LIFETIME: f32 = 30.0;
+
+/// How far a squad can throw a smoke grenade.
+pub const SMOKE_GRENADE_RANGE: f32 = 25.0;
+
+/// Accuracy lost when firing through fully obscuring smoke.
+pub const SMOKE_ACCURACY_PENALTY: f32 = 0.8;
+
+/// Wind over the battlefield (world units per second). Smoke drifts with it.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Wind {
+    pub vx: f32,
+    pub vy: f32,
+}
+
+/// System that drifts, ages and removes smoke clouds.
+///
+/// ## Data Access
+/// - Reads: DeltaTime, Wind
+/// - Writes: Position, SmokeCloud (despawns dispersed clouds)
+pub fn smoke_system(
+    mut commands: Commands,
+    dt: Res<DeltaTime>,
+    wind: Option<Res<Wind>>,
+    mut clouds: Query<(Entity, &mut Position, &mut SmokeCloud)>,
+) {
+    let delta = dt.0;
+    let wind = wind.map(|w| *w).unwrap_or_default();
+
+    for (entity, mut pos, mut cloud) in clouds.iter_mut() {
+        cloud.age += delta;
+        if cloud.is_expired() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        pos.x += wind.vx * delta;
+        pos.y += wind.vy * delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smoke_drifts_thins_and_disperses() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(1.0));
+        world.insert_resource(Wind { vx: 2.0, vy: 0.0 });
+        let cloud = world.spawn((Position::new(0.0, 0.0), SmokeCloud::new(10.0, 8.0))).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(smoke_system);
+        for _ in 0..7 {
+            schedule.run(&mut world);
+        }
+
+        assert_eq!(world.get::<Position>(cloud).unwrap().x, 14.0);
+        let density = world.get::<SmokeCloud>(cloud).unwrap().density();
+        assert!(density > 0.0 && density < 1.0, "density {}", density);
+
+        schedule.run(&mut world);
+        assert!(world.get_entity(cloud).is_err());
+    }
+}
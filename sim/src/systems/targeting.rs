@@ -0,0 +1,164 @@
+//! Target selection for direct fire.
+//!
+//! Instead of always engaging the nearest enemy, each shooter scores the
+//! closest visible enemies in range and fires at the best one. The score
+//! weighs:
+//! - distance (closer targets are easier to hit),
+//! - the target's cover against this shooter,
+//! - the target's threat (unit class, closing in on the shooter),
+//! - the target's suppression (pinned units are less urgent),
+//! - how many friendly squads already engaged it last tick,
+//! - the shooter's weapon suitability and `FirePriority`.
+//!
+//! Scoring is pure so it can run inside the parallel combat gather phase.
+
+use crate::components::*;
+use crate::systems::cover::CoverEvaluation;
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+
+/// Maximum number of visible enemies scored per shooter (closest first).
+pub const MAX_TARGET_CANDIDATES: usize = 8;
+
+/// Closing speed (units/sec) at which the "closing in" threat bonus saturates.
+const CLOSING_SPEED_SATURATION: f32 = 5.0;
+
+/// Per-target data gathered once per tick.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TargetInfo {
+    pub class: UnitClass,
+    pub suppression: f32,
+    pub vx: f32,
+    pub vy: f32,
+    /// Height above the ground the squad is at (upper floors of a building).
+    pub elevation: f32,
+    /// Only spotted at short range (garrisoned in a building).
+    pub concealed: bool,
+    /// Fights through the sector firepower pools (low LOD).
+    pub pooled: bool,
+}
+
+/// The shooter's side of target scoring.
+#[derive(Debug, Clone, Copy)]
+pub struct ShooterProfile {
+    pub x: f32,
+    pub y: f32,
+    pub fire_range: f32,
+    pub class: UnitClass,
+    pub priority: FirePriority,
+}
+
+/// A visible enemy being considered as a target.
+#[derive(Debug, Clone, Copy)]
+pub struct TargetCandidate {
+    pub entity: Entity,
+    pub x: f32,
+    pub y: f32,
+    pub dist: f32,
+    pub cover: CoverEvaluation,
+    /// Shooter's ground height minus the target's.
+    pub height_advantage: f32,
+    pub info: TargetInfo,
+}
+
+/// Which squads engaged which targets on the previous tick.
+///
+/// Used to spread fire: a target already engaged by several friendlies is
+/// less attractive to the next shooter.
+#[derive(Debug, Clone, Default)]
+pub struct Engagements {
+    assignments: HashMap<Entity, Entity>,
+    counts: HashMap<Entity, u32>,
+}
+
+impl Engagements {
+    /// Build from attacker -> target assignments.
+    pub fn from_assignments(assignments: HashMap<Entity, Entity>) -> Self {
+        let mut counts = HashMap::new();
+        for target in assignments.values() {
+            *counts.entry(*target).or_insert(0) += 1;
+        }
+        Self { assignments, counts }
+    }
+
+    /// Number of squads other than `shooter` that engaged `target` last tick.
+    pub fn others_engaging(&self, target: Entity, shooter: Entity) -> u32 {
+        let total = self.counts.get(&target).copied().unwrap_or(0);
+        let own = u32::from(self.assignments.get(&shooter) == Some(&target));
+        total - own
+    }
+}
+
+/// Score a candidate target. Higher is better; only relative values matter.
+pub fn score_target(shooter: &ShooterProfile, target: &TargetCandidate, engaged_by_others: u32) -> f32 {
+    let distance = 1.0 - 0.5 * (target.dist / shooter.fire_range.max(0.001)).min(1.0);
+    let cover = target.cover.damage_multiplier();
+
+    // Positive when the target is moving toward the shooter
+    let closing = if target.dist > 0.001 {
+        (target.info.vx * (shooter.x - target.x) + target.info.vy * (shooter.y - target.y)) / target.dist
+    } else {
+        0.0
+    };
+    let closing_bonus = 1.0 + 0.5 * (closing / CLOSING_SPEED_SATURATION).clamp(0.0, 1.0);
+    let threat = target.info.class.threat_weight() * closing_bonus;
+
+    let suppression = 1.0 - 0.5 * target.info.suppression.clamp(0.0, 1.0);
+    let suitability = shooter.class.weapon_suitability(target.info.class);
+    let priority = shooter.priority.bias(target.info.class);
+    let spread = 1.0 / (1.0 + 0.3 * engaged_by_others as f32);
+
+    distance * cover * threat * suppression * suitability * priority * spread
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shooter(class: UnitClass, priority: FirePriority) -> ShooterProfile {
+        ShooterProfile { x: 0.0, y: 0.0, fire_range: 60.0, class, priority }
+    }
+
+    fn candidate(id: u32, x: f32, cover: f32, class: UnitClass) -> TargetCandidate {
+        TargetCandidate {
+            entity: Entity::from_raw(id),
+            x,
+            y: 0.0,
+            dist: x.abs(),
+            cover: CoverEvaluation::from_value(cover),
+            height_advantage: 0.0,
+            info: TargetInfo { class, ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn test_exposed_threat_beats_closer_dug_in_target() {
+        let rifles = shooter(UnitClass::Rifle, FirePriority::Auto);
+        let dug_in = candidate(1, 20.0, 0.9, UnitClass::Rifle);
+        let mut assault = candidate(2, 35.0, 0.0, UnitClass::Rifle);
+        assault.info.vx = -5.0; // closing in
+
+        assert!(score_target(&rifles, &assault, 0) > score_target(&rifles, &dug_in, 0));
+        // Already engaged targets become less attractive
+        assert!(score_target(&rifles, &assault, 3) < score_target(&rifles, &assault, 0));
+    }
+
+    #[test]
+    fn test_fire_priority_and_weapon_suitability() {
+        let infantry = candidate(1, 20.0, 0.0, UnitClass::Rifle);
+        let vehicle = candidate(2, 40.0, 0.0, UnitClass::Vehicle);
+
+        let rifles = shooter(UnitClass::Rifle, FirePriority::Auto);
+        assert!(score_target(&rifles, &infantry, 0) > score_target(&rifles, &vehicle, 0));
+
+        let anti_tank = shooter(UnitClass::AntiTank, FirePriority::PreferVehicles);
+        assert!(score_target(&anti_tank, &vehicle, 0) > score_target(&anti_tank, &infantry, 0));
+
+        let mut engagements = HashMap::new();
+        engagements.insert(Entity::from_raw(10), Entity::from_raw(2));
+        engagements.insert(Entity::from_raw(11), Entity::from_raw(2));
+        let engagements = Engagements::from_assignments(engagements);
+        assert_eq!(engagements.others_engaging(Entity::from_raw(2), Entity::from_raw(10)), 1);
+        assert_eq!(engagements.others_engaging(Entity::from_raw(2), Entity::from_raw(12)), 2);
+    }
+}
@@ -3,12 +3,52 @@
 use crate::components::*;
 use bevy_ecs::prelude::*;
 
+/// Terrain damage events processed since the last snapshot.
+///
+/// Event entities only live for one tick; they are copied here so the next
+/// snapshot can still report them to the client. A run that never takes a
+/// snapshot keeps only the newest `MAX_EVENTS`.
+#[derive(Resource, Debug, Default)]
+pub struct TerrainDamageBuffer {
+    pub events: Vec<TerrainDamageEvent>,
+}
+
+impl TerrainDamageBuffer {
+    /// Most events held between snapshots; older ones are dropped first.
+    pub const MAX_EVENTS: usize = 1024;
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Drop the oldest events beyond `MAX_EVENTS`.
+    fn trim(&mut self) {
+        let excess = self.events.len().saturating_sub(Self::MAX_EVENTS);
+        if excess > 0 {
+            self.events.drain(..excess);
+        }
+    }
+}
+
 /// System that clears terrain damage events after they've been processed.
-/// Events are consumed by the snapshot system and then removed.
-pub fn clear_terrain_damage_system(mut commands: Commands, query: Query<Entity, With<TerrainDamageEvent>>) {
-    for entity in query.iter() {
+///
+/// Must run after every consumer of `TerrainDamageEvent` (destructible and
+/// squad blast damage) so each event is applied exactly once. Cleared events
+/// are kept in `TerrainDamageBuffer`, if present, for the snapshot.
+pub fn clear_terrain_damage_system(
+    mut commands: Commands,
+    mut buffer: Option<ResMut<TerrainDamageBuffer>>,
+    query: Query<(Entity, &TerrainDamageEvent)>,
+) {
+    for (entity, event) in query.iter() {
+        if let Some(buffer) = buffer.as_mut() {
+            buffer.events.push(*event);
+        }
         commands.entity(entity).despawn();
     }
+    if let Some(buffer) = buffer.as_mut() {
+        buffer.trim();
+    }
 }
 
 /// Helper function to spawn a terrain damage event (e.g., from artillery).
@@ -44,4 +84,24 @@ mod tests {
         // Verify it's gone
         assert_eq!(query.iter(&world).count(), 0);
     }
+
+    #[test]
+    fn test_terrain_damage_buffer_capped_without_snapshots() {
+        let mut world = World::new();
+        world.insert_resource(TerrainDamageBuffer::default());
+        let mut schedule = Schedule::default();
+        schedule.add_systems(clear_terrain_damage_system);
+
+        let ticks = TerrainDamageBuffer::MAX_EVENTS + 10;
+        for i in 0..ticks {
+            world.spawn(TerrainDamageEvent { x: i as f32, y: 0.0, radius: 5.0, depth: 1.0 });
+            schedule.run(&mut world);
+        }
+
+        let buffer = world.resource::<TerrainDamageBuffer>();
+        assert_eq!(buffer.events.len(), TerrainDamageBuffer::MAX_EVENTS);
+        // The newest events are the ones kept
+        assert_eq!(buffer.events.last().unwrap().x, (ticks - 1) as f32);
+        assert_eq!(buffer.events[0].x, 10.0);
+    }
 }
@@ -0,0 +1,260 @@
+//! Conditional and scheduled orders.
+//!
+//! A squad can hold orders that wait on a trigger: a tick, another squad
+//! arriving, a barrage lifting, a sector being captured or morale falling
+//! below a threshold. `order_trigger_system` checks the triggers every tick
+//! and releases an order once its trigger is met, replacing the squad's
+//! current order as if it had just been given. Because the triggers are
+//! evaluated inside the simulation, zero-hour attacks replay exactly.
+
+use crate::components::*;
+use crate::systems::defense::{PatrolLeg, Station};
+use crate::systems::explosion::LiftedBarrages;
+use crate::systems::formation::FormationMember;
+use crate::systems::performance::{SimConfig, SimTick};
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Condition that releases a scheduled order.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OrderTrigger {
+    /// The simulation has reached the tick.
+    AtTick(u64),
+    /// The squad has carried out its current order and has none queued.
+    /// Holding counts as carried out, so a squad idling on `Order::Hold`
+    /// meets this on the next tick.
+    Arrived { squad: u32 },
+    /// Every shell of the barrage has landed.
+    BarrageLifted { barrage: u32 },
+    /// The faction holds the sector: it has squads there that are still
+    /// fighting and the enemy has none.
+    SectorCaptured { sector: SectorId, faction: Faction },
+    /// The squad's morale has fallen below the threshold.
+    MoraleBelow { squad: u32, threshold: f32 },
+}
+
+/// An order waiting on a trigger.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScheduledOrder {
+    pub trigger: OrderTrigger,
+    pub order: Order,
+}
+
+/// Orders a squad will take once their triggers are met, in the order they
+/// were scheduled.
+#[derive(Component, Debug, Clone, Default)]
+pub struct ScheduledOrders(pub Vec<ScheduledOrder>);
+
+/// Give a squad a new order in place of its current one, as a player order
+/// does: its queued orders are dropped and it leaves any formation, station
+/// or patrol.
+pub(crate) fn replace_order(mut squad: EntityWorldMut, order: Order) {
+    if let Some(mut queue) = squad.get_mut::<OrderQueue>() {
+        queue.clear();
+    }
+    squad.remove::<(FormationMember, Station, PatrolLeg)>();
+    squad.insert(order);
+}
+
+/// What triggers can see of a squad.
+struct TriggerSquad {
+    arrived: bool,
+    morale: f32,
+}
+
+/// State of the battle the triggers are checked against.
+struct TriggerState {
+    tick: u64,
+    squads: HashMap<u32, TriggerSquad>,
+    lifted: HashSet<u32>,
+    /// Whether Blue and Red have fighting squads in each sector.
+    sectors: HashMap<SectorId, (bool, bool)>,
+}
+
+impl TriggerState {
+    fn is_met(&self, trigger: &OrderTrigger) -> bool {
+        match *trigger {
+            OrderTrigger::AtTick(tick) => self.tick >= tick,
+            OrderTrigger::Arrived { squad } => self.squads.get(&squad).is_some_and(|s| s.arrived),
+            OrderTrigger::BarrageLifted { barrage } => self.lifted.contains(&barrage),
+            OrderTrigger::SectorCaptured { sector, faction } => {
+                let (blue, red) = self.sectors.get(&sector).copied().unwrap_or((false, false));
+                match faction {
+                    Faction::Blue => blue && !red,
+                    Faction::Red => red && !blue,
+                }
+            }
+            OrderTrigger::MoraleBelow { squad, threshold } => {
+                self.squads.get(&squad).is_some_and(|s| s.morale < threshold)
+            }
+        }
+    }
+}
+
+/// Components read and written by `order_trigger_system`.
+type TriggerSquadData<'a> = (
+    Entity,
+    &'a SquadId,
+    &'a Faction,
+    &'a Position,
+    &'a Health,
+    &'a Morale,
+    &'a Order,
+    Option<&'a OrderQueue>,
+    Option<&'a mut ScheduledOrders>,
+);
+
+/// System that releases scheduled orders whose triggers are met.
+///
+/// ## Data Access
+/// - Reads: SimTick, SimConfig, LiftedBarrages, SquadId, Faction, Position, Health, Morale
+/// - Writes: ScheduledOrders, Order, OrderQueue (cleared), FormationMember, Station, PatrolLeg (removed)
+///
+/// If several of a squad's orders are released on the same tick, the one
+/// scheduled last wins. Dead and surrendered squads neither release orders
+/// nor meet triggers; routing squads keep theirs pending until they rally,
+/// as they can't take orders.
+pub fn order_trigger_system(
+    mut commands: Commands,
+    tick: Option<Res<SimTick>>,
+    config: Option<Res<SimConfig>>,
+    lifted: Option<Res<LiftedBarrages>>,
+    mut squads: Query<TriggerSquadData, Without<Surrendered>>,
+) {
+    let waiting = |scheduled: Option<&ScheduledOrders>| scheduled.is_some_and(|s| !s.0.is_empty());
+    if !squads.iter().any(|(.., scheduled)| waiting(scheduled)) {
+        return;
+    }
+    let sector_size = config.as_ref().map(|c| c.sector_size).unwrap_or(40.0);
+
+    let mut state = TriggerState {
+        tick: tick.as_ref().map(|t| t.0).unwrap_or(0),
+        squads: HashMap::new(),
+        lifted: lifted.map(|l| l.0.clone()).unwrap_or_default(),
+        sectors: HashMap::new(),
+    };
+    for (_, id, faction, pos, health, morale, order, queue, _) in squads.iter() {
+        if !health.is_alive() {
+            continue;
+        }
+        state.squads.insert(id.0, TriggerSquad {
+            arrived: order.is_complete(pos) && queue.is_none_or(|q| q.is_empty()),
+            morale: morale.value,
+        });
+        if !morale.is_broken() {
+            let held = state.sectors.entry(SectorId::from_position(pos.x, pos.y, sector_size)).or_default();
+            match faction {
+                Faction::Blue => held.0 = true,
+                Faction::Red => held.1 = true,
+            }
+        }
+    }
+
+    for (entity, _, _, _, health, morale, _, _, scheduled) in squads.iter_mut() {
+        let Some(mut scheduled) = scheduled else { continue };
+        if !health.is_alive() || morale.is_broken() || !scheduled.0.iter().any(|s| state.is_met(&s.trigger)) {
+            continue;
+        }
+        let mut released = None;
+        scheduled.0.retain(|s| {
+            let met = state.is_met(&s.trigger);
+            if met {
+                released = Some(s.order);
+            }
+            !met
+        });
+        if let Some(next) = released {
+            commands.entity(entity).queue(move |squad: EntityWorldMut| replace_order(squad, next));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_squad(world: &mut World, id: u32, faction: Faction, x: f32, y: f32) -> Entity {
+        world.spawn((
+            SquadId(id),
+            faction,
+            Position::new(x, y),
+            Health::new(100.0),
+            Morale::default(),
+            Order::Hold,
+        )).id()
+    }
+
+    fn schedule_order(world: &mut World, entity: Entity, trigger: OrderTrigger, order: Order) {
+        world.entity_mut(entity).insert(ScheduledOrders(vec![ScheduledOrder { trigger, order }]));
+    }
+
+    #[test]
+    fn test_orders_wait_for_sector_capture_and_morale() {
+        let mut world = World::new();
+        world.insert_resource(SimConfig::default());
+        let sector = SectorId::from_position(60.0, 0.0, SimConfig::default().sector_size);
+
+        let reserve = spawn_squad(&mut world, 1, Faction::Blue, 0.0, 0.0);
+        schedule_order(&mut world, reserve, OrderTrigger::SectorCaptured { sector, faction: Faction::Blue }, Order::MoveTo { x: 60.0, y: 0.0 });
+        let shaky = spawn_squad(&mut world, 2, Faction::Blue, 5.0, 0.0);
+        schedule_order(&mut world, shaky, OrderTrigger::MoraleBelow { squad: 2, threshold: 0.3 }, Order::Retreat);
+        let attacker = spawn_squad(&mut world, 3, Faction::Blue, 62.0, 0.0);
+        let defender = spawn_squad(&mut world, 4, Faction::Red, 65.0, 0.0);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(order_trigger_system);
+        schedule.run(&mut world);
+        assert!(matches!(world.get::<Order>(reserve), Some(Order::Hold)), "sector still contested");
+        assert!(matches!(world.get::<Order>(shaky), Some(Order::Hold)));
+
+        world.get_mut::<Health>(defender).unwrap().current = 0.0;
+        world.get_mut::<Morale>(shaky).unwrap().value = 0.2;
+        schedule.run(&mut world);
+        assert!(matches!(world.get::<Order>(reserve), Some(Order::MoveTo { .. })));
+        assert!(matches!(world.get::<Order>(shaky), Some(Order::Retreat)));
+        assert!(world.get::<ScheduledOrders>(reserve).unwrap().0.is_empty());
+        assert!(matches!(world.get::<Order>(attacker), Some(Order::Hold)));
+    }
+
+    #[test]
+    fn test_last_scheduled_order_wins_on_the_same_tick() {
+        let mut world = World::new();
+        world.insert_resource(SimTick(10));
+        let squad = spawn_squad(&mut world, 1, Faction::Blue, 0.0, 0.0);
+        world.entity_mut(squad).insert(ScheduledOrders(vec![
+            ScheduledOrder { trigger: OrderTrigger::AtTick(5), order: Order::Retreat },
+            ScheduledOrder { trigger: OrderTrigger::Arrived { squad: 1 }, order: Order::MoveTo { x: 1.0, y: 1.0 } },
+            ScheduledOrder { trigger: OrderTrigger::AtTick(20), order: Order::Hold },
+        ]));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(order_trigger_system);
+        schedule.run(&mut world);
+        assert!(matches!(world.get::<Order>(squad), Some(Order::MoveTo { .. })));
+        assert_eq!(world.get::<ScheduledOrders>(squad).unwrap().0.len(), 1);
+    }
+
+    #[test]
+    fn test_routing_squad_keeps_order_pending_until_it_rallies() {
+        let mut world = World::new();
+        world.insert_resource(SimTick(10));
+        let squad = spawn_squad(&mut world, 1, Faction::Blue, 0.0, 0.0);
+        schedule_order(&mut world, squad, OrderTrigger::AtTick(5), Order::MoveTo { x: 30.0, y: 0.0 });
+        world.entity_mut(squad).insert(OrderQueue::default());
+        world.get_mut::<OrderQueue>(squad).unwrap().push(Order::Hold, None);
+        world.get_mut::<Morale>(squad).unwrap().value = 0.1;
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(order_trigger_system);
+        schedule.run(&mut world);
+        assert!(matches!(world.get::<Order>(squad), Some(Order::Hold)), "routing squads take no orders");
+        assert_eq!(world.get::<ScheduledOrders>(squad).unwrap().0.len(), 1);
+
+        world.get_mut::<Morale>(squad).unwrap().value = 0.6;
+        schedule.run(&mut world);
+        assert!(matches!(world.get::<Order>(squad), Some(Order::MoveTo { .. })));
+        assert!(world.get::<ScheduledOrders>(squad).unwrap().0.is_empty());
+        assert!(world.get::<OrderQueue>(squad).unwrap().is_empty(), "queue dropped like a player order");
+    }
+}
@@ -31,6 +31,83 @@ impl TerrainResource {
     pub fn get_height_at(&self, x: f32, y: f32) -> f32 {
         self.0.read().map(|g| g.get_height_at(x, y)).unwrap_or(0.0)
     }
+
+    /// Acquire a read guard on the grid.
+    ///
+    /// Heavy systems should take the guard once per run rather than locking
+    /// for every lookup. Returns `None` if the lock is poisoned.
+    pub fn read(&self) -> Option<std::sync::RwLockReadGuard<'_, TerrainGrid>> {
+        self.0.read().ok()
+    }
+}
+
+/// Eye height above the ground used for line-of-sight checks (world units).
+pub const EYE_HEIGHT: f32 = 1.5;
+
+/// How far in front of a target (toward the shooter) cover cells are
+/// considered, in world units. A squad lying just behind a parapet or a
+/// rubble pile is protected by it.
+pub const COVER_SAMPLE_DISTANCE: f32 = 4.0;
+
+/// Fraction of oriented cover that still applies against fire from the
+/// flank or rear.
+pub const FLANK_COVER_FACTOR: f32 = 0.25;
+
+/// Effectiveness (0.0 - 1.0) of oriented cover against incoming fire.
+///
+/// `face` is the unit direction the cover protects against (e.g. a trench
+/// parapet facing the enemy line); `incoming` is the unit direction from the
+/// target toward the shooter. Frontal fire gets full cover, effectiveness
+/// falls off toward the flanks and bottoms out at `FLANK_COVER_FACTOR`.
+pub fn directional_cover_factor(face: (f32, f32), incoming: (f32, f32)) -> f32 {
+    let cos = face.0 * incoming.0 + face.1 * incoming.1;
+    ((cos + 1.0) * 0.5).powi(2).max(FLANK_COVER_FACTOR)
+}
+
+/// Height above the ground of a prone or crouching squad, for dead-ground
+/// checks (world units).
+pub const BODY_HEIGHT: f32 = 0.5;
+
+/// Cover value of dead ground: a target whose bodies are masked by a crest
+/// in front of it.
+pub const DEAD_GROUND_COVER: f32 = 0.7;
+
+/// Fire range gained per unit of height above the target.
+const ELEVATION_RANGE_PER_UNIT: f32 = 0.05;
+
+/// Maximum fire range bonus from elevation.
+const MAX_ELEVATION_RANGE_BONUS: f32 = 0.3;
+
+/// Accuracy gained per unit of height above the target (lost when firing
+/// uphill).
+const ELEVATION_ACCURACY_PER_UNIT: f32 = 0.04;
+
+/// Accuracy bonus and penalty bounds from elevation.
+const ELEVATION_ACCURACY_BOUNDS: (f32, f32) = (-0.2, 0.25);
+
+/// Spotting range gained per unit of ground height.
+const SPOTTING_RANGE_PER_UNIT: f32 = 0.05;
+
+/// Maximum spotting range bonus from height.
+const MAX_SPOTTING_RANGE_BONUS: f32 = 0.5;
+
+/// Fire range multiplier for a shooter `height_advantage` units above its
+/// target. Only higher ground extends range.
+pub fn elevation_range_factor(height_advantage: f32) -> f32 {
+    1.0 + (height_advantage * ELEVATION_RANGE_PER_UNIT).clamp(0.0, MAX_ELEVATION_RANGE_BONUS)
+}
+
+/// Accuracy multiplier for a shooter `height_advantage` units above its
+/// target (negative when firing uphill).
+pub fn elevation_accuracy_factor(height_advantage: f32) -> f32 {
+    let (min, max) = ELEVATION_ACCURACY_BOUNDS;
+    1.0 + (height_advantage * ELEVATION_ACCURACY_PER_UNIT).clamp(min, max)
+}
+
+/// Spotting range multiplier for an observer standing on ground of the
+/// given height.
+pub fn spotting_range_factor(ground_height: f32) -> f32 {
+    1.0 + (ground_height * SPOTTING_RANGE_PER_UNIT).clamp(0.0, MAX_SPOTTING_RANGE_BONUS)
 }
 
 /// Terrain type at a grid cell.
@@ -97,6 +174,11 @@ impl TerrainType {
     pub fn blocks_los(&self) -> bool {
         matches!(self, TerrainType::Forest)
     }
+
+    /// Whether squads can be ordered to stand on this terrain.
+    pub fn is_passable(&self) -> bool {
+        !matches!(self, TerrainType::Water)
+    }
 }
 
 /// A single cell in the terrain grid.
@@ -108,6 +190,10 @@ pub struct TerrainCell {
     pub terrain_type: TerrainType,
     /// Accumulated damage/deformation at this cell.
     pub damage: f32,
+    /// Direction (radians) the cell's cover faces, for oriented cover such
+    /// as trench parapets and walls. `None` protects from all sides.
+    #[serde(default)]
+    pub facing: Option<f32>,
 }
 
 impl Default for TerrainCell {
@@ -116,6 +202,19 @@ impl Default for TerrainCell {
             height: 0.0,
             terrain_type: TerrainType::Open,
             damage: 0.0,
+            facing: None,
+        }
+    }
+}
+
+impl TerrainCell {
+    /// Cover this cell provides against fire arriving from `incoming`
+    /// (unit direction from the cell toward the shooter).
+    pub fn cover_from(&self, incoming: (f32, f32)) -> f32 {
+        let cover = self.terrain_type.cover_value();
+        match self.facing {
+            Some(angle) => cover * directional_cover_factor((angle.cos(), angle.sin()), incoming),
+            None => cover,
         }
     }
 }
@@ -265,6 +364,18 @@ impl TerrainGrid {
         (gx, gy)
     }
 
+    /// Convert world coordinates to grid coordinates without clamping.
+    /// Returns `None` if the position lies outside the grid.
+    pub fn world_to_grid_checked(&self, world_x: f32, world_y: f32) -> Option<(usize, usize)> {
+        let gx = ((world_x - self.origin_x) / self.cell_size).floor();
+        let gy = ((world_y - self.origin_y) / self.cell_size).floor();
+        if gx < 0.0 || gy < 0.0 || gx >= self.width as f32 || gy >= self.height as f32 {
+            None
+        } else {
+            Some((gx as usize, gy as usize))
+        }
+    }
+
     /// Convert grid coordinates to world coordinates (center of cell).
     pub fn grid_to_world(&self, gx: usize, gy: usize) -> (f32, f32) {
         let world_x = self.origin_x + (gx as f32 + 0.5) * self.cell_size;
@@ -297,6 +408,157 @@ impl TerrainGrid {
         cell.terrain_type.cover_value()
     }
 
+    /// Get cover at a target position against fire from a shooter.
+    ///
+    /// Considers the target's own cell and the cells in front of it along the
+    /// line of fire (up to `COVER_SAMPLE_DISTANCE`), so oriented cover such as
+    /// trenches only protects against fire from the side it faces. A target
+    /// in dead ground behind a crest counts as covered.
+    pub fn get_cover_from(&self, from_x: f32, from_y: f32, world_x: f32, world_y: f32) -> f32 {
+        let dx = from_x - world_x;
+        let dy = from_y - world_y;
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist < 0.001 {
+            return self.get_cover_at(world_x, world_y);
+        }
+        let incoming = (dx / dist, dy / dist);
+
+        let mut best = self.get_terrain_at(world_x, world_y).cover_from(incoming);
+        if self.is_dead_ground(from_x, from_y, world_x, world_y) {
+            best = best.max(DEAD_GROUND_COVER);
+        }
+        let reach = COVER_SAMPLE_DISTANCE.min(dist * 0.5);
+        let step = self.cell_size * 0.5;
+        let mut t = step;
+        while t <= reach {
+            let x = world_x + incoming.0 * t;
+            let y = world_y + incoming.1 * t;
+            if let Some((gx, gy)) = self.world_to_grid_checked(x, y) {
+                if let Some(cell) = self.get_cell(gx, gy) {
+                    best = best.max(cell.cover_from(incoming));
+                }
+            }
+            t += step;
+        }
+        best
+    }
+
+    /// Whether a target is in dead ground as seen from a shooter: a crest
+    /// between them masks the target's bodies (at `BODY_HEIGHT`) from the
+    /// shooter's eye, so only the tops of heads and helmets show.
+    ///
+    /// The shooter's and target's own cells never mask.
+    pub fn is_dead_ground(&self, from_x: f32, from_y: f32, world_x: f32, world_y: f32) -> bool {
+        let dx = world_x - from_x;
+        let dy = world_y - from_y;
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist <= self.cell_size * 2.0 {
+            return false;
+        }
+
+        let start_cell = self.world_to_grid_checked(from_x, from_y);
+        let end_cell = self.world_to_grid_checked(world_x, world_y);
+        let start_h = self.get_height_at(from_x, from_y) + EYE_HEIGHT;
+        let end_h = self.get_height_at(world_x, world_y) + BODY_HEIGHT;
+
+        let steps = (dist / self.cell_size).ceil() as usize;
+        (1..steps).any(|i| {
+            let t = i as f32 / steps as f32;
+            let cell = self.world_to_grid_checked(from_x + dx * t, from_y + dy * t);
+            match cell {
+                Some((gx, gy)) if cell != start_cell && cell != end_cell => {
+                    self.cells[gy * self.width + gx].height > start_h + (end_h - start_h) * t
+                }
+                _ => false,
+            }
+        })
+    }
+
+    /// Raise a rounded hill centred on a world position.
+    pub fn add_hill(&mut self, world_x: f32, world_y: f32, radius: f32, height: f32) {
+        let (cx, cy) = self.world_to_grid(world_x, world_y);
+        let grid_radius = (radius / self.cell_size).ceil() as i32;
+        for dy in -grid_radius..=grid_radius {
+            for dx in -grid_radius..=grid_radius {
+                let (gx, gy) = (cx as i32 + dx, cy as i32 + dy);
+                if gx < 0 || gy < 0 {
+                    continue;
+                }
+                let dist = ((dx * dx + dy * dy) as f32).sqrt() * self.cell_size;
+                if dist > radius {
+                    continue;
+                }
+                if let Some(cell) = self.get_cell_mut(gx as usize, gy as usize) {
+                    // Cosine profile: flat top, gentle slopes
+                    let profile = 0.5 + 0.5 * (std::f32::consts::PI * dist / radius).cos();
+                    cell.height = cell.height.max(height * profile);
+                }
+            }
+        }
+    }
+
+    /// Dig a trench line between two world positions.
+    ///
+    /// `facing` is the direction (radians) the parapet faces, i.e. toward
+    /// the expected enemy.
+    pub fn dig_trench(&mut self, from_x: f32, from_y: f32, to_x: f32, to_y: f32, facing: f32) {
+        let dx = to_x - from_x;
+        let dy = to_y - from_y;
+        let dist = (dx * dx + dy * dy).sqrt();
+        let steps = (dist / (self.cell_size * 0.5)).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            if let Some((gx, gy)) = self.world_to_grid_checked(from_x + dx * t, from_y + dy * t) {
+                if let Some(cell) = self.get_cell_mut(gx, gy) {
+                    cell.terrain_type = TerrainType::Trench;
+                    cell.facing = Some(facing);
+                }
+            }
+        }
+    }
+
+    /// Check whether there is an unobstructed line of sight between two points.
+    ///
+    /// Ray-marches the cells between the endpoints at half-cell intervals.
+    /// The sight line runs from eye height above the observer's ground to eye
+    /// height above the target's ground; it is blocked by any intermediate cell
+    /// whose terrain blocks LOS (e.g. forest) or whose ground rises above the
+    /// line. The observer's and target's own cells never block, so squads at a
+    /// wood's edge can see out. Samples outside the grid are treated as open.
+    pub fn has_line_of_sight(&self, from_x: f32, from_y: f32, to_x: f32, to_y: f32) -> bool {
+        let dx = to_x - from_x;
+        let dy = to_y - from_y;
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist <= self.cell_size {
+            return true;
+        }
+
+        let start_cell = self.world_to_grid_checked(from_x, from_y);
+        let end_cell = self.world_to_grid_checked(to_x, to_y);
+        let start_h = self.get_height_at(from_x, from_y) + EYE_HEIGHT;
+        let end_h = self.get_height_at(to_x, to_y) + EYE_HEIGHT;
+
+        let steps = (dist / (self.cell_size * 0.5)).ceil() as usize;
+        for i in 1..steps {
+            let t = i as f32 / steps as f32;
+            let Some((gx, gy)) = self.world_to_grid_checked(from_x + dx * t, from_y + dy * t) else {
+                continue;
+            };
+            if Some((gx, gy)) == start_cell || Some((gx, gy)) == end_cell {
+                continue;
+            }
+            let cell = &self.cells[gy * self.width + gx];
+            if cell.terrain_type.blocks_los() {
+                return false;
+            }
+            let sight_h = start_h + (end_h - start_h) * t;
+            if cell.height > sight_h {
+                return false;
+            }
+        }
+        true
+    }
+
     /// Apply a crater/explosion to the terrain.
     pub fn apply_crater(&mut self, world_x: f32, world_y: f32, radius: f32, depth: f32) {
         let (cx, cy) = self.world_to_grid(world_x, world_y);
@@ -330,6 +592,7 @@ impl TerrainGrid {
                         // Convert terrain to crater if heavily damaged
                         if cell.damage > 2.0 {
                             cell.terrain_type = TerrainType::Crater;
+                            cell.facing = None;
                         } else if cell.damage > 1.0 && cell.terrain_type == TerrainType::Forest {
                             cell.terrain_type = TerrainType::Rubble;
                         } else if cell.damage > 0.5 && cell.terrain_type == TerrainType::Open {
@@ -373,6 +636,12 @@ impl TerrainGrid {
         }
     }
 
+    /// Whether a world position lies within the grid.
+    pub fn contains(&self, world_x: f32, world_y: f32) -> bool {
+        let (min_x, min_y, max_x, max_y) = self.get_bounds();
+        world_x >= min_x && world_x < max_x && world_y >= min_y && world_y < max_y
+    }
+
     /// Get world bounds.
     pub fn get_bounds(&self) -> (f32, f32, f32, f32) {
         let min_x = self.origin_x;
@@ -457,10 +726,70 @@ mod tests {
         assert!(TerrainType::Road.movement_multiplier() > 1.0);
     }
 
+    #[test]
+    fn test_line_of_sight_blocked_by_forest_and_ridge() {
+        let mut grid = TerrainGrid::new(50, 50, 2.0);
+        assert!(grid.has_line_of_sight(-20.0, 0.0, 20.0, 0.0));
+
+        // A forest strip between the two points blocks sight
+        let (gx, gy) = grid.world_to_grid(0.0, 0.0);
+        grid.get_cell_mut(gx, gy).unwrap().terrain_type = TerrainType::Forest;
+        assert!(!grid.has_line_of_sight(-20.0, 0.0, 20.0, 0.0));
+        assert!(grid.has_line_of_sight(-20.0, 10.0, 20.0, 10.0));
+
+        // A ridge taller than eye height blocks sight
+        let (gx, gy) = grid.world_to_grid(0.0, 10.0);
+        grid.get_cell_mut(gx, gy).unwrap().height = 3.0;
+        assert!(!grid.has_line_of_sight(-20.0, 10.0, 20.0, 10.0));
+    }
+
     #[test]
     fn test_cover_values() {
         assert_eq!(TerrainType::Open.cover_value(), 0.0);
         assert!(TerrainType::Crater.cover_value() > 0.0);
         assert!(TerrainType::Trench.cover_value() > TerrainType::Crater.cover_value());
     }
+
+    #[test]
+    fn test_elevation_modifiers_and_dead_ground() {
+        assert_eq!(elevation_range_factor(0.0), 1.0);
+        assert_eq!(elevation_range_factor(-5.0), 1.0);
+        assert!(elevation_range_factor(4.0) > 1.0);
+        assert!(elevation_accuracy_factor(4.0) > 1.0);
+        assert!(elevation_accuracy_factor(-4.0) < 1.0);
+        assert!(spotting_range_factor(6.0) > spotting_range_factor(0.0));
+
+        let mut grid = TerrainGrid::new(100, 50, 2.0);
+        grid.add_hill(0.0, 0.0, 20.0, 6.0);
+        assert!((grid.get_height_at(0.0, 0.0) - 6.0).abs() < 0.1);
+        assert!(grid.get_height_at(30.0, 0.0) == 0.0);
+
+        // Squads on the reverse slope are masked from the far side of the crest
+        assert!(grid.is_dead_ground(-60.0, 0.0, 12.0, 0.0));
+        assert!(grid.get_cover_from(-60.0, 0.0, 12.0, 0.0) >= DEAD_GROUND_COVER);
+        // ...but not from the crest itself, nor on flat ground
+        assert!(!grid.is_dead_ground(0.0, 0.0, 12.0, 0.0));
+        assert!(!grid.is_dead_ground(-60.0, 20.0, 60.0, 20.0));
+    }
+
+    #[test]
+    fn test_trench_cover_is_directional() {
+        let mut grid = TerrainGrid::new(50, 50, 2.0);
+        // North-south trench line at x = 0, parapet facing east (+x)
+        grid.dig_trench(0.0, -10.0, 0.0, 10.0, 0.0);
+
+        let trench = TerrainType::Trench.cover_value();
+        let frontal = grid.get_cover_from(30.0, 0.0, 0.0, 0.0);
+        let flank = grid.get_cover_from(0.0, 30.0, 0.0, 0.0);
+        let rear = grid.get_cover_from(-30.0, 0.0, 0.0, 0.0);
+
+        assert_eq!(frontal, trench);
+        assert!(flank < frontal * 0.5, "Flanking fire should bypass the parapet");
+        assert!(rear <= flank);
+
+        // A squad lying just behind the trench line is covered by it
+        let behind = grid.get_cover_from(30.0, 0.0, -2.0, 0.0);
+        assert_eq!(behind, trench);
+        assert_eq!(grid.get_cover_from(-30.0, 0.0, -2.0, 0.0), 0.0);
+    }
 }
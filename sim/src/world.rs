@@ -4,6 +4,11 @@
 //! that can be sent to Godot for visualization.
 
 use crate::components::*;
+use crate::systems::command::{CommandGroup, CommandLevel, CommandObjective, CommandParent};
+use crate::systems::cover::InCover;
+use crate::systems::retreat::{Fallback, RallyPoint};
+use crate::systems::terrain_damage::TerrainDamageBuffer;
+use crate::systems::trigger::{OrderTrigger, ScheduledOrders};
 use crate::terrain::Crater;
 use bevy_ecs::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -23,6 +28,120 @@ pub struct SquadSnapshot {
     pub morale: f32,
     pub suppression: f32,
     pub order: String,
+    /// Cover value (0.0 - 1.0) at the squad's position.
+    #[serde(default)]
+    pub cover: f32,
+    /// Fatigue (0.0 = fresh, 1.0 = spent).
+    #[serde(default)]
+    pub fatigue: f32,
+    /// Whether the squad has surrendered.
+    #[serde(default)]
+    pub surrendered: bool,
+    /// Enemy squads finished off by this squad's fire.
+    #[serde(default)]
+    pub kills: u32,
+    /// Direct-fire damage dealt by this squad.
+    #[serde(default)]
+    pub damage_dealt: f32,
+    /// Destructible ID of the building the squad occupies, if any.
+    #[serde(default)]
+    pub garrison: Option<u32>,
+    /// Direction the squad faces (radians, 0 = +x).
+    #[serde(default)]
+    pub facing: f32,
+    /// Orders queued after the current one, next first.
+    #[serde(default)]
+    pub queue: Vec<QueuedOrderSnapshot>,
+    /// ID of the platoon or company the squad belongs to, if any.
+    #[serde(default)]
+    pub parent: Option<u32>,
+    /// Point a retreating squad is falling back to.
+    #[serde(default)]
+    pub fallback: Option<(f32, f32)>,
+    /// Rules of engagement, e.g. `HoldFire` or `EngageTarget(7)`.
+    #[serde(default)]
+    pub fire_stance: String,
+    /// Orders waiting on a trigger, in the order they were given.
+    #[serde(default)]
+    pub scheduled: Vec<ScheduledOrderSnapshot>,
+}
+
+/// Snapshot of a queued order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedOrderSnapshot {
+    /// Same format as `SquadSnapshot::order`.
+    pub order: String,
+    /// Facing to take once the order completes (radians).
+    pub facing: Option<f32>,
+}
+
+/// Snapshot of a scheduled order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledOrderSnapshot {
+    /// Trigger that releases the order, e.g. `AtTick(300)`.
+    pub trigger: String,
+    /// Same format as `SquadSnapshot::order`.
+    pub order: String,
+}
+
+/// Format an order trigger for snapshots, e.g. `BarrageLifted(2)`.
+pub fn trigger_label(trigger: &OrderTrigger) -> String {
+    match trigger {
+        OrderTrigger::AtTick(tick) => format!("AtTick({})", tick),
+        OrderTrigger::Arrived { squad } => format!("Arrived({})", squad),
+        OrderTrigger::BarrageLifted { barrage } => format!("BarrageLifted({})", barrage),
+        OrderTrigger::SectorCaptured { sector, faction } => format!("SectorCaptured({},{},{:?})", sector.0, sector.1, faction),
+        OrderTrigger::MoraleBelow { squad, threshold } => format!("MoraleBelow({},{:.2})", squad, threshold),
+    }
+}
+
+/// Format an order for snapshots, e.g. `MoveTo(10.0,20.0)`.
+pub fn order_label(order: &Order) -> String {
+    match order {
+        Order::Hold => "Hold".to_string(),
+        Order::MoveTo { x, y } => format!("MoveTo({:.1},{:.1})", x, y),
+        Order::AttackMove { x, y } => format!("AttackMove({:.1},{:.1})", x, y),
+        Order::Retreat => "Retreat".to_string(),
+        Order::SuppressArea { x, y, radius } => format!("SuppressArea({:.1},{:.1},{:.1})", x, y, radius),
+        Order::Garrison { building, .. } => format!("Garrison({})", building),
+        Order::Ungarrison => "Ungarrison".to_string(),
+        Order::DefendArea { x, y, radius } => format!("DefendArea({:.1},{:.1},{:.1})", x, y, radius),
+        Order::Patrol { waypoints } => format!("Patrol({})", waypoints.len()),
+    }
+}
+
+/// Snapshot of a platoon or company.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandGroupSnapshot {
+    pub id: u32,
+    /// "Platoon" or "Company".
+    pub level: String,
+    pub faction: String,
+    /// ID of the parent group, if any.
+    pub parent: Option<u32>,
+    /// Remaining health of the group's squads (0.0 - 1.0).
+    pub strength: f32,
+    /// Point the group's AI squads advance on.
+    pub objective: Option<(f32, f32)>,
+}
+
+/// Snapshot of a rally point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RallyPointSnapshot {
+    pub id: u32,
+    pub faction: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Format a fire stance for snapshots, e.g. `EngageTarget(7)`.
+pub fn fire_stance_label(stance: &FireStance) -> String {
+    match stance {
+        FireStance::FireAtWill => "FireAtWill".to_string(),
+        FireStance::ReturnFire => "ReturnFire".to_string(),
+        FireStance::HoldFire => "HoldFire".to_string(),
+        FireStance::EngageTarget(id) => format!("EngageTarget({})", id),
+    }
 }
 
 /// Snapshot of a terrain damage event.
@@ -34,6 +153,16 @@ pub struct TerrainDamageSnapshot {
     pub depth: f32,
 }
 
+/// Snapshot of a smoke cloud.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmokeSnapshot {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    /// Opacity (1.0 = thick, 0.0 = gone).
+    pub density: f32,
+}
+
 /// Snapshot of a destructible object.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DestructibleSnapshot {
@@ -63,6 +192,15 @@ pub struct Snapshot {
     pub new_craters: Vec<Crater>,
     /// Whether terrain has been modified this tick.
     pub terrain_dirty: bool,
+    /// Active smoke clouds.
+    #[serde(default)]
+    pub smoke: Vec<SmokeSnapshot>,
+    /// Platoons and companies.
+    #[serde(default)]
+    pub command_groups: Vec<CommandGroupSnapshot>,
+    /// Rally points of both factions.
+    #[serde(default)]
+    pub rally_points: Vec<RallyPointSnapshot>,
 }
 
 impl Snapshot {
@@ -81,23 +219,35 @@ impl Snapshot {
             &Morale,
             &Suppression,
             &Order,
+            Option<&InCover>,
+            Option<&Fatigue>,
+            Has<Surrendered>,
+            Option<&CombatStats>,
+            (Option<&Garrisoned>, Option<&Facing>, Option<&OrderQueue>, Option<&CommandParent>, Option<&Fallback>, Option<&FireStance>, Option<&ScheduledOrders>),
         )>();
 
-        for (squad_id, faction, pos, vel, health, stats, morale, suppression, order) in
-            query.iter(world)
+        for (
+            squad_id,
+            faction,
+            pos,
+            vel,
+            health,
+            stats,
+            morale,
+            suppression,
+            order,
+            in_cover,
+            fatigue,
+            surrendered,
+            combat_stats,
+            (garrisoned, facing, queue, parent, fallback, stance, scheduled),
+        ) in query.iter(world)
         {
             let faction_str = match faction {
                 Faction::Blue => "Blue",
                 Faction::Red => "Red",
             };
 
-            let order_str = match order {
-                Order::Hold => "Hold".to_string(),
-                Order::MoveTo { x, y } => format!("MoveTo({:.1},{:.1})", x, y),
-                Order::AttackMove { x, y } => format!("AttackMove({:.1},{:.1})", x, y),
-                Order::Retreat => "Retreat".to_string(),
-            };
-
             squads.push(SquadSnapshot {
                 id: squad_id.0,
                 faction: faction_str.to_string(),
@@ -110,14 +260,44 @@ impl Snapshot {
                 size: stats.size,
                 morale: morale.value,
                 suppression: suppression.value,
-                order: order_str,
+                order: order_label(order),
+                cover: in_cover.map(|c| c.value).unwrap_or(0.0),
+                fatigue: fatigue.map(|f| f.value).unwrap_or(0.0),
+                surrendered,
+                kills: combat_stats.map(|s| s.kills).unwrap_or(0),
+                damage_dealt: combat_stats.map(|s| s.damage_dealt).unwrap_or(0.0),
+                garrison: garrisoned
+                    .and_then(|g| world.get::<DestructibleId>(g.building))
+                    .map(|id| id.0),
+                facing: facing.map(|f| f.0).unwrap_or(0.0),
+                queue: queue
+                    .map(|q| {
+                        q.pending.iter()
+                            .map(|queued| QueuedOrderSnapshot { order: order_label(&queued.order), facing: queued.facing })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                parent: parent.map(|p| p.0),
+                fallback: fallback.map(|f| (f.x, f.y)),
+                fire_stance: fire_stance_label(&stance.copied().unwrap_or_default()),
+                scheduled: scheduled
+                    .map(|s| {
+                        s.0.iter()
+                            .map(|s| ScheduledOrderSnapshot { trigger: trigger_label(&s.trigger), order: order_label(&s.order) })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
             });
         }
 
-        // Query terrain damage events
+        // Terrain damage events: already processed (buffered) and still pending
         let mut terrain_damage = Vec::new();
+        let processed: Vec<TerrainDamageEvent> = world
+            .get_resource::<TerrainDamageBuffer>()
+            .map(|b| b.events.clone())
+            .unwrap_or_default();
         let mut damage_query = world.query::<&TerrainDamageEvent>();
-        for event in damage_query.iter(world) {
+        for event in processed.iter().chain(damage_query.iter(world)) {
             terrain_damage.push(TerrainDamageSnapshot {
                 x: event.x,
                 y: event.y,
@@ -158,6 +338,49 @@ impl Snapshot {
             });
         }
 
+        let mut smoke_query = world.query::<(&Position, &SmokeCloud)>();
+        let smoke = smoke_query.iter(world)
+            .map(|(pos, cloud)| SmokeSnapshot {
+                x: pos.x,
+                y: pos.y,
+                radius: cloud.radius,
+                density: cloud.density(),
+            })
+            .collect();
+
+        let mut group_query = world.query::<(&CommandGroup, Option<&CommandParent>, Option<&CommandObjective>)>();
+        let mut command_groups: Vec<CommandGroupSnapshot> = group_query.iter(world)
+            .map(|(group, parent, objective)| CommandGroupSnapshot {
+                id: group.id,
+                level: match group.level {
+                    CommandLevel::Platoon => "Platoon",
+                    CommandLevel::Company => "Company",
+                }.to_string(),
+                faction: match group.faction {
+                    Faction::Blue => "Blue",
+                    Faction::Red => "Red",
+                }.to_string(),
+                parent: parent.map(|p| p.0),
+                strength: group.strength,
+                objective: objective.map(|o| (o.x, o.y)),
+            })
+            .collect();
+        command_groups.sort_by_key(|g| g.id);
+
+        let mut rally_query = world.query::<(&RallyPoint, &Position)>();
+        let mut rally_points: Vec<RallyPointSnapshot> = rally_query.iter(world)
+            .map(|(rally, pos)| RallyPointSnapshot {
+                id: rally.id,
+                faction: match rally.faction {
+                    Faction::Blue => "Blue",
+                    Faction::Red => "Red",
+                }.to_string(),
+                x: pos.x,
+                y: pos.y,
+            })
+            .collect();
+        rally_points.sort_by_key(|r| r.id);
+
         Self {
             tick,
             time,
@@ -166,6 +389,9 @@ impl Snapshot {
             terrain_damage,
             new_craters: Vec::new(),
             terrain_dirty: false,
+            smoke,
+            command_groups,
+            rally_points,
         }
     }
 